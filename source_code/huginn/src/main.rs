@@ -20,53 +20,89 @@ use std::fs::{File,OpenOptions};
 use std::io::prelude::*;
 // Path lib
 use std::path::Path;
-// Process lib
-use std::process;
+use std::thread;
+use std::time::Duration;
 
-use libstream::{Colors,Stream};
-use libcommand;
+use libstream::{to_ravnkey, Colors, RavnSchema, RavnValue, SchemaType, Severity};
+use libcommand::Service;
 
 // Version constant
 const HVERSION: &str = "0.1.1";
 
 fn main() {
     let color = Colors::new();
-    let mut threads_services = Vec::new();
+    // Services alive and if they are restarted when they fail
+    let mut running: Vec<(Service, bool)> = Vec::new();
 
     println!("Huginn sysinit version {HVERSION}");
 
     // Create huginn path configuration if do not exists
-    if !( Path::new("/etc/huginn/").try_exists().unwrap() ) {
+    if !( Path::new("/etc/huginn/").try_exists().unwrap_or(false) ) {
         match std::fs::create_dir_all("/etc/huginn/".to_string()){
             Ok(_d) => println!("[INFO]\tCreated huginn path configuration; /etc/huginn"),
             Err(e) =>  eprintln!("[ERR]\tError creating huginn path coniguration (/etc/huginn); {e}"),
         };
 
-    } else if !( Path::new("/etc/huginn/services").try_exists().unwrap() ) {
+    } else if !( Path::new("/etc/huginn/services").try_exists().unwrap_or(false) ) {
         // Create service file is not exists
         match OpenOptions::new().create(true).append(true).open( "/etc/huginn/services" ) {
     		Ok(_d) => println!("[INFO]\tCreated huginn file configuration; /etc/huginn/services"),
-    		Err(e) => {
-    			eprintln!("[ERR]\tError creating /etc/huginn/services file: {e}");
-                process::exit(1);
-    		},
+    		Err(e) => eprintln!("[ERR]\tError creating /etc/huginn/services file: {e}"),
     	};
     } else {}
 
     // Takes service's name and binary
-    // "readkey" returns a HashMap with <Service_name, binary_arguments>
-    let hservices: HashMap<String,HashMap<String, String>> = {
+    // The services are kept in the same order of the file, because is the order to start them.
+    // Each service's data can be "binary = [path]" lines or "binary { [path] }" keys.
+    let hservices: Vec<(String,HashMap<String, String>)> = {
+        // Huginn is the init, without the file or with a wrong one it goes on without services
         let mut fservices = String::new();
-        File::open("/etc/huginn/services").expect("Error opening configuration service.").read_to_string(&mut fservices).expect("Error reading configuration service.");
-        let buff = fservices.readkey();
-        let mut vreturn: HashMap<String,HashMap<String, String>> = Default::default();
-        for (serv,data) in buff{
-            let buff2 = data.readconfig();
-            vreturn.insert(serv,buff2);
+        if let Err(e) = File::open("/etc/huginn/services").and_then(|mut e| e.read_to_string(&mut fservices)) {
+            eprintln!("{}[ERR]\tError reading /etc/huginn/services, no services are started; {e}{}", color.red, color.reset);
+            fservices.clear();
+        }
+        let schema = RavnSchema::new().others(SchemaType::Block(
+            RavnSchema::new()
+                .required("binary", SchemaType::Path)
+                .optional("arguments", SchemaType::String)
+                .with_default("restart", SchemaType::Bool, "false"),
+        ));
+        // Services with errors, as unknown keys (like "binay") or without binary, are not started; the rest are
+        let report = schema.check(&fservices);
+        for diagnostic in &report.diagnostics {
+            match diagnostic.severity {
+                Severity::Error => eprintln!("{}[ERR]\t/etc/huginn/services; {diagnostic}{}", color.red, color.reset),
+                Severity::Warning => eprintln!("{}[WARN]\t/etc/huginn/services; {diagnostic}{}", color.yellow, color.reset),
+            }
+        }
+        let valid = report.valid_entries();
+        for (serv,data) in report.value.entries().unwrap_or_default() {
+            if !valid.iter().any(|e| std::ptr::eq(e.1, data)) {
+                eprintln!("{}[ERR]\t{serv} is not started, it has errors in /etc/huginn/services{}", color.red, color.reset);
+            }
+        }
+        let mut vreturn: Vec<(String,HashMap<String, String>)> = Vec::new();
+        for (serv,data) in valid {
+            let buff2 = data.entries().unwrap_or_default().iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_text()?.to_string())))
+                .collect();
+            vreturn.push((serv.to_string(),buff2));
         }
         vreturn
     };
     println!("Starting services (/etc/huginn/services)");
+    // Services loaded, as ravnkey
+    let mut loaded = RavnValue::Block(Vec::new());
+    for (serv,data) in &hservices {
+        let mut service = RavnValue::Block(Vec::new());
+        for key in ["binary", "arguments", "restart"] {
+            if let Some(d) = data.get(key) {
+                service.push(key, d.trim().into());
+            }
+        }
+        loaded.push(serv.trim(), service);
+    }
+    print!("{}", to_ravnkey(&loaded));
     for (serv,data) in &hservices {
         let serv = serv.trim().to_string();
         let binary = match data.get("binary"){
@@ -81,18 +117,37 @@ fn main() {
             None => "".to_string(),
         };
 
-        let command = libcommand::thread_command(serv, binary, arguments);
-        threads_services.push(command);
-    }
+        let restart = data.get("restart").is_some_and(|e| e == "true");
 
-    for handles in threads_services {
-        match handles.join(){
-            Ok(_d) => (),
-            Err(_e) => {
-                eprintln!("{}[ERR]Failing awaiting for thread!{}", color.red, color.reset);
-                ()
-            },
-        };
+        // Services which can not start are shown by "start" and not kept
+        if let Ok(d) = Service::start(&serv, &binary, &arguments) {
+            running.push((d, restart));
+        }
     }
 
+    // Each service is waited when it ends, so none is left as a zombie. The ones with "restart" which end
+    // with an error are started again after a second.
+    while !running.is_empty() {
+        let mut i = 0;
+        while i < running.len() {
+            let (service, restart) = &mut running[i];
+            match service.child().wait_timeout(Duration::from_millis(100)) {
+                Ok(None) => {
+                    i += 1;
+                    continue;
+                },
+                Ok(Some(_d)) => (),
+                Err(e) => eprintln!("{}[ERR]\t{} can not be waited; {e}{}", color.red, service.name(), color.reset),
+            }
+            let failed = !service.wait().is_ok_and(|e| e.success());
+            if *restart && failed {
+                thread::sleep(Duration::from_secs(1));
+                if service.restart(Duration::from_secs(5)).is_ok() {
+                    i += 1;
+                    continue;
+                }
+            }
+            running.remove(i);
+        }
+    }
 }
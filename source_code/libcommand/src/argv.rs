@@ -0,0 +1,126 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Command lines split into arguments, as the shells do but without expansions.
+//!
+//! Spaces and tabs separate the arguments. Inside single quotes everything is literal; inside double quotes a
+//! backslash escapes only '"', '\' and '$'; outside quotes a backslash escapes any character. Quotes can be
+//! joined to other text in the same argument and an empty pair of quotes is an empty argument.
+//!
+//! ```text
+//! -c "echo 'a b'" x\ y ""  =>  ["-c", "echo 'a b'", "x y", ""]
+//! ```
+
+use std::io;
+
+/// Arguments of a command line. An unclosed quote or a backslash at the end is an "InvalidInput" error.
+pub fn parse_argv(line: &str) -> io::Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    // An argument exists after a quote even if it is empty
+    let mut started = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if started {
+                    arguments.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            '\'' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(d) => current.push(d),
+                        None => return Err(invalid("unclosed single quote")),
+                    }
+                }
+            }
+            '"' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(d) if d == '"' || d == '\\' || d == '$' => current.push(d),
+                            Some(d) => {
+                                current.push('\\');
+                                current.push(d);
+                            }
+                            None => return Err(invalid("unclosed double quote")),
+                        },
+                        Some(d) => current.push(d),
+                        None => return Err(invalid("unclosed double quote")),
+                    }
+                }
+            }
+            '\\' => {
+                started = true;
+                match chars.next() {
+                    Some(d) => current.push(d),
+                    None => return Err(invalid("backslash at the end of the line")),
+                }
+            }
+            _ => {
+                started = true;
+                current.push(c);
+            }
+        }
+    }
+    if started {
+        arguments.push(current);
+    }
+    Ok(arguments)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces() {
+        assert_eq!(parse_argv("  -l \t-a\n/tmp  ").unwrap(), ["-l", "-a", "/tmp"]);
+        assert!(parse_argv("").unwrap().is_empty());
+        assert!(parse_argv(" \t ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(parse_argv(r#"-c "echo 'a b'" x\ y """#).unwrap(), ["-c", "echo 'a b'", "x y", ""]);
+        assert_eq!(parse_argv(r#"'a "b" \c $d'"#).unwrap(), [r#"a "b" \c $d"#]);
+        assert_eq!(parse_argv(r#""\" \\ \$ \n""#).unwrap(), [r#"" \ $ \n"#]);
+        assert_eq!(parse_argv(r#"--name="a b"'c'd"#).unwrap(), ["--name=a bcd"]);
+        assert_eq!(parse_argv("'' x").unwrap(), ["", "x"]);
+    }
+
+    #[test]
+    fn backslash() {
+        assert_eq!(parse_argv(r"a\\b \'c \a").unwrap(), [r"a\b", "'c", "a"]);
+    }
+
+    #[test]
+    fn errors() {
+        for line in ["'abc", r#""abc"#, r#""abc\""#, r"abc\"] {
+            let e = parse_argv(line).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{line}");
+        }
+    }
+}
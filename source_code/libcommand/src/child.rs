@@ -0,0 +1,483 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Running processes.
+//!
+//! "Spawn" sets the binary, the arguments and where the standard input and outputs go, "start" returns a
+//! "Child" alive; with its pid, its outputs to read while it runs, wait (with or without timeout), signals and
+//! the way it ended.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::raw::c_int;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, ChildStderr, ChildStdin, ChildStdout, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+extern "C" {
+    // The Rust std only sends SIGKILL, the C library is always linked by std.
+    fn kill(pid: c_int, signal: c_int) -> c_int;
+}
+
+pub const SIGHUP: i32 = 1;
+pub const SIGINT: i32 = 2;
+pub const SIGKILL: i32 = 9;
+pub const SIGTERM: i32 = 15;
+pub const SIGCONT: i32 = 18;
+pub const SIGSTOP: i32 = 19;
+
+/// Where a standard input or output of the child goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Io {
+    /// The same of this process; the terminal for interactive programs.
+    Inherit,
+    /// "/dev/null".
+    Null,
+    /// A pipe, read or written by this process.
+    Piped,
+}
+
+impl Io {
+    fn stdio(self) -> Stdio {
+        match self {
+            Io::Inherit => Stdio::inherit(),
+            Io::Null => Stdio::null(),
+            Io::Piped => Stdio::piped(),
+        }
+    }
+}
+
+/// How a process ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exit {
+    /// It returned this code, 0 is success.
+    Code(i32),
+    /// It was killed by this signal.
+    Signal(i32),
+}
+
+impl Exit {
+    pub fn success(&self) -> bool {
+        *self == Exit::Code(0)
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Exit::Code(d) => Some(*d),
+            Exit::Signal(_d) => None,
+        }
+    }
+
+    pub fn signal(&self) -> Option<i32> {
+        match self {
+            Exit::Code(_d) => None,
+            Exit::Signal(d) => Some(*d),
+        }
+    }
+}
+
+impl From<ExitStatus> for Exit {
+    fn from(status: ExitStatus) -> Exit {
+        match (status.code(), status.signal()) {
+            (Some(d), _) => Exit::Code(d),
+            (None, Some(d)) => Exit::Signal(d),
+            // Stopped or continued processes are not ended, "wait" does not return them
+            (None, None) => Exit::Code(-1),
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exit::Code(d) => write!(f, "exit code {d}"),
+            Exit::Signal(d) => write!(f, "signal {d} ({})", signal_name(*d)),
+        }
+    }
+}
+
+/// Name of the common Linux signals.
+pub fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        SIGHUP => "SIGHUP",
+        SIGINT => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        SIGKILL => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => "SIGSEGV",
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        SIGTERM => "SIGTERM",
+        SIGCONT => "SIGCONT",
+        SIGSTOP => "SIGSTOP",
+        _ => "unknown",
+    }
+}
+
+/// Line written by a child, from its standard output or error.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Process to start. Standard input, output and error are inherited by default.
+pub struct Spawn {
+    name: String,
+    binary: PathBuf,
+    arguments: Vec<String>,
+    stdin: Io,
+    stdout: Io,
+    stderr: Io,
+}
+
+impl Spawn {
+    /// "name" is only to identify it; a service name or the command.
+    pub fn new<P: AsRef<Path>>(name: &str, binary: P) -> Spawn {
+        Spawn {
+            name: name.to_string(),
+            binary: binary.as_ref().to_path_buf(),
+            arguments: Vec::new(),
+            stdin: Io::Inherit,
+            stdout: Io::Inherit,
+            stderr: Io::Inherit,
+        }
+    }
+
+    /// Each one is a different argument; use "parse_argv" for a command line.
+    pub fn args<S: AsRef<str>>(mut self, arguments: &[S]) -> Spawn {
+        self.arguments.extend(arguments.iter().map(|e| e.as_ref().to_string()));
+        self
+    }
+
+    pub fn stdin(mut self, io: Io) -> Spawn {
+        self.stdin = io;
+        self
+    }
+
+    pub fn stdout(mut self, io: Io) -> Spawn {
+        self.stdout = io;
+        self
+    }
+
+    pub fn stderr(mut self, io: Io) -> Spawn {
+        self.stderr = io;
+        self
+    }
+
+    pub fn start(self) -> io::Result<Child> {
+        let process = process::Command::new(&self.binary)
+            .args(&self.arguments)
+            .stdin(self.stdin.stdio())
+            .stdout(self.stdout.stdio())
+            .stderr(self.stderr.stdio())
+            .spawn()?;
+        let mut argv = vec![self.binary.display().to_string()];
+        argv.extend(self.arguments);
+        Ok(Child {
+            name: self.name,
+            pid: process.id(),
+            argv,
+            process,
+            exit: None,
+        })
+    }
+}
+
+/// Process started by "Spawn". It is not killed when dropped; wait for it or kill it.
+pub struct Child {
+    name: String,
+    pid: u32,
+    argv: Vec<String>,
+    process: process::Child,
+    exit: Option<Exit>,
+}
+
+impl Child {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Binary and arguments.
+    pub fn argv(&self) -> &[String] {
+        &self.argv
+    }
+
+    /// How it ended, if it was already waited.
+    pub fn exit(&self) -> Option<Exit> {
+        self.exit
+    }
+
+    /// Standard input when it is piped; "close_stdin" sends the end of file.
+    pub fn stdin(&mut self) -> Option<&mut ChildStdin> {
+        self.process.stdin.as_mut()
+    }
+
+    pub fn close_stdin(&mut self) {
+        self.process.stdin = None;
+    }
+
+    /// Standard output when it is piped, to read it while the child runs.
+    pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.process.stdout.take()
+    }
+
+    /// Standard error when it is piped, to read it while the child runs.
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.process.stderr.take()
+    }
+
+    /// Lines of the piped outputs as they are written, read by one thread for each output.
+    /// The channel is closed when the child closes both outputs, usually when it ends.
+    pub fn output_lines(&mut self) -> Receiver<OutputLine> {
+        let (sender, receiver) = mpsc::channel();
+        if let Some(d) = self.take_stdout() {
+            let sender = sender.clone();
+            thread::spawn(move || send_lines(d, |e| sender.send(OutputLine::Stdout(e)).is_ok()));
+        }
+        if let Some(d) = self.take_stderr() {
+            thread::spawn(move || send_lines(d, |e| sender.send(OutputLine::Stderr(e)).is_ok()));
+        }
+        receiver
+    }
+
+    /// Without blocking; None if it is still running.
+    pub fn try_wait(&mut self) -> io::Result<Option<Exit>> {
+        if self.exit.is_none() {
+            self.exit = self.process.try_wait()?.map(Exit::from);
+        }
+        Ok(self.exit)
+    }
+
+    /// Waits until it ends. The piped standard input is closed before, so the child does not wait for it.
+    pub fn wait(&mut self) -> io::Result<Exit> {
+        if let Some(d) = self.exit {
+            return Ok(d);
+        }
+        self.close_stdin();
+        let exit = Exit::from(self.process.wait()?);
+        self.exit = Some(exit);
+        Ok(exit)
+    }
+
+    /// Waits at most "timeout"; None if it is still running then.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<Option<Exit>> {
+        let start = Instant::now();
+        let mut delay = Duration::from_millis(1);
+        loop {
+            if let Some(d) = self.try_wait()? {
+                return Ok(Some(d));
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(None);
+            }
+            // Short checks at first, for fast commands, up to each 50 ms
+            thread::sleep(delay.min(timeout - elapsed));
+            delay = (delay * 2).min(Duration::from_millis(50));
+        }
+    }
+
+    /// Sends a signal; SIGTERM to ask it to end, SIGSTOP and SIGCONT to pause it. Nothing is sent to an
+    /// already waited child, its pid can be of other process now.
+    pub fn signal(&mut self, signal: i32) -> io::Result<()> {
+        if self.exit.is_some() {
+            return Ok(());
+        }
+        if unsafe { kill(self.pid as c_int, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// SIGKILL, the child can not ignore it.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.signal(SIGKILL)
+    }
+
+    /// Asks it to end with SIGTERM, and after "grace" without ending it is killed.
+    pub fn terminate(&mut self, grace: Duration) -> io::Result<Exit> {
+        self.signal(SIGTERM)?;
+        match self.wait_timeout(grace)? {
+            Some(d) => Ok(d),
+            None => {
+                self.kill()?;
+                self.wait()
+            }
+        }
+    }
+
+    /// Writes "input" to the piped standard input, closes it and waits collecting the piped outputs.
+    pub fn wait_with_output(mut self, input: &[u8]) -> io::Result<(Exit, Vec<u8>, Vec<u8>)> {
+        // The outputs are read by other threads while writing, so a big output does not block the input
+        let stdout = self.take_stdout().map(|e| thread::spawn(move || read_all(e)));
+        let stderr = self.take_stderr().map(|e| thread::spawn(move || read_all(e)));
+        if let Some(d) = self.stdin() {
+            match d.write_all(input) {
+                Ok(_d) => (),
+                // The child ended without reading it all
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+                Err(e) => return Err(e),
+            }
+        }
+        let exit = self.wait()?;
+        let join = |handle: Option<thread::JoinHandle<io::Result<Vec<u8>>>>| match handle {
+            Some(d) => d.join().unwrap_or_else(|_e| Err(io::Error::other("output reader failed"))),
+            None => Ok(Vec::new()),
+        };
+        Ok((exit, join(stdout)?, join(stderr)?))
+    }
+}
+
+fn read_all<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+// Lines without the end of line, invalid UTF-8 is replaced; stops when "send" fails
+fn send_lines<R: Read, F: Fn(String) -> bool>(reader: R, send: F) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Err(_e) => break,
+            Ok(_d) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                }
+                if !send(String::from_utf8_lossy(&line).into_owned()) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Spawn {
+        Spawn::new("test", "/bin/sh").args(&["-c", script])
+    }
+
+    #[test]
+    fn exit_code() {
+        let mut child = sh("exit 3").start().unwrap();
+        assert_eq!(child.wait().unwrap(), Exit::Code(3));
+        assert_eq!(child.exit(), Some(Exit::Code(3)));
+        // Already waited, the same exit and nothing is sent
+        assert_eq!(child.wait().unwrap(), Exit::Code(3));
+        assert!(child.kill().is_ok());
+    }
+
+    #[test]
+    fn exit_from_status() {
+        assert_eq!(Exit::from(ExitStatus::from_raw(0)), Exit::Code(0));
+        assert_eq!(Exit::from(ExitStatus::from_raw(3 << 8)), Exit::Code(3));
+        assert_eq!(Exit::from(ExitStatus::from_raw(SIGKILL)), Exit::Signal(SIGKILL));
+        assert!(Exit::Code(0).success());
+        assert!(!Exit::Code(1).success());
+        assert!(!Exit::Signal(SIGTERM).success());
+        assert_eq!(Exit::Code(3).code(), Some(3));
+        assert_eq!(Exit::Code(3).signal(), None);
+        assert_eq!(Exit::Signal(11).signal(), Some(11));
+        assert_eq!(Exit::Signal(11).code(), None);
+        assert_eq!(Exit::Code(3).to_string(), "exit code 3");
+        assert_eq!(Exit::Signal(11).to_string(), "signal 11 (SIGSEGV)");
+        assert_eq!(signal_name(64), "unknown");
+    }
+
+    #[test]
+    fn wait_timeout_and_kill() {
+        let mut child = Spawn::new("sleep", "sleep").args(&["5"]).start().unwrap();
+        let start = Instant::now();
+        assert_eq!(child.wait_timeout(Duration::from_millis(100)).unwrap(), None);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(child.try_wait().unwrap(), None);
+        child.kill().unwrap();
+        assert_eq!(child.wait().unwrap(), Exit::Signal(SIGKILL));
+    }
+
+    #[test]
+    fn wait_timeout_ended() {
+        let mut child = sh("exit 0").start().unwrap();
+        assert_eq!(child.wait_timeout(Duration::from_secs(5)).unwrap(), Some(Exit::Code(0)));
+    }
+
+    #[test]
+    fn terminate() {
+        let mut child = Spawn::new("sleep", "sleep").args(&["5"]).start().unwrap();
+        let exit = child.terminate(Duration::from_secs(5)).unwrap();
+        assert_eq!(exit, Exit::Signal(SIGTERM));
+        assert_eq!(exit.to_string(), "signal 15 (SIGTERM)");
+    }
+
+    #[test]
+    fn terminate_ignored() {
+        // SIGTERM is ignored, so it is killed after the grace time
+        let mut child = sh("trap '' TERM; sleep 5").start().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(child.terminate(Duration::from_millis(200)).unwrap(), Exit::Signal(SIGKILL));
+    }
+
+    #[test]
+    fn output_lines() {
+        let mut child = sh("echo a; echo b >&2; printf c").stdout(Io::Piped).stderr(Io::Piped).start().unwrap();
+        let mut lines: Vec<OutputLine> = child.output_lines().iter().collect();
+        assert_eq!(child.wait().unwrap(), Exit::Code(0));
+        // Stdout and stderr are read by different threads, their order is not known
+        lines.sort_by_key(|e| format!("{e:?}"));
+        assert_eq!(
+            lines,
+            [
+                OutputLine::Stderr("b".to_string()),
+                OutputLine::Stdout("a".to_string()),
+                OutputLine::Stdout("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_with_output() {
+        let child = sh("cat; echo err >&2; exit 2").stdin(Io::Piped).stdout(Io::Piped).stderr(Io::Piped).start().unwrap();
+        let (exit, stdout, stderr) = child.wait_with_output(b"input\n").unwrap();
+        assert_eq!(exit, Exit::Code(2));
+        assert_eq!(stdout, b"input\n");
+        assert_eq!(stderr, b"err\n");
+    }
+
+    #[test]
+    fn missing_binary() {
+        let e = Spawn::new("missing", "/nonexistent/binary").start().err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+}
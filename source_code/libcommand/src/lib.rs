@@ -13,167 +13,229 @@
 //! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023
 
 
-// Process crate
-use std::process::{self,ExitStatus,Stdio};
 // HashMap
 use std::collections::HashMap;
 
 // Thread and Sync libs
 use std::thread::{self,JoinHandle};
+use std::time::Duration;
 
 // Colors
 use libstream::Colors;
 
+mod argv;
+mod child;
+pub use argv::parse_argv;
+pub use child::{signal_name, Child, Exit, Io, OutputLine, Spawn, SIGCONT, SIGHUP, SIGINT, SIGKILL, SIGSTOP, SIGTERM};
 
+
+/// Service or command already ended.
 pub struct SService {
     sname: String,
     id: i64,
+    argv: Vec<String>,
     stdout: Vec<u8>,
     stderr: Vec<u8>,
-	exitstatus: ExitStatus,
+    exit: Exit,
 }
 
-// Mutex is used to allow only one thread to change the value at the same time
-// Arc is used to allow ownership between more that one thread.
-/////////////////////////////////////
-
-pub fn thread_command( name: String, binary: String, arguments: String) -> JoinHandle<Result<SService, String>>{
-		thread::spawn(move || {
-			let color = Colors::new();
-			let s_id: i64;
-	        let binn = binary.trim();
-
-	        // Execute the "binn" binary with the arguments collected in "arguments"
-	       // "Spawn" execute the command with the arguments. The child process is not lineal to this, will run in
-	        // 		another core
-	        let mut proc = process::Command::new( binn.trim() );
-	        // Arg method inserts the arguments in the Command struct, because of that we can iterate adding it
-
-	        // Stdout configure process' stdout
-	        // Stdio::piped connect parent and child processes
-	        // Stderr configure process' stderr
-	        // Stdio::piped connect parent and child processes
-	        proc.arg(arguments).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
-
-	        println!("{}[INFO]\tStarting; {name}{}", color.cyan, color.reset);
-	        // Spawn execute it
-	        let nproc = match proc.spawn(){
-	            Ok(d) => {
-	                s_id = d.id().into();
-	                println!("{}[OK]\tStarted {name}, id; {s_id}{}", color.green, color.reset);
-	                d
-	            },
-	            Err(e) => {
-	                eprintln!("{}[ERR]\t\tError executing {name}. Check if binary/command exists, you can try executing with absolute path.{}", color.red, color.reset);
-	                eprintln!("{}[ERR][INFO]\t{name} status; {e}{}", color.red, color.reset);
-					return Err("".to_string());
-	            },
-
-	        };
-	        // Drop function cleans from memory the variable and data
-	        drop(proc);
-
-
-	        // Wait_with_output method waits to command finishes and collect output, return Output struct
-	        let output = match nproc.wait_with_output() {
-	            Ok(d) => d,
-	            Err(_e) => {
-	                eprintln!("{}[ERR]\t\t{name} Error waiting for command and taking process' stdin stdout stderr{}", color.red, color.reset);
-					return Err("".to_string());
-	            }
-	        };
-
-			// Return this as Ok(SService)
-			let instance = SService {
-                sname: name,
-			    id: s_id,
-			    stdout: output.stdout,
-			    stderr: output.stderr,
-				exitstatus: output.status,
-			};
-	        Ok(instance)
-
-		})
+impl SService {
+    pub fn name(&self) -> &str {
+        &self.sname
+    }
+
+    /// Pid it had.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Binary and arguments.
+    pub fn argv(&self) -> &[String] {
+        &self.argv
+    }
+
+    /// Output collected; empty for services, their output is shown while they run.
+    pub fn stdout(&self) -> &[u8] {
+        &self.stdout
+    }
+
+    pub fn stderr(&self) -> &[u8] {
+        &self.stderr
+    }
+
+    pub fn exit(&self) -> Exit {
+        self.exit
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit.success()
+    }
 }
 
+/// Service alive, started by "Service::start". Each line of its output is shown with the service's name by a
+/// thread while it runs; the child is kept to wait for it, send it signals, stop it or restart it.
+pub struct Service {
+    name: String,
+    binary: String,
+    arguments: Vec<String>,
+    child: Child,
+    // Thread which shows the output, until the child closes it
+    output: Option<JoinHandle<()>>,
+}
 
-pub fn single_command( h_s_service:&mut HashMap<String,i64>, name: String, binary: String) -> Result<SService,String>{
-
-		let color = Colors::new();
-		let s_id: i64;
-
-        // Take the command, pass to "trim" for clean tabs, spaces and others, split it by spaces and then convert each to string
-        // collecting to strings.
-        let buff = binary.split(' ').map(|e| e.to_string()).collect::<Vec<String>>();
-        // Take the first, which is the binary to execute
-        let binn = &buff[0].clone();
-        let binn = binn.trim();
-        let mut arguments: Vec<String> = Vec::new();
-
-        // TIterate from zero to the size of buff var.
-        for i in 0..buff.len() {
-            // Avoid the first, remember, the binary
-            if i == 0{
-                continue;
-            } else {
-                arguments.push(buff[i].clone());
-            }
-        }
-
-        // Drop function cleans from memory the variable and data
-        drop(buff);
-
-        // Execute the "binn" binary with the arguments collected in "arguments"
-       // "Spawn" execute the command with the arguments. The child process is not lineal to this, will run in
-        // 		another core
-        let mut proc = process::Command::new( binn.trim() );
-        // Arg method inserts the arguments in the Command struct, because of that we can iterate adding it
-        for j in arguments {
-            // Stdout configure process' stdout
-            // Stdio::piped connect parent and child processes
-            // Stderr configure process' stderr
-            // Stdio::piped connect parent and child processes
-            proc.arg(j).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
-        }
-
-
-        println!("{}[INFO]\tStarting; {name}{}", color.cyan, color.reset);
-        // Spawn execute it
-        let nproc = match proc.spawn(){
-            Ok(d) => {
-                s_id = d.id().into();
-                h_s_service.insert( name.clone(), s_id );
-                println!("{}[INFO]\tStarted, id; {s_id}{}", color.green, color.reset);
-                d
-            },
+impl Service {
+    /// Starts "binary" with "arguments", a command line with quotes.
+    pub fn start(name: &str, binary: &str, arguments: &str) -> Result<Service, String> {
+        let color = Colors::new();
+        let arguments = match parse_argv(arguments) {
+            Ok(d) => d,
             Err(e) => {
-                eprintln!("{}[ERR]\t\tError executing {name}. Check if binary/command exists, you can try executing with absolute path.{}", color.red, color.reset);
-                eprintln!("{}[ERR][INFO]\t{name} status; {e}{}", color.red, color.reset);
-				return Err("".to_string());
+                eprintln!("{}[ERR]\t\t{name} arguments; {e}{}", color.red, color.reset);
+                return Err(e.to_string());
             },
-
         };
-        // Drop function cleans from memory the variable and data
-        drop(proc);
-
-
-        // Wait_with_output method waits to command finishes and collect output, return Output struct
-        let output = match nproc.wait_with_output() {
+        let binary = binary.trim().to_string();
+        let (child, output) = spawn_service(name, &binary, &arguments)?;
+        Ok(Service {
+            name: name.to_string(),
+            binary,
+            arguments,
+            child,
+            output: Some(output),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The process, for its pid, signals and "try_wait" or "wait_timeout".
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Waits until it ends and its output is shown.
+    pub fn wait(&mut self) -> Result<SService, String> {
+        let exit = match self.child.wait() {
             Ok(d) => d,
-            Err(_e) => {
-                eprintln!("{}[ERR]\t\t{name} Error waiting for command and taking process' stdin stdout stderr{}", color.red, color.reset);
-				return Err("".to_string());
+            Err(e) => {
+                let color = Colors::new();
+                eprintln!("{}[ERR]\t\t{} Error waiting for command; {e}{}", color.red, self.name, color.reset);
+                return Err(e.to_string());
             }
         };
+        self.ended(exit)
+    }
+
+    /// Asks it to end with SIGTERM, it is killed if it is alive after "grace".
+    pub fn stop(&mut self, grace: Duration) -> Result<SService, String> {
+        let exit = self.child.terminate(grace).map_err(|e| format!("{}; {e}", self.name))?;
+        self.ended(exit)
+    }
+
+    /// Stops it if it is running and starts it again, with the same binary and arguments.
+    pub fn restart(&mut self, grace: Duration) -> Result<(), String> {
+        if self.child.exit().is_none() {
+            self.stop(grace)?;
+        }
+        let (child, output) = spawn_service(&self.name, &self.binary, &self.arguments)?;
+        self.child = child;
+        self.output = Some(output);
+        Ok(())
+    }
+
+    // Shows how it ended once its output was shown whole
+    fn ended(&mut self, exit: Exit) -> Result<SService, String> {
+        let color = Colors::new();
+        if let Some(d) = self.output.take() {
+            if d.join().is_err() {
+                eprintln!("{}[ERR]\t\t{} output thread failed{}", color.red, self.name, color.reset);
+            }
+        }
+        if exit.success() {
+            println!("{}[INFO]\t{} ended; {exit}{}", color.cyan, self.name, color.reset);
+        } else {
+            eprintln!("{}[ERR]\t\t{} ended; {exit}{}", color.red, self.name, color.reset);
+        }
+        Ok(SService {
+            sname: self.name.clone(),
+            id: self.child.pid().into(),
+            argv: self.child.argv().to_vec(),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            exit,
+        })
+    }
+}
 
-		// Return this as Ok(SService)
-		let instance = SService {
-            sname: name,
-		    id: s_id,
-		    stdout: output.stdout,
-		    stderr: output.stderr,
-			exitstatus: output.status,
-		};
-        Ok(instance)
+// Starts a service and the thread which shows its output
+fn spawn_service(name: &str, binary: &str, arguments: &[String]) -> Result<(Child, JoinHandle<()>), String> {
+    let color = Colors::new();
+    println!("{}[INFO]\tStarting; {name}{}", color.cyan, color.reset);
+    // Stdin is null, services are not interactive. Stdout and stderr are piped to show them with the name
+    let mut child = match Spawn::new(name, binary).args(arguments).stdin(Io::Null).stdout(Io::Piped).stderr(Io::Piped).start() {
+        Ok(d) => {
+            println!("{}[OK]\tStarted {name}, id; {}{}", color.green, d.pid(), color.reset);
+            d
+        },
+        Err(e) => {
+            eprintln!("{}[ERR]\t\tError executing {name}. Check if binary/command exists, you can try executing with absolute path.{}", color.red, color.reset);
+            eprintln!("{}[ERR][INFO]\t{name} status; {e}{}", color.red, color.reset);
+            return Err(e.to_string());
+        },
+    };
+    let lines = child.output_lines();
+    let name = name.to_string();
+    let output = thread::spawn(move || {
+        // Until the service closes its outputs
+        for line in lines {
+            match line {
+                OutputLine::Stdout(d) => println!("[{name}]\t{d}"),
+                OutputLine::Stderr(d) => eprintln!("[{name}]\t{d}"),
+            }
+        }
+    });
+    Ok((child, output))
+}
 
+// Runs "binary" (a command line; the binary and its arguments, with quotes) until it ends, collecting its output.
+pub fn single_command( h_s_service:&mut HashMap<String,i64>, name: String, binary: String) -> Result<SService,String>{
+    let color = Colors::new();
+    let argv = match parse_argv(&binary) {
+        Ok(d) if !d.is_empty() => d,
+        Ok(_d) => return Err(format!("{name}; empty command")),
+        Err(e) => return Err(format!("{name}; {e}")),
+    };
+
+    println!("{}[INFO]\tStarting; {name}{}", color.cyan, color.reset);
+    let child = match Spawn::new(&name, &argv[0]).args(&argv[1..]).stdin(Io::Null).stdout(Io::Piped).stderr(Io::Piped).start() {
+        Ok(d) => {
+            h_s_service.insert( name.clone(), d.pid().into() );
+            println!("{}[INFO]\tStarted, id; {}{}", color.green, d.pid(), color.reset);
+            d
+        },
+        Err(e) => {
+            eprintln!("{}[ERR]\t\tError executing {name}. Check if binary/command exists, you can try executing with absolute path.{}", color.red, color.reset);
+            eprintln!("{}[ERR][INFO]\t{name} status; {e}{}", color.red, color.reset);
+            return Err(e.to_string());
+        },
+    };
+
+    let id = child.pid().into();
+    let (exit, stdout, stderr) = match child.wait_with_output(&[]) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{}[ERR]\t\t{name} Error waiting for command and taking process' stdout stderr{}", color.red, color.reset);
+            return Err(e.to_string());
+        }
+    };
+
+    Ok(SService {
+        sname: name,
+        id,
+        argv,
+        stdout,
+        stderr,
+        exit,
+    })
 }
@@ -0,0 +1,668 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! DEFLATE compressor, LZ77 with hash chains and Huffman coding.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+
+use crate::inflate::{fixed_lit_lengths, CL_ORDER, DIST_BASE, DIST_EXTRA, LEN_BASE, LEN_EXTRA};
+
+// History size, the biggest distance allowed
+const WSIZE: usize = 32768;
+// Input bytes compressed in each block
+const BLOCK_SIZE: usize = 65536;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+const NONE: u32 = u32::MAX;
+// Matches of 3 bytes further than this usually cost more than the literals
+const TOO_FAR: usize = 4096;
+
+#[derive(Clone, Copy)]
+enum Token {
+    Literal(u8),
+    Match(u16, u16),
+}
+
+// Max hash chain length, length good enough to stop searching and lazy matching
+fn level_params(level: u32) -> (usize, usize, bool) {
+    match level {
+        1 => (4, 8, false),
+        2 => (8, 16, false),
+        3 => (16, 32, false),
+        4 => (16, 32, true),
+        5 => (32, 64, true),
+        6 => (128, 128, true),
+        7 => (256, 128, true),
+        8 => (1024, 258, true),
+        _ => (4096, 258, true),
+    }
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let value = (data[i] as u32) | (data[i + 1] as u32) << 8 | (data[i + 2] as u32) << 16;
+    (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+fn len_code(len: usize) -> usize {
+    LEN_BASE.partition_point(|b| *b as usize <= len) - 1
+}
+
+fn dist_code(dist: usize) -> usize {
+    DIST_BASE.partition_point(|b| *b as usize <= dist) - 1
+}
+
+// Huffman code lengths from the frequencies without limit
+fn tree_lengths(weights: &[u64]) -> Vec<u8> {
+    let mut lengths = vec![0u8; weights.len()];
+    let used: Vec<usize> = (0..weights.len()).filter(|i| weights[*i] > 0).collect();
+    if used.is_empty() {
+        return lengths;
+    }
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+
+    // Leaves are the first nodes, internal nodes are added after
+    let mut parent = vec![usize::MAX; used.len()];
+    let mut heap = BinaryHeap::new();
+    for (node, symbol) in used.iter().enumerate() {
+        heap.push(Reverse((weights[*symbol], node)));
+    }
+    while heap.len() > 1 {
+        let (Some(Reverse((wa, a))), Some(Reverse((wb, b)))) = (heap.pop(), heap.pop()) else {
+            break;
+        };
+        let id = parent.len();
+        parent.push(usize::MAX);
+        parent[a] = id;
+        parent[b] = id;
+        heap.push(Reverse((wa + wb, id)));
+    }
+
+    for (leaf, symbol) in used.iter().enumerate() {
+        let mut depth = 0usize;
+        let mut node = leaf;
+        while parent[node] != usize::MAX {
+            node = parent[node];
+            depth += 1;
+        }
+        lengths[*symbol] = depth.min(255) as u8;
+    }
+    lengths
+}
+
+/// Huffman code lengths no longer than "limit" bits.
+/// Frequencies are halved until the tree is short enough.
+fn huffman_lengths(freqs: &[u32], limit: u8) -> Vec<u8> {
+    let mut weights: Vec<u64> = freqs.iter().map(|f| *f as u64).collect();
+    loop {
+        let lengths = tree_lengths(&weights);
+        if lengths.iter().all(|l| *l <= limit) {
+            return lengths;
+        }
+        for w in weights.iter_mut().filter(|w| **w > 0) {
+            *w = w.div_ceil(2);
+        }
+    }
+}
+
+/// Canonical codes for the lengths, bit reversed to be written from the least significant bit.
+fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut count = [0u32; 16];
+    for len in lengths {
+        count[*len as usize] += 1;
+    }
+    count[0] = 0;
+    let mut next = [0u32; 16];
+    for len in 1..16 {
+        next[len] = (next[len - 1] + count[len - 1]) << 1;
+    }
+    lengths
+        .iter()
+        .map(|len| {
+            if *len == 0 {
+                return 0;
+            }
+            let code = next[*len as usize];
+            next[*len as usize] += 1;
+            (code.reverse_bits() >> (32 - *len as u32)) as u16
+        })
+        .collect()
+}
+
+// Make sure at least two symbols are used so the code is complete
+fn at_least_two(freqs: &mut [u32]) {
+    let mut used = freqs.iter().filter(|f| **f > 0).count();
+    let mut i = 0;
+    while used < 2 && i < freqs.len() {
+        if freqs[i] == 0 {
+            freqs[i] = 1;
+            used += 1;
+        }
+        i += 1;
+    }
+}
+
+// Run length encoding of the code lengths with the symbols 16, 17 and 18
+fn rle_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut symbols = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let value = lengths[i];
+        let mut run = lengths[i..].iter().take_while(|l| **l == value).count();
+        i += run;
+        if value == 0 {
+            while run >= 11 {
+                let n = run.min(138);
+                symbols.push((18, (n - 11) as u8));
+                run -= n;
+            }
+            if run >= 3 {
+                symbols.push((17, (run - 3) as u8));
+                run = 0;
+            }
+        } else {
+            symbols.push((value, 0));
+            run -= 1;
+            while run >= 3 {
+                let n = run.min(6);
+                symbols.push((16, (n - 3) as u8));
+                run -= n;
+            }
+        }
+        for _ in 0..run {
+            symbols.push((value, 0));
+        }
+    }
+    symbols
+}
+
+fn cl_extra_bits(symbol: u8) -> u32 {
+    match symbol {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// Encoder of raw DEFLATE streams.
+/// "finish" must be called to write the last block and get the writer back.
+pub struct DeflateEncoder<W: Write> {
+    inner: Option<W>,
+    level: u32,
+    // History (up to 32KB) followed by the input not compressed yet
+    data: Vec<u8>,
+    pending: usize,
+    head: Vec<u32>,
+    prev: Vec<u32>,
+    out: Vec<u8>,
+    bitbuf: u64,
+    bitcount: u32,
+    done: bool,
+}
+
+impl<W: Write> DeflateEncoder<W> {
+    /// "level" goes from 0 (only stored blocks) to 9, bigger values are taken as 9.
+    pub fn new(inner: W, level: u32) -> DeflateEncoder<W> {
+        DeflateEncoder {
+            inner: Some(inner),
+            level: level.min(9),
+            data: Vec::with_capacity(WSIZE + BLOCK_SIZE),
+            pending: 0,
+            head: Vec::new(),
+            prev: Vec::new(),
+            out: Vec::new(),
+            bitbuf: 0,
+            bitcount: 0,
+            done: false,
+        }
+    }
+
+    fn put_bits(&mut self, value: u32, n: u32) {
+        self.bitbuf |= (value as u64) << self.bitcount;
+        self.bitcount += n;
+        while self.bitcount >= 8 {
+            self.out.push(self.bitbuf as u8);
+            self.bitbuf >>= 8;
+            self.bitcount -= 8;
+        }
+    }
+
+    fn align_bits(&mut self) {
+        if self.bitcount > 0 {
+            self.out.push(self.bitbuf as u8);
+        }
+        self.bitbuf = 0;
+        self.bitcount = 0;
+    }
+
+    /// Bytes written as they are, used by gzip and zlib for headers and trailers.
+    pub(crate) fn push_raw(&mut self, bytes: &[u8]) {
+        self.align_bits();
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn write_out(&mut self) -> io::Result<()> {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.write_all(&self.out)?;
+        }
+        self.out.clear();
+        Ok(())
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let h = hash(&self.data, i);
+            self.prev[i] = self.head[h];
+            self.head[h] = i as u32;
+        }
+    }
+
+    // Longest match for the position "i" looking the hash chain
+    fn longest_match(&self, i: usize, max_chain: usize, nice: usize) -> (usize, usize) {
+        let data = &self.data;
+        let max = MAX_MATCH.min(data.len() - i);
+        if max < MIN_MATCH {
+            return (0, 0);
+        }
+        let limit = i.saturating_sub(WSIZE);
+        let mut candidate = self.head[hash(data, i)];
+        let mut chain = max_chain;
+        let (mut best, mut dist) = (0, 0);
+        while candidate != NONE && (candidate as usize) >= limit && chain > 0 {
+            let c = candidate as usize;
+            if data[c + best.min(max - 1)] == data[i + best.min(max - 1)] {
+                let len = data[c..c + max]
+                    .iter()
+                    .zip(&data[i..i + max])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best {
+                    best = len;
+                    dist = i - c;
+                    if len >= nice || len == max {
+                        break;
+                    }
+                }
+            }
+            candidate = self.prev[c];
+            chain -= 1;
+        }
+        if best < MIN_MATCH || (best == MIN_MATCH && dist > TOO_FAR) {
+            return (0, 0);
+        }
+        (best, dist)
+    }
+
+    fn tokenize(&mut self) -> Vec<Token> {
+        let (max_chain, nice, lazy) = level_params(self.level);
+        let start = self.data.len() - self.pending;
+        self.head = vec![NONE; 1 << HASH_BITS];
+        self.prev = vec![NONE; self.data.len()];
+        for i in 0..start {
+            self.insert(i);
+        }
+
+        let mut tokens = Vec::with_capacity(self.pending);
+        let mut i = start;
+        while i < self.data.len() {
+            let (len, dist) = self.longest_match(i, max_chain, nice);
+            self.insert(i);
+            if len == 0 {
+                tokens.push(Token::Literal(self.data[i]));
+                i += 1;
+                continue;
+            }
+            // If the next position has a longer match, this byte goes as literal
+            if lazy && len < nice && i + 1 < self.data.len() {
+                let (next, _) = self.longest_match(i + 1, max_chain, nice);
+                if next > len {
+                    tokens.push(Token::Literal(self.data[i]));
+                    i += 1;
+                    continue;
+                }
+            }
+            tokens.push(Token::Match(len as u16, dist as u16));
+            for j in i + 1..i + len {
+                self.insert(j);
+            }
+            i += len;
+        }
+        tokens
+    }
+
+    fn data_cost(tokens: &[Token], lit: &[u8], dist: &[u8]) -> u64 {
+        let mut cost = lit[256] as u64;
+        for token in tokens {
+            cost += match token {
+                Token::Literal(b) => lit[*b as usize] as u64,
+                Token::Match(len, d) => {
+                    let lc = len_code(*len as usize);
+                    let dc = dist_code(*d as usize);
+                    (lit[257 + lc] + LEN_EXTRA[lc] + dist[dc] + DIST_EXTRA[dc]) as u64
+                }
+            };
+        }
+        cost
+    }
+
+    fn write_tokens(&mut self, tokens: &[Token], lit: &[u8], dist: &[u8]) {
+        let lit_codes = canonical_codes(lit);
+        let dist_codes = canonical_codes(dist);
+        for token in tokens {
+            match token {
+                Token::Literal(b) => {
+                    let b = *b as usize;
+                    self.put_bits(lit_codes[b] as u32, lit[b] as u32);
+                }
+                Token::Match(len, d) => {
+                    let (len, d) = (*len as usize, *d as usize);
+                    let lc = len_code(len);
+                    self.put_bits(lit_codes[257 + lc] as u32, lit[257 + lc] as u32);
+                    self.put_bits((len - LEN_BASE[lc] as usize) as u32, LEN_EXTRA[lc] as u32);
+                    let dc = dist_code(d);
+                    self.put_bits(dist_codes[dc] as u32, dist[dc] as u32);
+                    self.put_bits((d - DIST_BASE[dc] as usize) as u32, DIST_EXTRA[dc] as u32);
+                }
+            }
+        }
+        self.put_bits(lit_codes[256] as u32, lit[256] as u32);
+    }
+
+    fn write_stored(&mut self, last: bool) {
+        let start = self.data.len() - self.pending;
+        let raw = self.data[start..].to_vec();
+        let mut chunks: Vec<&[u8]> = raw.chunks(65535).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        let count = chunks.len();
+        for (n, chunk) in chunks.into_iter().enumerate() {
+            self.put_bits((last && n + 1 == count) as u32, 1);
+            self.put_bits(0, 2);
+            self.align_bits();
+            let len = chunk.len() as u16;
+            self.out.extend_from_slice(&len.to_le_bytes());
+            self.out.extend_from_slice(&(!len).to_le_bytes());
+            self.out.extend_from_slice(chunk);
+        }
+    }
+
+    // Compress all the pending input as one block, choosing stored, fixed or dynamic codes
+    fn compress_block(&mut self, last: bool) -> io::Result<()> {
+        if self.level == 0 {
+            self.write_stored(last);
+        } else {
+            let tokens = self.tokenize();
+
+            let mut lit_freq = [0u32; 286];
+            let mut dist_freq = [0u32; 30];
+            lit_freq[256] = 1;
+            for token in &tokens {
+                match token {
+                    Token::Literal(b) => lit_freq[*b as usize] += 1,
+                    Token::Match(len, d) => {
+                        lit_freq[257 + len_code(*len as usize)] += 1;
+                        dist_freq[dist_code(*d as usize)] += 1;
+                    }
+                }
+            }
+            at_least_two(&mut lit_freq);
+            at_least_two(&mut dist_freq);
+            let lit = huffman_lengths(&lit_freq, 15);
+            let dist = huffman_lengths(&dist_freq, 15);
+
+            // Code lengths of the dynamic header
+            let hlit = lit.iter().rposition(|l| *l > 0).map_or(257, |p| (p + 1).max(257));
+            let hdist = dist.iter().rposition(|l| *l > 0).map_or(1, |p| p + 1);
+            let mut all = lit[..hlit].to_vec();
+            all.extend_from_slice(&dist[..hdist]);
+            let rle = rle_lengths(&all);
+            let mut cl_freq = [0u32; 19];
+            for (symbol, _) in &rle {
+                cl_freq[*symbol as usize] += 1;
+            }
+            at_least_two(&mut cl_freq);
+            let cl = huffman_lengths(&cl_freq, 7);
+            let hclen = CL_ORDER.iter().rposition(|s| cl[*s] > 0).map_or(4, |p| (p + 1).max(4));
+
+            let header_cost = 14
+                + 3 * hclen as u64
+                + rle
+                    .iter()
+                    .map(|(s, _)| cl[*s as usize] as u64 + cl_extra_bits(*s) as u64)
+                    .sum::<u64>();
+            let dynamic_cost = header_cost + Self::data_cost(&tokens, &lit, &dist);
+            let fixed_lit = fixed_lit_lengths();
+            let fixed_dist = [5u8; 30];
+            let fixed_cost = Self::data_cost(&tokens, &fixed_lit, &fixed_dist);
+            let stored_cost = 8 * self.pending as u64 + 40 * (self.pending as u64 / 65535 + 1);
+
+            if stored_cost <= fixed_cost.min(dynamic_cost) {
+                self.write_stored(last);
+            } else if fixed_cost <= dynamic_cost {
+                self.put_bits(last as u32, 1);
+                self.put_bits(1, 2);
+                self.write_tokens(&tokens, &fixed_lit, &fixed_dist);
+            } else {
+                self.put_bits(last as u32, 1);
+                self.put_bits(2, 2);
+                self.put_bits((hlit - 257) as u32, 5);
+                self.put_bits((hdist - 1) as u32, 5);
+                self.put_bits((hclen - 4) as u32, 4);
+                for symbol in CL_ORDER.iter().take(hclen) {
+                    self.put_bits(cl[*symbol] as u32, 3);
+                }
+                let cl_codes = canonical_codes(&cl);
+                for (symbol, extra) in rle {
+                    let s = symbol as usize;
+                    self.put_bits(cl_codes[s] as u32, cl[s] as u32);
+                    self.put_bits(extra as u32, cl_extra_bits(symbol));
+                }
+                self.write_tokens(&tokens, &lit, &dist);
+            }
+        }
+
+        // Keep only the history needed by the next block
+        self.pending = 0;
+        if self.data.len() > WSIZE {
+            let drop = self.data.len() - WSIZE;
+            self.data.drain(..drop);
+        }
+        if self.out.len() >= BLOCK_SIZE {
+            self.write_out()?;
+        }
+        Ok(())
+    }
+
+    /// Write the last block, after this only raw bytes can be added.
+    pub(crate) fn finish_deflate(&mut self) -> io::Result<()> {
+        if !self.done {
+            self.compress_block(true)?;
+            self.align_bits();
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    /// Write the pending output and return the writer.
+    pub(crate) fn into_inner(mut self) -> io::Result<W> {
+        self.write_out()?;
+        match self.inner.take() {
+            Some(mut inner) => {
+                inner.flush()?;
+                Ok(inner)
+            }
+            None => Err(io::Error::other("encoder without writer")),
+        }
+    }
+
+    /// Compress the pending input, write the last block and return the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_deflate()?;
+        self.into_inner()
+    }
+}
+
+impl<W: Write> Write for DeflateEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.done {
+            return Err(io::Error::other("stream already finished"));
+        }
+        self.data.extend_from_slice(buf);
+        self.pending += buf.len();
+        if self.pending >= BLOCK_SIZE {
+            self.compress_block(false)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Compress the pending input and align to a byte with an empty stored block
+    /// (sync flush), so everything written so far can be decompressed.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.done {
+            if self.pending > 0 {
+                self.compress_block(false)?;
+            }
+            self.write_stored(false);
+        }
+        self.write_out()?;
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inflate::DeflateDecoder;
+    use std::io::Read;
+
+    fn compress(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), level);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        DeflateDecoder::new(data).read_to_end(&mut output).unwrap();
+        output
+    }
+
+    // Type of the first block; 0 stored, 1 fixed and 2 dynamic codes
+    fn block_type(compressed: &[u8]) -> u8 {
+        (compressed[0] >> 1) & 3
+    }
+
+    fn text() -> Vec<u8> {
+        (0..40)
+            .flat_map(|i| format!("line {i} of the ravnos test text, with some words repeated: huginn rune muninn\n").into_bytes())
+            .collect()
+    }
+
+    // Bytes without repetitions, a xorshift
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stored() {
+        let data = b"stored block".to_vec();
+        let compressed = compress(&data, 0);
+        assert_eq!(block_type(&compressed), 0);
+        assert_eq!(compressed.len(), data.len() + 5);
+        assert_eq!(decompress(&compressed), data);
+
+        // Data which does not compress is stored at any level
+        let data = noise(1000);
+        let compressed = compress(&data, 6);
+        assert_eq!(block_type(&compressed), 0);
+        assert_eq!(decompress(&compressed), data);
+    }
+
+    #[test]
+    fn fixed() {
+        let data = b"hello hello hello hello\n";
+        let compressed = compress(data, 9);
+        assert_eq!(block_type(&compressed), 1);
+        assert_eq!(decompress(&compressed), data);
+    }
+
+    #[test]
+    fn dynamic() {
+        let data = text();
+        for level in 1..=9 {
+            let compressed = compress(&data, level);
+            assert_eq!(block_type(&compressed), 2, "level {level}");
+            assert!(compressed.len() < data.len() / 4, "level {level}");
+            assert_eq!(decompress(&compressed), data, "level {level}");
+        }
+    }
+
+    #[test]
+    fn empty() {
+        for level in [0, 6] {
+            assert_eq!(decompress(&compress(b"", level)), b"");
+        }
+    }
+
+    #[test]
+    fn several_blocks() {
+        // Longer than a block and than the history, with matches between blocks
+        let mut data = noise(40000);
+        data.extend_from_within(..);
+        data.extend(text().iter().cycle().take(3 * BLOCK_SIZE));
+        for level in [0, 1, 6, 9] {
+            assert_eq!(decompress(&compress(&data, level)), data, "level {level}");
+        }
+    }
+
+    #[test]
+    fn flush() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), 6);
+        encoder.write_all(b"first part, ").unwrap();
+        encoder.flush().unwrap();
+        // After a flush everything written can be decompressed, without the last block
+        let partial = encoder.inner.clone().unwrap();
+        let mut output = vec![0; 12];
+        DeflateDecoder::new(&partial[..]).read_exact(&mut output).unwrap();
+        assert_eq!(output, b"first part, ");
+        encoder.write_all(b"second part").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decompress(&compressed), b"first part, second part");
+    }
+
+    #[test]
+    fn finished() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), 6);
+        encoder.finish_deflate().unwrap();
+        assert!(encoder.write_all(b"late").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Gzip framing (RFC 1952) over DEFLATE.
+
+use std::io::{self, Read, Write};
+
+use crate::checksum::Crc32;
+use crate::deflate::DeflateEncoder;
+use crate::inflate::{BitReader, Inflater};
+use crate::{invalid, GZIP_MAGIC};
+
+// Header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const FRESERVED: u8 = 0xe0;
+
+/// Gzip compressor, "finish" writes the trailer and returns the writer.
+pub struct GzEncoder<W: Write> {
+    deflate: DeflateEncoder<W>,
+    crc: Crc32,
+    size: u32,
+}
+
+impl<W: Write> GzEncoder<W> {
+    pub fn new(inner: W, level: u32) -> GzEncoder<W> {
+        GzEncoder::with_header(inner, level, None, 0)
+    }
+
+    /// Encoder storing the original file name and modification time in the header.
+    pub fn with_header(inner: W, level: u32, name: Option<&str>, mtime: u32) -> GzEncoder<W> {
+        let mut header = vec![GZIP_MAGIC[0], GZIP_MAGIC[1], 8, 0];
+        header.extend_from_slice(&mtime.to_le_bytes());
+        // Extra flags; 2 for the slowest level, 4 for the fastest one
+        header.push(match level {
+            9.. => 2,
+            1 => 4,
+            _ => 0,
+        });
+        // Operating system; Unix
+        header.push(3);
+        if let Some(name) = name {
+            header[3] |= FNAME;
+            header.extend(name.bytes().filter(|b| *b != 0));
+            header.push(0);
+        }
+
+        let mut deflate = DeflateEncoder::new(inner, level);
+        deflate.push_raw(&header);
+        GzEncoder {
+            deflate,
+            crc: Crc32::new(),
+            size: 0,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.deflate.finish_deflate()?;
+        let mut trailer = self.crc.value().to_le_bytes().to_vec();
+        trailer.extend_from_slice(&self.size.to_le_bytes());
+        self.deflate.push_raw(&trailer);
+        self.deflate.into_inner()
+    }
+}
+
+impl<W: Write> Write for GzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deflate.write_all(buf)?;
+        self.crc.update(buf);
+        // ISIZE is the size modulo 2^32
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deflate.flush()
+    }
+}
+
+enum GzState {
+    Header,
+    Body,
+    Done,
+}
+
+/// Gzip decompressor, members concatenated are decompressed one after the other.
+pub struct GzDecoder<R: Read> {
+    bits: BitReader<R>,
+    inflater: Inflater,
+    state: GzState,
+    members: usize,
+    crc: Crc32,
+    size: u32,
+}
+
+impl<R: Read> GzDecoder<R> {
+    pub fn new(inner: R) -> GzDecoder<R> {
+        GzDecoder {
+            bits: BitReader::new(inner),
+            inflater: Inflater::new(),
+            state: GzState::Header,
+            members: 0,
+            crc: Crc32::new(),
+            size: 0,
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let bits = &mut self.bits;
+        if bits.byte()? != GZIP_MAGIC[0] || bits.byte()? != GZIP_MAGIC[1] {
+            return Err(invalid("not in gzip format"));
+        }
+        if bits.byte()? != 8 {
+            return Err(invalid("unknown gzip compression method"));
+        }
+        let flags = bits.byte()?;
+        if flags & FRESERVED != 0 {
+            return Err(invalid("reserved gzip flags are set"));
+        }
+        // Modification time, extra flags and operating system
+        for _ in 0..6 {
+            bits.byte()?;
+        }
+        if flags & FEXTRA != 0 {
+            for _ in 0..bits.u16_le()? {
+                bits.byte()?;
+            }
+        }
+        if flags & FNAME != 0 {
+            while bits.byte()? != 0 {}
+        }
+        if flags & FCOMMENT != 0 {
+            while bits.byte()? != 0 {}
+        }
+        if flags & FHCRC != 0 {
+            bits.u16_le()?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.state {
+                GzState::Done => return Ok(0),
+                GzState::Header => {
+                    if self.members > 0 && self.bits.at_end()? {
+                        self.state = GzState::Done;
+                        continue;
+                    }
+                    self.read_header()?;
+                    self.state = GzState::Body;
+                }
+                GzState::Body => {
+                    let n = self.inflater.read(&mut self.bits, buf)?;
+                    if n > 0 {
+                        self.crc.update(&buf[..n]);
+                        self.size = self.size.wrapping_add(n as u32);
+                        return Ok(n);
+                    }
+                    // End of the member, check the trailer
+                    if self.bits.u32_le()? != self.crc.value() {
+                        return Err(invalid("gzip crc32 mismatch"));
+                    }
+                    if self.bits.u32_le()? != self.size {
+                        return Err(invalid("gzip length mismatch"));
+                    }
+                    self.members += 1;
+                    self.inflater.reset();
+                    self.crc = Crc32::new();
+                    self.size = 0;
+                    self.state = GzState::Header;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,442 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! DEFLATE decompressor (inflate).
+
+use std::io::{self, Read};
+
+use crate::invalid;
+
+/// Base length for each length code (257 to 285).
+pub(crate) const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// Extra bits for each length code.
+pub(crate) const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distance for each distance code (0 to 29).
+pub(crate) const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits for each distance code.
+pub(crate) const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which the code length code lengths are stored.
+pub(crate) const CL_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Lengths of the fixed literal/length code.
+pub(crate) fn fixed_lit_lengths() -> [u8; 288] {
+    let mut lengths = [0u8; 288];
+    for (i, e) in lengths.iter_mut().enumerate() {
+        *e = match i {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    lengths
+}
+
+// History size, the biggest distance allowed
+const WSIZE: usize = 32768;
+
+/// Reads the input bit per bit, from the least significant bit of each byte.
+pub(crate) struct BitReader<R: Read> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    bits: u64,
+    count: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    pub(crate) fn new(inner: R) -> BitReader<R> {
+        BitReader {
+            inner,
+            buf: vec![0u8; 16384],
+            pos: 0,
+            len: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    // Read more input into the buffer, returns false at the end of input
+    fn refill(&mut self) -> io::Result<bool> {
+        loop {
+            match self.inner.read(&mut self.buf) {
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                    return Ok(n > 0);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Load bytes into the bit buffer while there is space and input
+    fn fill(&mut self) -> io::Result<()> {
+        while self.count <= 56 {
+            if self.pos == self.len && !self.refill()? {
+                break;
+            }
+            self.bits |= (self.buf[self.pos] as u64) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn bits(&mut self, n: u32) -> io::Result<u32> {
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.count < n {
+            self.fill()?;
+            if self.count < n {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "compressed data truncated"));
+            }
+        }
+        let value = (self.bits & ((1u64 << n) - 1)) as u32;
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drop the bits until the next byte.
+    pub(crate) fn align(&mut self) {
+        let rest = self.count % 8;
+        self.bits >>= rest;
+        self.count -= rest;
+    }
+
+    /// Read a whole byte, the reader must be aligned.
+    pub(crate) fn byte(&mut self) -> io::Result<u8> {
+        if self.count >= 8 {
+            return Ok(self.bits(8)? as u8);
+        }
+        if self.pos == self.len && !self.refill()? {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "compressed data truncated"));
+        }
+        self.pos += 1;
+        Ok(self.buf[self.pos - 1])
+    }
+
+    pub(crate) fn u16_le(&mut self) -> io::Result<u16> {
+        Ok(self.byte()? as u16 | (self.byte()? as u16) << 8)
+    }
+
+    pub(crate) fn u32_le(&mut self) -> io::Result<u32> {
+        Ok(self.u16_le()? as u32 | (self.u16_le()? as u32) << 16)
+    }
+
+    pub(crate) fn u32_be(&mut self) -> io::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            value = (value << 8) | self.byte()? as u32;
+        }
+        Ok(value)
+    }
+
+    /// Check if there is not more input, the reader must be aligned.
+    pub(crate) fn at_end(&mut self) -> io::Result<bool> {
+        Ok(self.count == 0 && self.pos == self.len && !self.refill()?)
+    }
+}
+
+/// Huffman decoding table, indexed by the next "maxlen" bits of input.
+/// Each entry has the symbol in the low 9 bits and the code length in the upper bits.
+pub(crate) struct Huffman {
+    table: Vec<u16>,
+    maxlen: u32,
+}
+
+impl Huffman {
+    pub(crate) fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let maxlen = lengths.iter().copied().max().unwrap_or(0) as u32;
+        let mut count = [0u32; 16];
+        for len in lengths {
+            count[*len as usize] += 1;
+        }
+        count[0] = 0;
+
+        // Check that the code is not oversubscribed
+        let mut left: i32 = 1;
+        for c in count.iter().skip(1) {
+            left = (left << 1) - *c as i32;
+            if left < 0 {
+                return Err(invalid("invalid huffman code lengths"));
+            }
+        }
+
+        let mut next = [0u32; 16];
+        for len in 1..16 {
+            next[len] = (next[len - 1] + count[len - 1]) << 1;
+        }
+
+        let mut table = vec![0u16; 1 << maxlen];
+        for (symbol, len) in lengths.iter().enumerate() {
+            let len = *len as u32;
+            if len == 0 {
+                continue;
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            // Codes are stored from the most significant bit, but read from the least one
+            let reversed = code.reverse_bits() >> (32 - len);
+            let entry = symbol as u16 | (len as u16) << 9;
+            let mut i = reversed as usize;
+            while i < table.len() {
+                table[i] = entry;
+                i += 1 << len;
+            }
+        }
+        Ok(Huffman { table, maxlen })
+    }
+
+    pub(crate) fn decode<R: Read>(&self, bits: &mut BitReader<R>) -> io::Result<u16> {
+        if bits.count < self.maxlen {
+            bits.fill()?;
+        }
+        let peek = (bits.bits & ((1u64 << self.maxlen) - 1)) as usize;
+        let entry = self.table[peek];
+        let len = (entry >> 9) as u32;
+        if len == 0 {
+            return Err(invalid("invalid huffman code"));
+        }
+        if len > bits.count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "compressed data truncated"));
+        }
+        bits.bits >>= len;
+        bits.count -= len;
+        Ok(entry & 0x1ff)
+    }
+}
+
+enum State {
+    Header,
+    Stored(u32),
+    Codes,
+    Copy(usize, usize),
+    Done,
+}
+
+/// DEFLATE decoding state, independent of the input so gzip and zlib can read their
+/// headers and trailers from the same bit reader.
+pub(crate) struct Inflater {
+    state: State,
+    last: bool,
+    lit: Option<Huffman>,
+    dist: Option<Huffman>,
+    window: Vec<u8>,
+    wpos: usize,
+    // Total output, to check that distances do not go before the start
+    total: u64,
+}
+
+impl Inflater {
+    pub(crate) fn new() -> Inflater {
+        Inflater {
+            state: State::Header,
+            last: false,
+            lit: None,
+            dist: None,
+            window: vec![0u8; WSIZE],
+            wpos: 0,
+            total: 0,
+        }
+    }
+
+    /// Prepare for another stream (gzip members).
+    pub(crate) fn reset(&mut self) {
+        self.state = State::Header;
+        self.last = false;
+        self.lit = None;
+        self.dist = None;
+        self.wpos = 0;
+        self.total = 0;
+    }
+
+    fn read_dynamic<R: Read>(&mut self, bits: &mut BitReader<R>) -> io::Result<()> {
+        let hlit = bits.bits(5)? as usize + 257;
+        let hdist = bits.bits(5)? as usize + 1;
+        let hclen = bits.bits(4)? as usize + 4;
+        if hlit > 286 || hdist > 30 {
+            return Err(invalid("invalid dynamic block header"));
+        }
+
+        let mut cl_lengths = [0u8; 19];
+        for i in CL_ORDER.iter().take(hclen) {
+            cl_lengths[*i] = bits.bits(3)? as u8;
+        }
+        let cl = Huffman::new(&cl_lengths)?;
+
+        let mut lengths = vec![0u8; hlit + hdist];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = cl.decode(bits)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    if i == 0 {
+                        return Err(invalid("repeat without previous length"));
+                    }
+                    (lengths[i - 1], 3 + bits.bits(2)? as usize)
+                }
+                17 => (0, 3 + bits.bits(3)? as usize),
+                _ => (0, 11 + bits.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(invalid("too many code lengths"));
+            }
+            for e in &mut lengths[i..i + repeat] {
+                *e = value;
+            }
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("missing end of block code"));
+        }
+        self.lit = Some(Huffman::new(&lengths[..hlit])?);
+        self.dist = Some(Huffman::new(&lengths[hlit..])?);
+        Ok(())
+    }
+
+    fn push(&mut self, out: &mut [u8], n: &mut usize, byte: u8) {
+        out[*n] = byte;
+        *n += 1;
+        self.window[self.wpos] = byte;
+        self.wpos = (self.wpos + 1) & (WSIZE - 1);
+        self.total += 1;
+    }
+
+    /// Decompress into "out", returns 0 at the end of the deflate stream.
+    pub(crate) fn read<R: Read>(&mut self, bits: &mut BitReader<R>, out: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < out.len() {
+            match self.state {
+                State::Done => break,
+                State::Header => {
+                    if self.last {
+                        // The next data (trailers) starts at the byte boundary
+                        bits.align();
+                        self.state = State::Done;
+                        continue;
+                    }
+                    self.last = bits.bits(1)? == 1;
+                    match bits.bits(2)? {
+                        0 => {
+                            bits.align();
+                            let len = bits.u16_le()?;
+                            let nlen = bits.u16_le()?;
+                            if len != !nlen {
+                                return Err(invalid("invalid stored block length"));
+                            }
+                            self.state = State::Stored(len as u32);
+                        }
+                        1 => {
+                            self.lit = Some(Huffman::new(&fixed_lit_lengths())?);
+                            self.dist = Some(Huffman::new(&[5u8; 30])?);
+                            self.state = State::Codes;
+                        }
+                        2 => {
+                            self.read_dynamic(bits)?;
+                            self.state = State::Codes;
+                        }
+                        _ => return Err(invalid("invalid block type")),
+                    }
+                }
+                State::Stored(rest) => {
+                    if rest == 0 {
+                        self.state = State::Header;
+                        continue;
+                    }
+                    let byte = bits.byte()?;
+                    self.push(out, &mut n, byte);
+                    self.state = State::Stored(rest - 1);
+                }
+                State::Copy(len, dist) => {
+                    if len == 0 {
+                        self.state = State::Codes;
+                        continue;
+                    }
+                    let byte = self.window[(self.wpos + WSIZE - dist) & (WSIZE - 1)];
+                    self.push(out, &mut n, byte);
+                    self.state = State::Copy(len - 1, dist);
+                }
+                State::Codes => {
+                    let (lit, dist) = match (&self.lit, &self.dist) {
+                        (Some(l), Some(d)) => (l, d),
+                        _ => return Err(invalid("missing huffman tables")),
+                    };
+                    let symbol = lit.decode(bits)? as usize;
+                    if symbol < 256 {
+                        self.push(out, &mut n, symbol as u8);
+                    } else if symbol == 256 {
+                        self.state = State::Header;
+                    } else {
+                        let code = symbol - 257;
+                        if code >= 29 {
+                            return Err(invalid("invalid length code"));
+                        }
+                        let len = LEN_BASE[code] as usize + bits.bits(LEN_EXTRA[code] as u32)? as usize;
+                        let dcode = dist.decode(bits)? as usize;
+                        if dcode >= 30 {
+                            return Err(invalid("invalid distance code"));
+                        }
+                        let distance =
+                            DIST_BASE[dcode] as usize + bits.bits(DIST_EXTRA[dcode] as u32)? as usize;
+                        if distance as u64 > self.total {
+                            return Err(invalid("distance too far back"));
+                        }
+                        self.state = State::Copy(len, distance);
+                    }
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Decoder of raw DEFLATE streams.
+pub struct DeflateDecoder<R: Read> {
+    bits: BitReader<R>,
+    inflater: Inflater,
+}
+
+impl<R: Read> DeflateDecoder<R> {
+    pub fn new(inner: R) -> DeflateDecoder<R> {
+        DeflateDecoder {
+            bits: BitReader::new(inner),
+            inflater: Inflater::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for DeflateDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inflater.read(&mut self.bits, buf)
+    }
+}
@@ -0,0 +1,174 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//!
+//! RavnOS's libcompress
+//! DEFLATE (RFC 1951) compressor and decompressor with zlib (RFC 1950) and gzip (RFC 1952) framing.
+//! Each format has a decoder implementing Read and an encoder implementing Write.
+
+mod checksum;
+mod deflate;
+mod gzip;
+mod inflate;
+mod zlib;
+
+pub use checksum::{adler32, crc32, Adler32, Crc32};
+pub use deflate::DeflateEncoder;
+pub use gzip::{GzDecoder, GzEncoder};
+pub use inflate::DeflateDecoder;
+pub use zlib::{ZlibDecoder, ZlibEncoder};
+
+/// Input Output lib
+use std::io::{self, Read, Write};
+
+/// Default compression level, from 0 (only stored blocks) to 9 (slowest and smallest).
+pub const DEFAULT_LEVEL: u32 = 6;
+
+/// Gzip's magic number, the first two bytes of each gzip member.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Compress "data" into a gzip stream.
+pub fn gzip_compress(data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decompress a gzip stream (with one or more members).
+pub fn gzip_decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Compress "data" into a zlib stream.
+pub fn zlib_compress(data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decompress a zlib stream.
+pub fn zlib_decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    ZlibDecoder::new(data).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+pub(crate) fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_deflate() {
+        // "hello hello hello hello\n" with fixed codes, by zlib
+        let compressed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0xb9, 0x00];
+        let mut output = Vec::new();
+        DeflateDecoder::new(&compressed[..]).read_to_end(&mut output).unwrap();
+        assert_eq!(output, b"hello hello hello hello\n");
+
+        // Stored block written by hand
+        let compressed = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        let mut output = Vec::new();
+        DeflateDecoder::new(&compressed[..]).read_to_end(&mut output).unwrap();
+        assert_eq!(output, b"abc");
+    }
+
+    #[test]
+    fn gzip() {
+        // "hello world\n" by gzip, without name and with time 0
+        let compressed = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57,
+            0x28, 0xcf, 0x2f, 0xca, 0x49, 0xe1, 0x02, 0x00, 0x2d, 0x3b, 0x08, 0xaf, 0x0c, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(gzip_decompress(&compressed).unwrap(), b"hello world\n");
+
+        // The trailer has the CRC32 and the size
+        let compressed = gzip_compress(b"123456789", DEFAULT_LEVEL).unwrap();
+        assert_eq!(compressed[..2], GZIP_MAGIC);
+        let trailer = &compressed[compressed.len() - 8..];
+        assert_eq!(trailer[..4], 0xcbf4_3926u32.to_le_bytes());
+        assert_eq!(trailer[4..], 9u32.to_le_bytes());
+        assert_eq!(gzip_decompress(&compressed).unwrap(), b"123456789");
+    }
+
+    #[test]
+    fn gzip_header() {
+        let mut encoder = GzEncoder::with_header(Vec::new(), 9, Some("file.txt"), 1_700_000_000);
+        encoder.write_all(b"content").unwrap();
+        let compressed = encoder.finish().unwrap();
+        // Name flag, time, extra flags of the slowest level and Unix
+        assert_eq!(compressed[3], 0x08);
+        assert_eq!(compressed[4..8], 1_700_000_000u32.to_le_bytes());
+        assert_eq!(compressed[8..10], [2, 3]);
+        assert_eq!(&compressed[10..19], b"file.txt\0");
+        assert_eq!(gzip_decompress(&compressed).unwrap(), b"content");
+    }
+
+    #[test]
+    fn gzip_members() {
+        let mut compressed = gzip_compress(b"first ", 1).unwrap();
+        compressed.extend(gzip_compress(b"second", 0).unwrap());
+        assert_eq!(gzip_decompress(&compressed).unwrap(), b"first second");
+    }
+
+    #[test]
+    fn gzip_errors() {
+        let mut compressed = gzip_compress(b"123456789", DEFAULT_LEVEL).unwrap();
+        let len = compressed.len();
+        compressed[len - 8] ^= 1;
+        let e = gzip_decompress(&compressed).unwrap_err();
+        assert_eq!(e.to_string(), "gzip crc32 mismatch");
+
+        let compressed = gzip_compress(b"123456789", DEFAULT_LEVEL).unwrap();
+        let e = gzip_decompress(&compressed[..len - 4]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        let e = gzip_decompress(b"not gzip").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn zlib() {
+        // "hello world\n" by zlib
+        let compressed = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0x28, 0xcf, 0x2f, 0xca, 0x49, 0xe1, 0x02, 0x00,
+            0x1e, 0x72, 0x04, 0x67,
+        ];
+        assert_eq!(zlib_decompress(&compressed).unwrap(), b"hello world\n");
+
+        // The trailer has the Adler32, big endian
+        let mut compressed = zlib_compress(b"Wikipedia", DEFAULT_LEVEL).unwrap();
+        let len = compressed.len();
+        assert_eq!(compressed[len - 4..], 0x11e6_0398u32.to_be_bytes());
+        assert_eq!(zlib_decompress(&compressed).unwrap(), b"Wikipedia");
+
+        compressed[len - 1] ^= 1;
+        let e = zlib_decompress(&compressed).unwrap_err();
+        assert_eq!(e.to_string(), "zlib adler32 mismatch");
+    }
+
+    #[test]
+    fn levels() {
+        let data: Vec<u8> = (0..5000u32).flat_map(|i| (i % 251).to_string().into_bytes()).collect();
+        for level in 0..=10 {
+            assert_eq!(gzip_decompress(&gzip_compress(&data, level).unwrap()).unwrap(), data, "level {level}");
+            assert_eq!(zlib_decompress(&zlib_compress(&data, level).unwrap()).unwrap(), data, "level {level}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Zlib framing (RFC 1950) over DEFLATE.
+
+use std::io::{self, Read, Write};
+
+use crate::checksum::Adler32;
+use crate::deflate::DeflateEncoder;
+use crate::inflate::{BitReader, Inflater};
+use crate::invalid;
+
+/// Zlib compressor, "finish" writes the trailer and returns the writer.
+pub struct ZlibEncoder<W: Write> {
+    deflate: DeflateEncoder<W>,
+    adler: Adler32,
+}
+
+impl<W: Write> ZlibEncoder<W> {
+    pub fn new(inner: W, level: u32) -> ZlibEncoder<W> {
+        // Deflate with a 32KB window
+        let cmf: u16 = 0x78;
+        let flevel: u16 = match level {
+            0 | 1 => 0,
+            2..=5 => 1,
+            6 => 2,
+            _ => 3,
+        };
+        let mut flg = flevel << 6;
+        // Check bits, CMF * 256 + FLG must be a multiple of 31
+        flg += 31 - ((cmf << 8) | flg) % 31;
+
+        let mut deflate = DeflateEncoder::new(inner, level);
+        deflate.push_raw(&[cmf as u8, flg as u8]);
+        ZlibEncoder {
+            deflate,
+            adler: Adler32::new(),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.deflate.finish_deflate()?;
+        self.deflate.push_raw(&self.adler.value().to_be_bytes());
+        self.deflate.into_inner()
+    }
+}
+
+impl<W: Write> Write for ZlibEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deflate.write_all(buf)?;
+        self.adler.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deflate.flush()
+    }
+}
+
+/// Zlib decompressor.
+pub struct ZlibDecoder<R: Read> {
+    bits: BitReader<R>,
+    inflater: Inflater,
+    header: bool,
+    done: bool,
+    adler: Adler32,
+}
+
+impl<R: Read> ZlibDecoder<R> {
+    pub fn new(inner: R) -> ZlibDecoder<R> {
+        ZlibDecoder {
+            bits: BitReader::new(inner),
+            inflater: Inflater::new(),
+            header: false,
+            done: false,
+            adler: Adler32::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for ZlibDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if !self.header {
+            let cmf = self.bits.byte()?;
+            let flg = self.bits.byte()?;
+            if cmf & 0x0f != 8 || cmf >> 4 > 7 {
+                return Err(invalid("unknown zlib compression method"));
+            }
+            if !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+                return Err(invalid("invalid zlib header check"));
+            }
+            if flg & 0x20 != 0 {
+                return Err(invalid("zlib preset dictionaries are not supported"));
+            }
+            self.header = true;
+        }
+        let n = self.inflater.read(&mut self.bits, buf)?;
+        if n > 0 {
+            self.adler.update(&buf[..n]);
+            return Ok(n);
+        }
+        if self.bits.u32_be()? != self.adler.value() {
+            return Err(invalid("zlib adler32 mismatch"));
+        }
+        self.done = true;
+        Ok(0)
+    }
+}
@@ -0,0 +1,471 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Declarative arguments.
+//!
+//! Each program describes its options, positional arguments and subcommands with an "ArgSpec", which parses
+//! the arguments and writes the help and the usage errors from the same description.
+//! Short options can be together, "-rv", and take their value joined or in the next argument, "-m5" or "-m 5".
+//! Long options take it as "--max-count 5" or "--max-count=5". After "--" everything is a positional argument.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Type of the value of an option, checked while parsing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    Text,
+    Integer,
+    Unsigned,
+    Float,
+}
+
+impl ValueType {
+    // What is expected, when the value is not of the type
+    fn check(self, value: &str) -> Result<(), &'static str> {
+        let (valid, expected) = match self {
+            ValueType::Text => (true, ""),
+            ValueType::Integer => (value.parse::<i64>().is_ok(), "an integer number"),
+            ValueType::Unsigned => (value.parse::<u64>().is_ok(), "a positive integer number"),
+            ValueType::Float => (value.parse::<f64>().is_ok(), "a number"),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(expected)
+        }
+    }
+}
+
+/// Help asked with "-h" or "--help", or an error in the arguments; both are the text to show.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgError {
+    Help(String),
+    Usage(String),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgError::Help(d) | ArgError::Usage(d) => write!(f, "{d}"),
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+#[derive(Clone, Debug)]
+struct OptionSpec {
+    // As they are written; "-r", "--recursive" or "-rk"
+    names: Vec<String>,
+    // Name of the value for the help, and its type
+    value: Option<(String, ValueType)>,
+    help: String,
+}
+
+impl OptionSpec {
+    // The name given with or without dashes
+    fn has_name(&self, name: &str) -> bool {
+        self.names.iter().any(|e| e == name || e.trim_start_matches('-') == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PositionalSpec {
+    name: String,
+    help: String,
+    required: bool,
+    many: bool,
+}
+
+/// Description of the arguments of a program or subcommand.
+#[derive(Clone, Debug)]
+pub struct ArgSpec {
+    name: String,
+    about: String,
+    options: Vec<OptionSpec>,
+    positionals: Vec<PositionalSpec>,
+    subcommands: Vec<ArgSpec>,
+}
+
+impl ArgSpec {
+    pub fn new(name: &str, about: &str) -> ArgSpec {
+        ArgSpec {
+            name: name.to_string(),
+            about: about.to_string(),
+            options: Vec::new(),
+            positionals: Vec::new(),
+            subcommands: Vec::new(),
+        }
+    }
+
+    /// Option without value. The names are separated by commas, "-r, --recursive"; a name with one dash and
+    /// several letters, as "-rk", is only taken as it is and not as short options together.
+    pub fn flag(mut self, names: &str, help: &str) -> ArgSpec {
+        self.options.push(OptionSpec {
+            names: names.split(',').map(|e| e.trim().to_string()).collect(),
+            value: None,
+            help: help.to_string(),
+        });
+        self
+    }
+
+    /// Option with a value of "kind", "value" is its name in the help.
+    pub fn option(mut self, names: &str, value: &str, kind: ValueType, help: &str) -> ArgSpec {
+        self.options.push(OptionSpec {
+            names: names.split(',').map(|e| e.trim().to_string()).collect(),
+            value: Some((value.to_string(), kind)),
+            help: help.to_string(),
+        });
+        self
+    }
+
+    /// Positional argument which must be given.
+    pub fn positional(self, name: &str, help: &str) -> ArgSpec {
+        self.add_positional(name, help, true, false)
+    }
+
+    /// Positional argument which can be omitted, after the required ones.
+    pub fn optional(self, name: &str, help: &str) -> ArgSpec {
+        self.add_positional(name, help, false, false)
+    }
+
+    /// The rest of the positional arguments, none or several.
+    pub fn many(self, name: &str, help: &str) -> ArgSpec {
+        self.add_positional(name, help, false, true)
+    }
+
+    fn add_positional(mut self, name: &str, help: &str, required: bool, many: bool) -> ArgSpec {
+        self.positionals.push(PositionalSpec {
+            name: name.to_string(),
+            help: help.to_string(),
+            required,
+            many,
+        });
+        self
+    }
+
+    /// Subcommand with its own arguments, chosen by the first positional argument.
+    pub fn subcommand(mut self, spec: ArgSpec) -> ArgSpec {
+        self.subcommands.push(spec);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// "Usage; [name] [options] <positional> [optional] [many]..."
+    pub fn usage(&self) -> String {
+        let mut usage = format!("Usage; {}", self.name);
+        if !self.options.is_empty() {
+            usage += " [options]";
+        }
+        if !self.subcommands.is_empty() {
+            usage += " <command>";
+        }
+        for positional in &self.positionals {
+            usage += &match (positional.required, positional.many) {
+                (_, true) => format!(" [{}]...", positional.name),
+                (true, false) => format!(" <{}>", positional.name),
+                (false, false) => format!(" [{}]", positional.name),
+            };
+        }
+        usage
+    }
+
+    /// Usage, description and the list of options, arguments and subcommands.
+    pub fn help(&self) -> String {
+        let mut help = self.usage() + "\n";
+        if !self.about.is_empty() {
+            help = help + "\n" + &self.about + "\n";
+        }
+
+        let mut options: Vec<(String, &str)> = Vec::new();
+        for option in &self.options {
+            let mut names = option.names.join(", ");
+            if let Some((value, _)) = &option.value {
+                names = names + " [" + value + "]";
+            }
+            options.push((names, &option.help));
+        }
+        if !self.options.iter().any(|e| e.has_name("h") || e.has_name("help")) {
+            options.push(("-h, --help".to_string(), "show this help."));
+        }
+        help += &section("Options", &options);
+
+        let positionals: Vec<(String, &str)> = self
+            .positionals
+            .iter()
+            .filter(|e| !e.help.is_empty())
+            .map(|e| (e.name.clone(), e.help.as_str()))
+            .collect();
+        help += &section("Arguments", &positionals);
+
+        let commands: Vec<(String, &str)> = self.subcommands.iter().map(|e| (e.name.clone(), e.about.as_str())).collect();
+        help += &section("Commands", &commands);
+        help
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.options.iter().position(|e| e.names.iter().any(|n| n == name))
+    }
+
+    fn error(&self, message: &str) -> ArgError {
+        ArgError::Usage(format!("{}; {message}\n{}\nUse '--help' to see the options.", self.name, self.usage()))
+    }
+
+    // "-5" or "-0.5" is a value, unless there are options with digits
+    fn is_number(&self, argument: &str) -> bool {
+        argument.parse::<f64>().is_ok()
+            && !self.options.iter().any(|e| e.names.iter().any(|n| n.starts_with("-") && n[1..].starts_with(|c: char| c.is_ascii_digit())))
+    }
+
+    /// Parses the arguments, without the name of the program.
+    pub fn parse<S: AsRef<str>>(&self, arguments: &[S]) -> Result<ArgMatches, ArgError> {
+        let mut matches = ArgMatches {
+            options: self.options.iter().map(|e| e.names.clone()).collect(),
+            values: vec![Vec::new(); self.options.len()],
+            positionals: Vec::new(),
+            subcommand: None,
+        };
+        let mut rest: Vec<String> = Vec::new();
+        let mut only_positionals = false;
+        let mut i = 0;
+        while i < arguments.len() {
+            let argument = arguments[i].as_ref();
+            i += 1;
+            if only_positionals || argument == "-" || !argument.starts_with('-') || self.is_number(argument) {
+                if rest.is_empty() && !only_positionals {
+                    if let Some(sub) = self.subcommands.iter().find(|e| e.name == argument) {
+                        let parsed = sub.parse(&arguments[i..])?;
+                        matches.subcommand = Some((sub.name.clone(), Box::new(parsed)));
+                        break;
+                    }
+                }
+                rest.push(argument.to_string());
+                continue;
+            }
+            if argument == "--" {
+                only_positionals = true;
+                continue;
+            }
+            if (argument == "-h" || argument == "--help") && self.find(argument).is_none() {
+                return Err(ArgError::Help(self.help()));
+            }
+
+            // "--name" or "--name=value"
+            if let Some(long) = argument.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((n, v)) => (format!("--{n}"), Some(v)),
+                    None => (argument.to_string(), None),
+                };
+                let index = self
+                    .find(&name)
+                    .ok_or_else(|| self.error(&format!("unknown option '{name}'")))?;
+                let value = match (&self.options[index].value, inline) {
+                    (None, None) => None,
+                    (None, Some(_)) => return Err(self.error(&format!("option '{name}' does not take a value"))),
+                    (Some(_), Some(d)) => Some(d.to_string()),
+                    (Some(_), None) => Some(self.next_value(arguments, &mut i, &name)?),
+                };
+                self.add(&mut matches, index, &name, value)?;
+                continue;
+            }
+
+            // Names as they are, as "-E" or "-rk"
+            if let Some(index) = self.find(argument) {
+                let value = match &self.options[index].value {
+                    Some(_) => Some(self.next_value(arguments, &mut i, argument)?),
+                    None => None,
+                };
+                self.add(&mut matches, index, argument, value)?;
+                continue;
+            }
+
+            // Short options together, "-rv", the one with a value takes the rest; "-m5"
+            for (at, c) in argument[1..].char_indices() {
+                let name = format!("-{c}");
+                let index = self.find(&name).ok_or_else(|| {
+                    if at == 0 {
+                        self.error(&format!("unknown option '{argument}'"))
+                    } else {
+                        self.error(&format!("unknown option '{name}' in '{argument}'"))
+                    }
+                })?;
+                if self.options[index].value.is_none() {
+                    self.add(&mut matches, index, &name, None)?;
+                    continue;
+                }
+                let joined = &argument[1 + at + c.len_utf8()..];
+                let value = if joined.is_empty() {
+                    self.next_value(arguments, &mut i, &name)?
+                } else {
+                    joined.to_string()
+                };
+                self.add(&mut matches, index, &name, Some(value))?;
+                break;
+            }
+        }
+
+        // The positional arguments in order, the ones which can be several take the rest
+        let required = self.positionals.iter().filter(|e| e.required).count();
+        if rest.len() < required && matches.subcommand.is_none() {
+            let missing = &self.positionals.iter().filter(|e| e.required).nth(rest.len()).map(|e| e.name.clone()).unwrap_or_default();
+            return Err(self.error(&format!("missing <{missing}>")));
+        }
+        if !self.subcommands.is_empty() && matches.subcommand.is_none() && self.positionals.is_empty() {
+            return match rest.first() {
+                Some(d) => Err(self.error(&format!("unknown command '{d}'"))),
+                None => Err(self.error("missing <command>")),
+            };
+        }
+        let mut rest = rest.into_iter();
+        for positional in &self.positionals {
+            if positional.many {
+                matches.positionals.extend(rest.by_ref().map(|e| (positional.name.clone(), e)));
+            } else if let Some(d) = rest.next() {
+                matches.positionals.push((positional.name.clone(), d));
+            }
+        }
+        if let Some(d) = rest.next() {
+            return Err(self.error(&format!("unexpected argument '{d}'")));
+        }
+        Ok(matches)
+    }
+
+    /// Parses the arguments of a program. With "--help" the help is printed and the program ends,
+    /// with an error it is printed and the program ends with code 2.
+    pub fn parse_or_exit<S: AsRef<str>>(&self, arguments: &[S]) -> ArgMatches {
+        match self.parse(arguments) {
+            Ok(d) => d,
+            Err(ArgError::Help(d)) => {
+                print!("{d}");
+                std::process::exit(0);
+            }
+            Err(ArgError::Usage(d)) => {
+                eprintln!("{d}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // The next argument as the value of "name"
+    fn next_value<S: AsRef<str>>(&self, arguments: &[S], i: &mut usize, name: &str) -> Result<String, ArgError> {
+        match arguments.get(*i) {
+            Some(d) => {
+                *i += 1;
+                Ok(d.as_ref().to_string())
+            }
+            None => Err(self.error(&format!("option '{name}' needs a value"))),
+        }
+    }
+
+    fn add(&self, matches: &mut ArgMatches, index: usize, name: &str, value: Option<String>) -> Result<(), ArgError> {
+        if let (Some((_, kind)), Some(value)) = (&self.options[index].value, &value) {
+            if let Err(e) = kind.check(value) {
+                return Err(self.error(&format!("option '{name}' needs {e}; '{value}'")));
+            }
+        }
+        matches.values[index].push(value.unwrap_or_default());
+        Ok(())
+    }
+}
+
+// Section of the help with names and their descriptions aligned, the lines after the first one of a
+// description are aligned with it
+fn section(title: &str, entries: &[(String, &str)]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let width = entries.iter().map(|e| e.0.chars().count()).filter(|e| *e <= 24).max().unwrap_or(0);
+    let mut text = format!("\n{title}:\n");
+    for (names, help) in entries {
+        let mut lines = help.lines();
+        text += &format!("    {names:width$} : {}\n", lines.next().unwrap_or_default());
+        for line in lines {
+            text += &format!("    {:width$}   {}\n", "", line.trim());
+        }
+    }
+    text
+}
+
+/// Arguments given, by the names of the specification.
+#[derive(Clone, Debug)]
+pub struct ArgMatches {
+    options: Vec<Vec<String>>,
+    // Values of each option, an empty one each time a flag is given
+    values: Vec<Vec<String>>,
+    positionals: Vec<(String, String)>,
+    subcommand: Option<(String, Box<ArgMatches>)>,
+}
+
+impl ArgMatches {
+    // Any of the names of the option, with or without dashes; "r", "-r" or "recursive"
+    fn values_of(&self, name: &str) -> &[String] {
+        match self
+            .options
+            .iter()
+            .position(|e| e.iter().any(|n| n == name || n.trim_start_matches('-') == name))
+        {
+            Some(d) => &self.values[d],
+            None => &[],
+        }
+    }
+
+    /// If the option was given.
+    pub fn flag(&self, name: &str) -> bool {
+        !self.values_of(name).is_empty()
+    }
+
+    /// Times the option was given, as "-vv".
+    pub fn occurrences(&self, name: &str) -> usize {
+        self.values_of(name).len()
+    }
+
+    /// Value of the option, the last one if it was given several times.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values_of(name).last().map(|e| e.as_str())
+    }
+
+    /// All the values of the option, in order.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.values_of(name).iter().map(|e| e.as_str()).collect()
+    }
+
+    /// Value of the option converted, as the type was checked only fails if "T" is other type.
+    pub fn get<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.value(name).and_then(|e| e.parse().ok())
+    }
+
+    /// Value of the positional argument.
+    pub fn positional(&self, name: &str) -> Option<&str> {
+        self.positionals.iter().find(|e| e.0 == name).map(|e| e.1.as_str())
+    }
+
+    /// Values of the positional argument which takes the rest.
+    pub fn positional_values(&self, name: &str) -> Vec<&str> {
+        self.positionals.iter().filter(|e| e.0 == name).map(|e| e.1.as_str()).collect()
+    }
+
+    /// All the positional arguments, in order.
+    pub fn positionals(&self) -> Vec<&str> {
+        self.positionals.iter().map(|e| e.1.as_str()).collect()
+    }
+
+    /// Subcommand given and its arguments.
+    pub fn subcommand(&self) -> Option<(&str, &ArgMatches)> {
+        self.subcommand.as_ref().map(|(n, m)| (n.as_str(), m.as_ref()))
+    }
+}
@@ -0,0 +1,855 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! ELF files of 32 and 64 bits, in little or big endian (System V ABI, "gABI").
+//!
+//! The file header, the program headers (segments), the section headers with their names, the symbols of
+//! ".symtab" and ".dynsym", the dynamic section and the notes are read. The dynamic section and the notes
+//! are taken from the segments, so they are read also when the section headers are stripped; the addresses of
+//! the dynamic section are turned into offsets with the "LOAD" segments.
+//! Every offset and size is checked against the file, a truncated or wrong file is an "InvalidData" error.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::encoding::HEX;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+
+// Program header types
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_INTERP: u32 = 3;
+pub const PT_NOTE: u32 = 4;
+
+// Section header types
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOTE: u32 = 7;
+const SHT_NOBITS: u32 = 8;
+const SHT_DYNSYM: u32 = 11;
+
+// Dynamic tags
+pub const DT_NULL: i64 = 0;
+pub const DT_NEEDED: i64 = 1;
+pub const DT_STRTAB: i64 = 5;
+pub const DT_SONAME: i64 = 14;
+pub const DT_RPATH: i64 = 15;
+pub const DT_RUNPATH: i64 = 29;
+pub const DT_FLAGS_1: i64 = 0x6ffffffb;
+const DF_1_PIE: u64 = 0x08000000;
+
+// Object types
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+
+// Note of the GNU tools with the build id
+const NT_GNU_BUILD_ID: u32 = 3;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Offset "length" bytes after "at"; both come from the file, a sum out of 64 bits is not in it
+fn add(at: u64, length: u64) -> io::Result<u64> {
+    at.checked_add(length).ok_or_else(|| invalid("offset out of the file"))
+}
+
+// Size of "count" entries of "size" bytes
+fn table_size(count: usize, size: u64) -> io::Result<u64> {
+    (count as u64).checked_mul(size).ok_or_else(|| invalid("table out of the file"))
+}
+
+/// File header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElfHeader {
+    /// 32 or 64.
+    pub class: u8,
+    pub little_endian: bool,
+    pub os_abi: u8,
+    /// "e_type"; 1 relocatable, 2 executable, 3 shared object or position independent executable, 4 core.
+    pub kind: u16,
+    pub machine: u16,
+    pub version: u32,
+    pub entry: u64,
+    pub flags: u32,
+    pub phoff: u64,
+    pub shoff: u64,
+    pub phnum: usize,
+    pub shnum: usize,
+    pub shstrndx: usize,
+}
+
+/// Program header, a segment of the process image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramHeader {
+    pub kind: u32,
+    /// Bits; 1 execute, 2 write, 4 read.
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+    pub align: u64,
+}
+
+/// Section header, with its name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub kind: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub align: u64,
+    pub entsize: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    /// 0 local, 1 global, 2 weak.
+    pub bind: u8,
+    /// 0 none, 1 object, 2 function, 3 section, 4 file, 5 common, 6 TLS, 10 indirect function.
+    pub kind: u8,
+    pub visibility: u8,
+    /// Index of its section, 0 if it is undefined.
+    pub section: u16,
+}
+
+/// Entry of the dynamic section, the strings (needed libraries, soname and paths) are resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicEntry {
+    pub tag: i64,
+    pub value: u64,
+    pub text: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub owner: String,
+    pub kind: u32,
+    pub desc: Vec<u8>,
+}
+
+impl Note {
+    /// Name of the type by its owner, or the number.
+    pub fn kind_name(&self) -> String {
+        match (self.owner.as_str(), self.kind) {
+            ("GNU", 1) => "ABI_TAG",
+            ("GNU", 2) => "HWCAP",
+            ("GNU", 3) => "BUILD_ID",
+            ("GNU", 4) => "GOLD_VERSION",
+            ("GNU", 5) => "PROPERTY_TYPE_0",
+            ("Go", 4) => "GO_BUILD_ID",
+            _ => return self.kind.to_string(),
+        }
+        .to_string()
+    }
+}
+
+/// ELF file, read whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Elf {
+    pub header: ElfHeader,
+    pub segments: Vec<ProgramHeader>,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub dynamic_symbols: Vec<Symbol>,
+    pub dynamic: Vec<DynamicEntry>,
+    pub notes: Vec<Note>,
+    /// Program interpreter, the dynamic linker; "/lib/ld-musl-aarch64.so.1".
+    pub interpreter: Option<String>,
+}
+
+// Reads numbers in the byte order and size of the file, checking the bounds
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    wide: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, at: u64, length: u64) -> io::Result<&'a [u8]> {
+        let end = at.checked_add(length).ok_or_else(|| invalid("offset out of the file"))?;
+        if end > self.data.len() as u64 {
+            return Err(invalid("offset out of the file"));
+        }
+        Ok(&self.data[at as usize..end as usize])
+    }
+
+    fn u8(&self, at: u64) -> io::Result<u8> {
+        Ok(self.bytes(at, 1)?[0])
+    }
+
+    fn u16(&self, at: u64) -> io::Result<u16> {
+        let bytes: [u8; 2] = self.bytes(at, 2)?.try_into().map_err(|_e| invalid("short read"))?;
+        Ok(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32(&self, at: u64) -> io::Result<u32> {
+        let bytes: [u8; 4] = self.bytes(at, 4)?.try_into().map_err(|_e| invalid("short read"))?;
+        Ok(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn u64(&self, at: u64) -> io::Result<u64> {
+        let bytes: [u8; 8] = self.bytes(at, 8)?.try_into().map_err(|_e| invalid("short read"))?;
+        Ok(if self.little_endian { u64::from_le_bytes(bytes) } else { u64::from_be_bytes(bytes) })
+    }
+
+    // Address, offset or size; 4 bytes in ELF32 and 8 in ELF64
+    fn word(&self, at: u64) -> io::Result<u64> {
+        if self.wide {
+            self.u64(at)
+        } else {
+            self.u32(at).map(u64::from)
+        }
+    }
+
+    // String ended by NUL
+    fn string(&self, at: u64) -> io::Result<String> {
+        let rest = self.bytes(at, (self.data.len() as u64).saturating_sub(at))?;
+        let end = rest.iter().position(|e| *e == 0).ok_or_else(|| invalid("string without end"))?;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+impl Elf {
+    pub fn open(path: &Path) -> io::Result<Elf> {
+        Elf::parse(&fs::read(path)?)
+    }
+
+    pub fn parse(data: &[u8]) -> io::Result<Elf> {
+        if !data.starts_with(&ELF_MAGIC) {
+            return Err(invalid("not an ELF file"));
+        }
+        let wide = match data.get(4) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => return Err(invalid("unknown ELF class")),
+        };
+        let little_endian = match data.get(5) {
+            Some(1) => true,
+            Some(2) => false,
+            _ => return Err(invalid("unknown ELF byte order")),
+        };
+        let reader = Reader {
+            data,
+            little_endian,
+            wide,
+        };
+
+        let header = read_header(&reader)?;
+        let mut elf = Elf {
+            segments: read_segments(&reader, &header)?,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            dynamic_symbols: Vec::new(),
+            dynamic: Vec::new(),
+            notes: Vec::new(),
+            interpreter: None,
+            header,
+        };
+        elf.sections = read_sections(&reader, &elf.header)?;
+
+        for section in &elf.sections {
+            match section.kind {
+                SHT_SYMTAB => elf.symbols = read_symbols(&reader, section, &elf.sections)?,
+                SHT_DYNSYM => elf.dynamic_symbols = read_symbols(&reader, section, &elf.sections)?,
+                _ => {}
+            }
+        }
+        for segment in &elf.segments {
+            match segment.kind {
+                PT_INTERP => elf.interpreter = Some(reader.string(segment.offset)?),
+                PT_DYNAMIC => elf.dynamic = elf.read_dynamic(&reader, segment)?,
+                PT_NOTE => elf.notes.extend(read_notes(&reader, segment.offset, segment.filesz, segment.align)?),
+                _ => {}
+            }
+        }
+        // Relocatable objects have no segments, their notes are only in sections
+        if elf.segments.is_empty() {
+            for section in elf.sections.iter().filter(|e| e.kind == SHT_NOTE) {
+                elf.notes.extend(read_notes(&reader, section.offset, section.size, section.align)?);
+            }
+        }
+        Ok(elf)
+    }
+
+    /// Offset in the file of a virtual address, by the "LOAD" segments.
+    pub fn offset_of(&self, vaddr: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .filter(|e| e.kind == PT_LOAD)
+            .find(|e| vaddr >= e.vaddr && vaddr - e.vaddr < e.filesz)
+            .and_then(|e| e.offset.checked_add(vaddr - e.vaddr))
+    }
+
+    fn read_dynamic(&self, reader: &Reader, segment: &ProgramHeader) -> io::Result<Vec<DynamicEntry>> {
+        let size = if reader.wide { 16 } else { 8 };
+        let mut entries = Vec::new();
+        for i in 0..segment.filesz / size {
+            let at = add(segment.offset, i * size)?;
+            let tag = if reader.wide {
+                reader.u64(at)? as i64
+            } else {
+                reader.u32(at)? as i32 as i64
+            };
+            if tag == DT_NULL {
+                break;
+            }
+            let value = reader.word(add(at, size / 2)?)?;
+            entries.push(DynamicEntry { tag, value, text: None });
+        }
+
+        // The strings are offsets in the table of DT_STRTAB
+        let strtab = entries.iter().find(|e| e.tag == DT_STRTAB).and_then(|e| self.offset_of(e.value));
+        if let Some(strtab) = strtab {
+            for entry in entries.iter_mut() {
+                if matches!(entry.tag, DT_NEEDED | DT_SONAME | DT_RPATH | DT_RUNPATH) {
+                    entry.text = add(strtab, entry.value).and_then(|e| reader.string(e)).ok();
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Libraries needed, DT_NEEDED.
+    pub fn needed(&self) -> Vec<&str> {
+        self.dynamic
+            .iter()
+            .filter(|e| e.tag == DT_NEEDED)
+            .filter_map(|e| e.text.as_deref())
+            .collect()
+    }
+
+    /// Build id of the GNU note, in hexadecimal.
+    pub fn build_id(&self) -> Option<String> {
+        self.notes
+            .iter()
+            .find(|e| e.owner == "GNU" && e.kind == NT_GNU_BUILD_ID)
+            .map(|e| HEX.encode(&e.desc))
+    }
+
+    /// Fully static; an executable, static PIE too, without interpreter and without needed libraries.
+    /// Shared libraries, objects and cores are not.
+    pub fn is_static(&self) -> bool {
+        (self.header.kind == ET_EXEC || self.is_pie()) && self.interpreter.is_none() && self.needed().is_empty()
+    }
+
+    /// Position independent executable, dynamic or static. Old linkers don't set DF_1_PIE, then it is a shared
+    /// object with interpreter and without soname (libc has interpreter too, to be run).
+    pub fn is_pie(&self) -> bool {
+        self.header.kind == ET_DYN
+            && (self.dynamic.iter().any(|e| e.tag == DT_FLAGS_1 && e.value & DF_1_PIE != 0)
+                || (self.interpreter.is_some() && !self.dynamic.iter().any(|e| e.tag == DT_SONAME)))
+    }
+
+    /// Section by its index, as the one of a symbol.
+    pub fn section_name(&self, index: u16) -> &str {
+        match index {
+            0 => "UND",
+            0xfff1 => "ABS",
+            0xfff2 => "COMMON",
+            _ => self.sections.get(index as usize).map_or("", |e| e.name.as_str()),
+        }
+    }
+}
+
+fn read_header(reader: &Reader) -> io::Result<ElfHeader> {
+    // After e_ident (16 bytes); e_type, e_machine, e_version, e_entry, e_phoff, e_shoff, e_flags, e_ehsize,
+    // e_phentsize, e_phnum, e_shentsize, e_shnum and e_shstrndx
+    let word = if reader.wide { 8 } else { 4 };
+    let flags_at = 24 + 3 * word;
+    let mut header = ElfHeader {
+        class: if reader.wide { 64 } else { 32 },
+        little_endian: reader.little_endian,
+        os_abi: reader.u8(7)?,
+        kind: reader.u16(16)?,
+        machine: reader.u16(18)?,
+        version: reader.u32(20)?,
+        entry: reader.word(24)?,
+        phoff: reader.word(24 + word)?,
+        shoff: reader.word(24 + 2 * word)?,
+        flags: reader.u32(flags_at)?,
+        phnum: reader.u16(flags_at + 8)? as usize,
+        shnum: reader.u16(flags_at + 12)? as usize,
+        shstrndx: reader.u16(flags_at + 14)? as usize,
+    };
+    if reader.u16(flags_at + 6)? as u64 != program_header_size(reader) && header.phnum > 0 {
+        return Err(invalid("unknown program header size"));
+    }
+    if reader.u16(flags_at + 10)? as u64 != section_header_size(reader) && header.shoff != 0 {
+        return Err(invalid("unknown section header size"));
+    }
+
+    // With too many entries the numbers are in the first section header
+    if header.shoff != 0 && (header.shnum == 0 || header.shstrndx == 0xffff || header.phnum == 0xffff) {
+        let first = read_section_header(reader, header.shoff)?;
+        if header.shnum == 0 {
+            header.shnum = first.size as usize;
+        }
+        if header.shstrndx == 0xffff {
+            header.shstrndx = first.link as usize;
+        }
+        if header.phnum == 0xffff {
+            header.phnum = first.info as usize;
+        }
+    }
+    Ok(header)
+}
+
+fn program_header_size(reader: &Reader) -> u64 {
+    if reader.wide {
+        56
+    } else {
+        32
+    }
+}
+
+fn section_header_size(reader: &Reader) -> u64 {
+    if reader.wide {
+        64
+    } else {
+        40
+    }
+}
+
+fn read_segments(reader: &Reader, header: &ElfHeader) -> io::Result<Vec<ProgramHeader>> {
+    let size = program_header_size(reader);
+    // The table must be in the file before reserving memory for it
+    reader.bytes(header.phoff, table_size(header.phnum, size)?)?;
+    let mut segments = Vec::with_capacity(header.phnum);
+    for i in 0..header.phnum as u64 {
+        let at = header.phoff + i * size;
+        // In ELF64 the flags come after the type, in ELF32 after the sizes
+        segments.push(if reader.wide {
+            ProgramHeader {
+                kind: reader.u32(at)?,
+                flags: reader.u32(at + 4)?,
+                offset: reader.u64(at + 8)?,
+                vaddr: reader.u64(at + 16)?,
+                paddr: reader.u64(at + 24)?,
+                filesz: reader.u64(at + 32)?,
+                memsz: reader.u64(at + 40)?,
+                align: reader.u64(at + 48)?,
+            }
+        } else {
+            ProgramHeader {
+                kind: reader.u32(at)?,
+                offset: reader.u32(at + 4)? as u64,
+                vaddr: reader.u32(at + 8)? as u64,
+                paddr: reader.u32(at + 12)? as u64,
+                filesz: reader.u32(at + 16)? as u64,
+                memsz: reader.u32(at + 20)? as u64,
+                flags: reader.u32(at + 24)?,
+                align: reader.u32(at + 28)? as u64,
+            }
+        });
+    }
+    Ok(segments)
+}
+
+fn read_section_header(reader: &Reader, at: u64) -> io::Result<Section> {
+    let word = if reader.wide { 8 } else { 4 };
+    // "e_shoff" is read before the table is checked
+    let at = |field: u64| add(at, field);
+    Ok(Section {
+        name: String::new(),
+        kind: reader.u32(at(4)?)?,
+        flags: reader.word(at(8)?)?,
+        addr: reader.word(at(8 + word)?)?,
+        offset: reader.word(at(8 + 2 * word)?)?,
+        size: reader.word(at(8 + 3 * word)?)?,
+        link: reader.u32(at(8 + 4 * word)?)?,
+        info: reader.u32(at(12 + 4 * word)?)?,
+        align: reader.word(at(16 + 4 * word)?)?,
+        entsize: reader.word(at(16 + 5 * word)?)?,
+    })
+}
+
+fn read_sections(reader: &Reader, header: &ElfHeader) -> io::Result<Vec<Section>> {
+    if header.shoff == 0 {
+        return Ok(Vec::new());
+    }
+    let size = section_header_size(reader);
+    reader.bytes(header.shoff, table_size(header.shnum, size)?)?;
+    let mut sections = Vec::with_capacity(header.shnum);
+    let mut names = Vec::with_capacity(header.shnum);
+    for i in 0..header.shnum as u64 {
+        let at = header.shoff + i * size;
+        names.push(reader.u32(at)?);
+        sections.push(read_section_header(reader, at)?);
+    }
+    // Names are offsets in the section of the names
+    if let Some(strtab) = sections.get(header.shstrndx).map(|e| e.offset) {
+        for (section, name) in sections.iter_mut().zip(names) {
+            section.name = add(strtab, name as u64).and_then(|e| reader.string(e)).unwrap_or_default();
+        }
+    }
+    Ok(sections)
+}
+
+fn read_symbols(reader: &Reader, section: &Section, sections: &[Section]) -> io::Result<Vec<Symbol>> {
+    let size: u64 = if reader.wide { 24 } else { 16 };
+    if section.kind == SHT_NOBITS {
+        return Ok(Vec::new());
+    }
+    reader.bytes(section.offset, section.size)?;
+    let strtab = sections.get(section.link as usize).map(|e| e.offset);
+    let mut symbols = Vec::with_capacity((section.size / size) as usize);
+    for i in 0..section.size / size {
+        let at = section.offset + i * size;
+        // ELF64; name, info, other, shndx, value, size. ELF32; name, value, size, info, other, shndx
+        let (value, length, info_at) = if reader.wide {
+            (reader.u64(at + 8)?, reader.u64(at + 16)?, at + 4)
+        } else {
+            (reader.u32(at + 4)? as u64, reader.u32(at + 8)? as u64, at + 12)
+        };
+        let info = reader.u8(info_at)?;
+        let name = match strtab {
+            Some(d) => add(d, reader.u32(at)? as u64).and_then(|e| reader.string(e)).unwrap_or_default(),
+            None => String::new(),
+        };
+        symbols.push(Symbol {
+            name,
+            value,
+            size: length,
+            bind: info >> 4,
+            kind: info & 0x0f,
+            visibility: reader.u8(info_at + 1)? & 0x03,
+            section: reader.u16(info_at + 2)?,
+        });
+    }
+    Ok(symbols)
+}
+
+// Name size, description size and type, then the name and the description, each one aligned
+fn read_notes(reader: &Reader, offset: u64, size: u64, align: u64) -> io::Result<Vec<Note>> {
+    let align = if align == 8 { 8 } else { 4 };
+    let pad = |e: u64| e.div_ceil(align) * align;
+    let end = offset.checked_add(size).ok_or_else(|| invalid("note out of the file"))?;
+    reader.bytes(offset, size)?;
+    let mut notes = Vec::new();
+    let mut at = offset;
+    while at + 12 <= end {
+        let namesz = reader.u32(at)? as u64;
+        let descsz = reader.u32(at + 4)? as u64;
+        let kind = reader.u32(at + 8)?;
+        let name = reader.bytes(at + 12, namesz)?;
+        let desc_at = at + 12 + pad(namesz);
+        let desc = reader.bytes(desc_at, descsz)?;
+        notes.push(Note {
+            owner: String::from_utf8_lossy(name).trim_end_matches('\0').to_string(),
+            kind,
+            desc: desc.to_vec(),
+        });
+        at = desc_at + pad(descsz);
+    }
+    Ok(notes)
+}
+
+/// Name of a segment type.
+pub fn segment_kind_name(kind: u32) -> String {
+    match kind {
+        0 => "NULL",
+        PT_LOAD => "LOAD",
+        PT_DYNAMIC => "DYNAMIC",
+        PT_INTERP => "INTERP",
+        PT_NOTE => "NOTE",
+        5 => "SHLIB",
+        6 => "PHDR",
+        7 => "TLS",
+        0x6474e550 => "GNU_EH_FRAME",
+        0x6474e551 => "GNU_STACK",
+        0x6474e552 => "GNU_RELRO",
+        0x6474e553 => "GNU_PROPERTY",
+        0x70000003 => "RISCV_ATTRIBUTES",
+        _ => return format!("{kind:#x}"),
+    }
+    .to_string()
+}
+
+/// Name of a section type.
+pub fn section_kind_name(kind: u32) -> String {
+    match kind {
+        0 => "NULL",
+        1 => "PROGBITS",
+        SHT_SYMTAB => "SYMTAB",
+        3 => "STRTAB",
+        4 => "RELA",
+        5 => "HASH",
+        6 => "DYNAMIC",
+        SHT_NOTE => "NOTE",
+        SHT_NOBITS => "NOBITS",
+        9 => "REL",
+        SHT_DYNSYM => "DYNSYM",
+        14 => "INIT_ARRAY",
+        15 => "FINI_ARRAY",
+        16 => "PREINIT_ARRAY",
+        17 => "GROUP",
+        18 => "SYMTAB_SHNDX",
+        0x6ffffff6 => "GNU_HASH",
+        0x6ffffffd => "VERDEF",
+        0x6ffffffe => "VERNEED",
+        0x6fffffff => "VERSYM",
+        0x70000001 => "ARM_EXIDX",
+        0x70000003 => "ARM_ATTRIBUTES",
+        _ => return format!("{kind:#x}"),
+    }
+    .to_string()
+}
+
+/// Name of a dynamic tag.
+pub fn dynamic_tag_name(tag: i64) -> String {
+    match tag {
+        DT_NEEDED => "NEEDED",
+        2 => "PLTRELSZ",
+        3 => "PLTGOT",
+        4 => "HASH",
+        DT_STRTAB => "STRTAB",
+        6 => "SYMTAB",
+        7 => "RELA",
+        8 => "RELASZ",
+        9 => "RELAENT",
+        10 => "STRSZ",
+        11 => "SYMENT",
+        12 => "INIT",
+        13 => "FINI",
+        DT_SONAME => "SONAME",
+        DT_RPATH => "RPATH",
+        16 => "SYMBOLIC",
+        17 => "REL",
+        18 => "RELSZ",
+        19 => "RELENT",
+        20 => "PLTREL",
+        21 => "DEBUG",
+        22 => "TEXTREL",
+        23 => "JMPREL",
+        24 => "BIND_NOW",
+        25 => "INIT_ARRAY",
+        26 => "FINI_ARRAY",
+        27 => "INIT_ARRAYSZ",
+        28 => "FINI_ARRAYSZ",
+        DT_RUNPATH => "RUNPATH",
+        30 => "FLAGS",
+        32 => "PREINIT_ARRAY",
+        33 => "PREINIT_ARRAYSZ",
+        0x6ffffef5 => "GNU_HASH",
+        0x6ffffff0 => "VERSYM",
+        0x6ffffff9 => "RELACOUNT",
+        0x6ffffffa => "RELCOUNT",
+        DT_FLAGS_1 => "FLAGS_1",
+        0x6ffffffc => "VERDEF",
+        0x6ffffffd => "VERDEFNUM",
+        0x6ffffffe => "VERNEED",
+        0x6fffffff => "VERNEEDNUM",
+        _ => return format!("{tag:#x}"),
+    }
+    .to_string()
+}
+
+/// Name of a symbol binding.
+pub fn symbol_bind_name(bind: u8) -> String {
+    match bind {
+        0 => "LOCAL",
+        1 => "GLOBAL",
+        2 => "WEAK",
+        10 => "UNIQUE",
+        _ => return bind.to_string(),
+    }
+    .to_string()
+}
+
+/// Name of a symbol type.
+pub fn symbol_kind_name(kind: u8) -> String {
+    match kind {
+        0 => "NOTYPE",
+        1 => "OBJECT",
+        2 => "FUNC",
+        3 => "SECTION",
+        4 => "FILE",
+        5 => "COMMON",
+        6 => "TLS",
+        10 => "IFUNC",
+        _ => return kind.to_string(),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x400000;
+    const DYNSTR: u64 = 304;
+    const SHOFF: u64 = 472;
+
+    fn put(file: &mut [u8], at: u64, bytes: &[u8]) {
+        file[at as usize..at as usize + bytes.len()].copy_from_slice(bytes);
+    }
+
+    // Shared object of x86-64 with interpreter, a needed library, a build id and a symbol; as "cc -pie" but small
+    fn fixture() -> Vec<u8> {
+        let mut file = vec![0u8; SHOFF as usize + 4 * 64];
+        put(&mut file, 0, &[0x7f, b'E', b'L', b'F', 2, 1, 1]);
+        put(&mut file, 16, &ET_DYN.to_le_bytes());
+        put(&mut file, 18, &62u16.to_le_bytes());
+        put(&mut file, 20, &1u32.to_le_bytes());
+        put(&mut file, 24, &(BASE + 0x1000).to_le_bytes());
+        put(&mut file, 32, &64u64.to_le_bytes());
+        put(&mut file, 40, &SHOFF.to_le_bytes());
+        // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum and e_shstrndx
+        for (i, value) in [64u16, 56, 4, 64, 4, 2].iter().enumerate() {
+            put(&mut file, 52 + 2 * i as u64, &value.to_le_bytes());
+        }
+
+        // LOAD of the whole file, INTERP, DYNAMIC and NOTE
+        let length = file.len() as u64;
+        let segments = [(PT_LOAD, 0u64, length), (PT_INTERP, 288, 11), (PT_DYNAMIC, 320, 48), (PT_NOTE, 368, 20)];
+        for (i, (kind, offset, size)) in segments.iter().enumerate() {
+            let at = 64 + 56 * i as u64;
+            put(&mut file, at, &kind.to_le_bytes());
+            put(&mut file, at + 4, &4u32.to_le_bytes());
+            put(&mut file, at + 8, &offset.to_le_bytes());
+            put(&mut file, at + 16, &(BASE + offset).to_le_bytes());
+            put(&mut file, at + 32, &size.to_le_bytes());
+            put(&mut file, at + 48, &4u64.to_le_bytes());
+        }
+        put(&mut file, 288, b"/lib/ld.so\0");
+        put(&mut file, DYNSTR, b"\0libc.so.6\0main\0");
+        put(&mut file, 320, &DT_NEEDED.to_le_bytes());
+        put(&mut file, 328, &1u64.to_le_bytes());
+        put(&mut file, 336, &DT_STRTAB.to_le_bytes());
+        put(&mut file, 344, &(BASE + DYNSTR).to_le_bytes());
+        put(&mut file, 368, &[4, 0, 0, 0, 4, 0, 0, 0, 3, 0, 0, 0, b'G', b'N', b'U', 0, 0xde, 0xad, 0xbe, 0xef]);
+        put(&mut file, 392, b"\0.dynstr\0.shstrtab\0.symtab\0");
+        // The second symbol, "main"; global function
+        put(&mut file, 448, &11u32.to_le_bytes());
+        put(&mut file, 452, &[0x12, 0, 1, 0]);
+        put(&mut file, 456, &(BASE + 0x1000).to_le_bytes());
+        put(&mut file, 464, &16u64.to_le_bytes());
+
+        // Null, ".dynstr", ".shstrtab" and ".symtab" linked to ".dynstr"
+        let sections = [(1u32, 3u32, DYNSTR, 16u64, 0u32), (9, 3, 392, 27, 0), (19, SHT_SYMTAB, 424, 48, 1)];
+        for (i, (name, kind, offset, size, link)) in sections.iter().enumerate() {
+            let at = SHOFF + 64 * (i as u64 + 1);
+            put(&mut file, at, &name.to_le_bytes());
+            put(&mut file, at + 4, &kind.to_le_bytes());
+            put(&mut file, at + 24, &offset.to_le_bytes());
+            put(&mut file, at + 32, &size.to_le_bytes());
+            put(&mut file, at + 40, &link.to_le_bytes());
+        }
+        put(&mut file, SHOFF + 3 * 64 + 56, &24u64.to_le_bytes());
+        file
+    }
+
+    fn error(file: &[u8]) -> String {
+        let error = Elf::parse(file).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        error.to_string()
+    }
+
+    #[test]
+    fn parse() {
+        let elf = Elf::parse(&fixture()).unwrap();
+        assert_eq!((elf.header.class, elf.header.kind, elf.header.machine), (64, ET_DYN, 62));
+        assert_eq!(elf.segments.len(), 4);
+        assert_eq!(elf.interpreter.as_deref(), Some("/lib/ld.so"));
+        assert_eq!(elf.needed(), ["libc.so.6"]);
+        assert_eq!(elf.build_id().as_deref(), Some("deadbeef"));
+        assert!(elf.is_pie() && !elf.is_static());
+        let names: Vec<&str> = elf.sections.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["", ".dynstr", ".shstrtab", ".symtab"]);
+        assert_eq!(elf.symbols[1].name, "main");
+        assert_eq!((elf.symbols[1].bind, elf.symbols[1].kind), (1, 2));
+        assert_eq!(elf.section_name(elf.symbols[1].section), ".dynstr");
+        assert_eq!(elf.offset_of(BASE + DYNSTR), Some(DYNSTR));
+        assert_eq!(elf.offset_of(BASE - 1), None);
+    }
+
+    #[test]
+    fn truncated() {
+        let file = fixture();
+        assert_eq!(error(b"\x7fELF"), "unknown ELF class");
+        assert_eq!(error(&file[..40]), "offset out of the file");
+        // Without the section headers
+        assert_eq!(error(&file[..SHOFF as usize + 10]), "offset out of the file");
+        assert_eq!(error(b"MZ\x90\0"), "not an ELF file");
+    }
+
+    #[test]
+    fn overflows() {
+        // e_shoff at the end of the 64 bits, read for the number of sections
+        let mut file = fixture();
+        put(&mut file, 40, &(u64::MAX - 8).to_le_bytes());
+        put(&mut file, 60, &0u16.to_le_bytes());
+        assert_eq!(error(&file), "offset out of the file");
+
+        // The DYNAMIC segment at the end of the 64 bits
+        let mut file = fixture();
+        put(&mut file, 64 + 2 * 56 + 8, &(u64::MAX - 16).to_le_bytes());
+        assert_eq!(error(&file), "offset out of the file");
+
+        // A LOAD segment at the end of the 64 bits has no offset for its addresses
+        let mut file = fixture();
+        put(&mut file, 64 + 8, &(u64::MAX - 8).to_le_bytes());
+        let elf = Elf::parse(&file).unwrap();
+        assert_eq!(elf.offset_of(BASE + DYNSTR), None);
+        assert!(elf.needed().is_empty());
+
+        // The name of a symbol, a section and a library out of the 64 bits are empty
+        let mut file = fixture();
+        put(&mut file, 448, &u32::MAX.to_le_bytes());
+        put(&mut file, SHOFF + 2 * 64 + 24, &(u64::MAX - 8).to_le_bytes());
+        put(&mut file, 328, &u64::MAX.to_le_bytes());
+        let elf = Elf::parse(&file).unwrap();
+        assert!(elf.sections.iter().all(|e| e.name.is_empty()));
+        assert_eq!(elf.symbols[1].name, "");
+        assert_eq!(elf.dynamic[0].text, None);
+    }
+
+    #[test]
+    fn is_static() {
+        // Without INTERP and with DT_FLAGS_1 instead of DT_NEEDED
+        let mut file = fixture();
+        put(&mut file, 64 + 56, &0u32.to_le_bytes());
+        put(&mut file, 320, &DT_FLAGS_1.to_le_bytes());
+        put(&mut file, 328, &0u64.to_le_bytes());
+        // A shared library is not static, even without interpreter and libraries
+        let elf = Elf::parse(&file).unwrap();
+        assert!(elf.interpreter.is_none() && elf.needed().is_empty());
+        assert!(!elf.is_pie() && !elf.is_static());
+
+        // Static PIE
+        put(&mut file, 328, &DF_1_PIE.to_le_bytes());
+        let elf = Elf::parse(&file).unwrap();
+        assert!(elf.is_pie() && elf.is_static());
+
+        // Executable
+        put(&mut file, 16, &ET_EXEC.to_le_bytes());
+        let elf = Elf::parse(&file).unwrap();
+        assert!(!elf.is_pie() && elf.is_static());
+
+        // Relocatable object
+        put(&mut file, 16, &1u16.to_le_bytes());
+        assert!(!Elf::parse(&file).unwrap().is_static());
+    }
+}
@@ -0,0 +1,521 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Binary data as text (RFC 4648); base64, base64 with the URL and file name alphabet, base32 and hex.
+//!
+//! Each symbol carries 6, 5 or 4 bits, the bytes are taken in groups of 3, 5 or 1 which give 4, 8 or 2 symbols,
+//! and the last group is completed with '='. MIME (RFC 2045) is base64 in lines of 76 symbols ended by CRLF.
+//! Decoding is strict; a byte out of the alphabet, padding in the middle or of wrong length, a length which can not
+//! come from any data and unused bits which are not zero are errors, so each data has only one valid text.
+//! Hex and base32 are decoded in upper or lower case.
+//!
+//! Vectors of the RFC 4648, section 10;
+//!
+//! ```text
+//! data        base64      base32              hex
+//! ""          ""          ""                  ""
+//! "f"         "Zg=="      "MY======"          "66"
+//! "fo"        "Zm8="      "MZXQ===="          "666f"
+//! "foo"       "Zm9v"      "MZXW6==="          "666f6f"
+//! "foob"      "Zm9vYg=="  "MZXW6YQ="          "666f6f62"
+//! "fooba"     "Zm9vYmE="  "MZXW6YTB"          "666f6f6261"
+//! "foobar"    "Zm9vYmFy"  "MZXW6YTBOI======"  "666f6f626172"
+//! ```
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Bytes read or encoded each time by the streams
+const BLOCK: usize = 8192;
+
+const PAD: u8 = b'=';
+
+/// Alphabet and format of an encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Encoding {
+    alphabet: &'static [u8],
+    // Bits of each symbol; 6, 5 or 4
+    bits: u32,
+    padding: bool,
+    // Symbols by line, 0 for one line
+    wrap: usize,
+    separator: &'static str,
+}
+
+/// Standard base64, with padding.
+pub const BASE64: Encoding = Encoding {
+    alphabet: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+    bits: 6,
+    padding: true,
+    wrap: 0,
+    separator: "",
+};
+
+/// Base64 with '-' and '_' instead of '+' and '/', safe in URLs and file names.
+pub const BASE64_URL: Encoding = Encoding {
+    alphabet: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+    ..BASE64
+};
+
+/// Base64 in lines of 76 symbols ended by CRLF, as in mails.
+pub const BASE64_MIME: Encoding = BASE64.wrap(76, "\r\n");
+
+pub const BASE32: Encoding = Encoding {
+    alphabet: b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567",
+    bits: 5,
+    padding: true,
+    wrap: 0,
+    separator: "",
+};
+
+/// Hexadecimal in lower case.
+pub const HEX: Encoding = Encoding {
+    alphabet: b"0123456789abcdef",
+    bits: 4,
+    padding: false,
+    wrap: 0,
+    separator: "",
+};
+
+/// Error decoding, the offsets are in the text without the line separators.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// Byte out of the alphabet.
+    InvalidByte { offset: usize, byte: u8 },
+    /// Number of symbols which can not come from any data.
+    InvalidLength { length: usize },
+    /// Padding before the end, or more or less than needed.
+    InvalidPadding { offset: usize },
+    /// The bits of the last symbol which are not data are not zero.
+    TrailingBits { offset: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::InvalidByte { offset, byte } => {
+                if byte.is_ascii_graphic() {
+                    write!(f, "invalid character '{}' at offset {offset}", *byte as char)
+                } else {
+                    write!(f, "invalid byte 0x{byte:02x} at offset {offset}")
+                }
+            }
+            DecodeError::InvalidLength { length } => write!(f, "invalid length, {length} symbols can not come from any data"),
+            DecodeError::InvalidPadding { offset } => write!(f, "invalid padding at offset {offset}"),
+            DecodeError::TrailingBits { offset } => write!(f, "unused bits are not zero at offset {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for io::Error {
+    fn from(error: DecodeError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl Encoding {
+    /// With or without '=' at the end; without it, the decoder does not accept it.
+    pub const fn padding(mut self, padding: bool) -> Encoding {
+        self.padding = padding;
+        self
+    }
+
+    /// Lines of "width" symbols ended by "separator", 0 for one line. The decoder skips '\r' and '\n'.
+    pub const fn wrap(mut self, width: usize, separator: &'static str) -> Encoding {
+        self.wrap = width;
+        self.separator = separator;
+        self
+    }
+
+    // Symbols of a whole group and bytes they carry; 4 and 3, 8 and 5, 2 and 1
+    fn group(&self) -> (usize, usize) {
+        match self.bits {
+            6 => (4, 3),
+            5 => (8, 5),
+            _ => (2, 1),
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> String {
+        let mut text = Vec::new();
+        let mut column = 0;
+        self.wrap_into(&self.symbols(data, true), &mut column, &mut text);
+        // Only symbols of the alphabet, '=' and the separator
+        String::from_utf8_lossy(&text).into_owned()
+    }
+
+    pub fn decode<T: AsRef<[u8]>>(&self, text: T) -> Result<Vec<u8>, DecodeError> {
+        let mut data = Vec::new();
+        self.decode_symbols(&self.strip(text.as_ref()), 0, &mut data)?;
+        Ok(data)
+    }
+
+    // Symbols of the data, with the last group completed if "last"
+    fn symbols(&self, data: &[u8], last: bool) -> Vec<u8> {
+        let mask = (1u32 << self.bits) - 1;
+        let mut symbols = Vec::with_capacity(data.len() * 8 / self.bits as usize + 8);
+        let mut accumulator: u32 = 0;
+        let mut bits = 0;
+        for byte in data {
+            accumulator = (accumulator << 8) | *byte as u32;
+            bits += 8;
+            while bits >= self.bits {
+                bits -= self.bits;
+                symbols.push(self.alphabet[((accumulator >> bits) & mask) as usize]);
+            }
+        }
+        if bits > 0 {
+            symbols.push(self.alphabet[((accumulator << (self.bits - bits)) & mask) as usize]);
+        }
+        let (group, _) = self.group();
+        if last && self.padding {
+            while !symbols.len().is_multiple_of(group) {
+                symbols.push(PAD);
+            }
+        }
+        symbols
+    }
+
+    // Adds the symbols to "text" with the separator after each "wrap" of them
+    fn wrap_into(&self, symbols: &[u8], column: &mut usize, text: &mut Vec<u8>) {
+        if self.wrap == 0 {
+            text.extend_from_slice(symbols);
+            return;
+        }
+        for symbol in symbols {
+            if *column == self.wrap {
+                text.extend_from_slice(self.separator.as_bytes());
+                *column = 0;
+            }
+            text.push(*symbol);
+            *column += 1;
+        }
+    }
+
+    // The text without the line breaks of the wrapping
+    fn strip(&self, text: &[u8]) -> Vec<u8> {
+        if self.wrap == 0 {
+            return text.to_vec();
+        }
+        text.iter().copied().filter(|e| *e != b'\r' && *e != b'\n').collect()
+    }
+
+    // Value of each byte, 0xff for the ones out of the alphabet
+    fn values(&self) -> [u8; 256] {
+        let mut values = [0xff; 256];
+        let both_cases = self.alphabet.iter().any(|e| e.is_ascii_lowercase()) && self.alphabet.iter().any(|e| e.is_ascii_uppercase());
+        for (i, symbol) in self.alphabet.iter().enumerate() {
+            values[*symbol as usize] = i as u8;
+            if !both_cases {
+                values[symbol.to_ascii_lowercase() as usize] = i as u8;
+                values[symbol.to_ascii_uppercase() as usize] = i as u8;
+            }
+        }
+        values
+    }
+
+    // Every byte must be a symbol or '=' before the padding and the length are checked; returns the values
+    fn check_bytes(&self, text: &[u8], offset: usize) -> Result<[u8; 256], DecodeError> {
+        let values = self.values();
+        match text.iter().position(|e| *e != PAD && values[*e as usize] == 0xff) {
+            Some(i) => Err(DecodeError::InvalidByte {
+                offset: offset + i,
+                byte: text[i],
+            }),
+            None => Ok(values),
+        }
+    }
+
+    // Decodes symbols which are the end of the text, "offset" is where they start in it
+    fn decode_symbols(&self, text: &[u8], offset: usize, data: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let (group, _) = self.group();
+        let values = self.check_bytes(text, offset)?;
+        let length = text.iter().position(|e| *e == PAD).unwrap_or(text.len());
+        if let Some(i) = text[length..].iter().position(|e| *e != PAD) {
+            return Err(DecodeError::InvalidPadding { offset: offset + length + i });
+        }
+        let padding = text.len() - length;
+        if padding > 0 && (!self.padding || !text.len().is_multiple_of(group) || padding >= group) {
+            return Err(DecodeError::InvalidPadding { offset: offset + length });
+        }
+        if self.padding && padding == 0 && !length.is_multiple_of(group) {
+            return Err(DecodeError::InvalidPadding { offset: offset + length });
+        }
+        // The bits left can not be a whole symbol, so the last one is not only padding
+        if (length * self.bits as usize) % 8 >= self.bits as usize {
+            return Err(DecodeError::InvalidLength { length });
+        }
+
+        let mut accumulator: u32 = 0;
+        let mut bits = 0;
+        for symbol in &text[..length] {
+            accumulator = (accumulator << self.bits) | values[*symbol as usize] as u32;
+            bits += self.bits;
+            if bits >= 8 {
+                bits -= 8;
+                data.push((accumulator >> bits) as u8);
+            }
+        }
+        if accumulator & ((1 << bits) - 1) != 0 {
+            return Err(DecodeError::TrailingBits {
+                offset: offset + length - 1,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Writes the data encoded, "finish" writes the last group and returns the writer.
+pub struct BaseEncoder<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    // Bytes which are not a whole group yet
+    pending: Vec<u8>,
+    column: usize,
+}
+
+impl<W: Write> BaseEncoder<W> {
+    pub fn new(inner: W, encoding: Encoding) -> BaseEncoder<W> {
+        BaseEncoder {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            column: 0,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut text = Vec::new();
+        self.encoding
+            .wrap_into(&self.encoding.symbols(&self.pending, true), &mut self.column, &mut text);
+        self.inner.write_all(&text)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BaseEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (_, bytes) = self.encoding.group();
+        for chunk in buf.chunks(BLOCK) {
+            self.pending.extend_from_slice(chunk);
+            let whole = self.pending.len() / bytes * bytes;
+            let mut text = Vec::new();
+            self.encoding
+                .wrap_into(&self.encoding.symbols(&self.pending[..whole], false), &mut self.column, &mut text);
+            self.pending.drain(..whole);
+            self.inner.write_all(&text)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads the data of encoded text, errors in it are "InvalidData" with a "DecodeError" inside.
+pub struct BaseDecoder<R: Read> {
+    inner: R,
+    encoding: Encoding,
+    // Symbols which are not a whole group yet, or the padding
+    text: Vec<u8>,
+    // Offset of the first of them in the text
+    offset: usize,
+    data: Vec<u8>,
+    at: usize,
+    eof: bool,
+}
+
+impl<R: Read> BaseDecoder<R> {
+    pub fn new(inner: R, encoding: Encoding) -> BaseDecoder<R> {
+        BaseDecoder {
+            inner,
+            encoding,
+            text: Vec::new(),
+            offset: 0,
+            data: Vec::new(),
+            at: 0,
+            eof: false,
+        }
+    }
+
+    // Decodes the whole groups of the next block of text, or the rest at the end
+    fn fill(&mut self) -> io::Result<()> {
+        self.data.clear();
+        self.at = 0;
+        let mut block = [0u8; BLOCK];
+        let read = loop {
+            match self.inner.read(&mut block) {
+                Ok(d) => break d,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if read == 0 {
+            self.eof = true;
+            let text = std::mem::take(&mut self.text);
+            return Ok(self.encoding.decode_symbols(&text, self.offset, &mut self.data)?);
+        }
+        self.text.extend(self.encoding.strip(&block[..read]));
+        self.encoding.check_bytes(&self.text, self.offset)?;
+
+        // After the padding only more padding can come, the rest waits for the end
+        let (group, _) = self.encoding.group();
+        let length = self.text.iter().position(|e| *e == PAD).unwrap_or(self.text.len());
+        if let Some(i) = self.text[length..].iter().position(|e| *e != PAD) {
+            return Err(DecodeError::InvalidPadding {
+                offset: self.offset + length + i,
+            }
+            .into());
+        }
+        let whole = length / group * group;
+        self.encoding.decode_symbols(&self.text[..whole], self.offset, &mut self.data)?;
+        self.text.drain(..whole);
+        self.offset += whole;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BaseDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.at == self.data.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let length = buf.len().min(self.data.len() - self.at);
+        buf[..length].copy_from_slice(&self.data[self.at..self.at + length]);
+        self.at += length;
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4648, section 10
+    const VECTORS: [(&str, &str, &str, &str); 7] = [
+        ("", "", "", ""),
+        ("f", "Zg==", "MY======", "66"),
+        ("fo", "Zm8=", "MZXQ====", "666f"),
+        ("foo", "Zm9v", "MZXW6===", "666f6f"),
+        ("foob", "Zm9vYg==", "MZXW6YQ=", "666f6f62"),
+        ("fooba", "Zm9vYmE=", "MZXW6YTB", "666f6f6261"),
+        ("foobar", "Zm9vYmFy", "MZXW6YTBOI======", "666f6f626172"),
+    ];
+
+    // Gives one byte each time, to decode the groups split between reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.is_empty()) {
+                (Some((byte, rest)), false) => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn stream_encode(encoding: Encoding, data: &[u8]) -> String {
+        let mut encoder = BaseEncoder::new(Vec::new(), encoding);
+        for byte in data {
+            encoder.write_all(&[*byte]).unwrap();
+        }
+        String::from_utf8(encoder.finish().unwrap()).unwrap()
+    }
+
+    fn stream_decode(encoding: Encoding, text: &str) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        BaseDecoder::new(Trickle(text.as_bytes()), encoding).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn check(encoding: Encoding, data: &str, text: &str) {
+        assert_eq!(encoding.encode(data.as_bytes()), text);
+        assert_eq!(encoding.decode(text).unwrap(), data.as_bytes());
+        assert_eq!(stream_encode(encoding, data.as_bytes()), text);
+        assert_eq!(stream_decode(encoding, text).unwrap(), data.as_bytes());
+    }
+
+    #[test]
+    fn rfc_vectors() {
+        for (data, base64, base32, hex) in VECTORS {
+            check(BASE64, data, base64);
+            check(BASE64_URL, data, base64);
+            check(BASE32, data, base32);
+            check(HEX, data, hex);
+            assert_eq!(BASE32.decode(base32.to_lowercase()).unwrap(), data.as_bytes());
+            assert_eq!(HEX.decode(hex.to_uppercase()).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn alphabets_and_padding() {
+        assert_eq!(BASE64.encode(&[0xfb, 0xff]), "+/8=");
+        assert_eq!(BASE64_URL.encode(&[0xfb, 0xff]), "-_8=");
+        assert_eq!(BASE64.padding(false).encode(b"f"), "Zg");
+        assert_eq!(BASE64.padding(false).decode("Zm8").unwrap(), b"fo");
+        assert_eq!(BASE64.padding(false).decode("Zm8="), Err(DecodeError::InvalidPadding { offset: 3 }));
+        assert_eq!(BASE64.decode("Zm8"), Err(DecodeError::InvalidPadding { offset: 3 }));
+    }
+
+    #[test]
+    fn mime_lines() {
+        let data = [7u8; 100];
+        let text = BASE64_MIME.encode(&data);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(lines.iter().map(|e| e.len()).collect::<Vec<usize>>(), [76, 60]);
+        assert_eq!(stream_encode(BASE64_MIME, &data), text);
+        assert_eq!(BASE64_MIME.decode(&text).unwrap(), data);
+        assert_eq!(stream_decode(BASE64_MIME, &text).unwrap(), data);
+    }
+
+    #[test]
+    fn errors() {
+        // The bytes are checked before the length and the padding
+        assert_eq!(BASE64.decode("Zm9v\n"), Err(DecodeError::InvalidByte { offset: 4, byte: b'\n' }));
+        assert_eq!(BASE64.decode("Zm==\n"), Err(DecodeError::InvalidByte { offset: 4, byte: b'\n' }));
+        assert_eq!(BASE64.decode("Zm9v!"), Err(DecodeError::InvalidByte { offset: 4, byte: b'!' }));
+        assert_eq!(HEX.decode("6g"), Err(DecodeError::InvalidByte { offset: 1, byte: b'g' }));
+        assert_eq!(BASE64.decode("Zg=a"), Err(DecodeError::InvalidPadding { offset: 3 }));
+        assert_eq!(BASE64.decode("Zg="), Err(DecodeError::InvalidPadding { offset: 2 }));
+        assert_eq!(BASE64.decode("Z==="), Err(DecodeError::InvalidLength { length: 1 }));
+        assert_eq!(BASE64.padding(false).decode("Z"), Err(DecodeError::InvalidLength { length: 1 }));
+        assert_eq!(HEX.decode("666"), Err(DecodeError::InvalidLength { length: 3 }));
+        assert_eq!(BASE64.decode("Zh=="), Err(DecodeError::TrailingBits { offset: 1 }));
+        assert_eq!(BASE32.decode("MZ======"), Err(DecodeError::TrailingBits { offset: 1 }));
+    }
+
+    #[test]
+    fn stream_errors() {
+        let error = |text: &str| {
+            let error = stream_decode(BASE64, text).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            error.into_inner().unwrap().downcast::<DecodeError>().map(|e| *e).unwrap()
+        };
+        assert_eq!(error("Zm9vYg==\n"), DecodeError::InvalidByte { offset: 8, byte: b'\n' });
+        assert_eq!(error("Zm9vYg=a"), DecodeError::InvalidPadding { offset: 7 });
+        assert_eq!(error("Zm9vYh=="), DecodeError::TrailingBits { offset: 5 });
+        assert_eq!(error("Zm9vY"), DecodeError::InvalidPadding { offset: 5 });
+    }
+}
@@ -12,16 +12,33 @@
 //!
 //! Copyright; Joaquin "ShyanJMC" Crespo - 2022
 
-/// Standard environment variable
-use std::env::var;
 /// Standard files and read libs
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 /// Standard path
-use std::path::PathBuf;
+use std::path::Path;
+/// C types and strings, used to talk with the system C library
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_ulong};
+use std::os::unix::ffi::OsStrExt;
 
+extern crate libcompress;
 extern crate libstream;
-use libstream::Stream;
+use libcompress::{GzDecoder, GZIP_MAGIC};
+
+mod elf;
+mod encoding;
+mod hash;
+mod sniff;
+mod which;
+pub use elf::{
+    dynamic_tag_name, section_kind_name, segment_kind_name, symbol_bind_name, symbol_kind_name, DynamicEntry, Elf,
+    ElfHeader, Note, ProgramHeader, Section, Symbol,
+};
+pub use encoding::{BaseDecoder, BaseEncoder, DecodeError, Encoding, BASE32, BASE64, BASE64_MIME, BASE64_URL, HEX};
+pub use hash::{hash_file, hash_reader, to_hex, Blake2b, HashAlgorithm, Sha256, Sha512};
+pub use sniff::{elf_kind_name, elf_machine_name, sniff, sniff_file, sniff_reader, FileType, TextEncoding};
+pub use which::{which, which_all, CommandCache};
 
 /// Trait to work with files' datas and information.
 pub trait RavnSizeFile {
@@ -63,157 +80,129 @@ impl RavnSizeFile for u64 {
 }
 
 impl RavnFile for File {
-    /// Detect if file is binary or not
+    /// Detect if file is binary or not, by its first block.
+    /// Text in UTF-8 or UTF-16 with BOM, or in other 8 bits encoding, is not binary.
     fn is_binary(&self) -> bool {
         let mut file = self;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .expect("is_binary function: error to read file");
-
-        for byte in &buffer {
-            // If first value is more 127 in size is a binary
-            if *byte > 127 {
-                return true;
-            }
+        match sniff_reader(&mut file) {
+            Ok(d) => !d.is_text(),
+            Err(_e) => false,
         }
-        return false;
     }
 
-    // Honestly, I needed help with the movement of bits ("<<" ">>") on octets because of that ChatGPT helped me a lot with
-    // the "while" loop.
+    /// The file's content in standard base64, read by parts.
     fn encode_base64(&self) -> String {
-        let base64_chars: Vec<char> = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
-            .chars()
-            .collect();
-        let mut i = 0;
-        let mut input = Vec::new();
-        let mut output = String::new();
-
-        {
-            let mut file = self;
-            match file.read_to_end(&mut input) {
-                Ok(_) => (),
-                Err(e) => return format!("Error reading file: {}", e),
-            };
+        let mut file = self;
+        let mut encoder = BaseEncoder::new(Vec::new(), BASE64);
+        match io::copy(&mut file, &mut encoder).and_then(|_| encoder.finish()) {
+            Ok(d) => String::from_utf8_lossy(&d).into_owned(),
+            Err(e) => format!("Error reading file: {}", e),
         }
+    }
+}
 
-        while i < input.len() {
-            let octet_a;
-            let mut octet_b = 0u8;
-            let mut octet_c = 0u8;
-
-            octet_a = input[i];
-
-            if i + 1 < input.len() {
-                octet_b = input[i + 1];
-            }
-
-            if i + 2 < input.len() {
-                octet_c = input[i + 2];
-            }
-
-            let triple = (u32::from(octet_a) << 16) | (u32::from(octet_b) << 8) | u32::from(octet_c);
-
-            for j in (0..4).rev() {
-                let index = (triple >> (6 * j)) & 0x3F;
-                output.push(base64_chars[index as usize]);
-            }
-
-            i += 3;
-        }
+/// Filesystem information returned by "statvfs".
+/// Sizes are in bytes.
+#[derive(Clone, Debug)]
+pub struct FsStat {
+    pub block_size: u64,
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub files: u64,
+    pub files_free: u64,
+}
 
-        let len = output.len();
+// The "statvfs" structure of Linux's C libraries (glibc and musl) in 64 bits. In 32 bits "fsblkcnt_t" and
+// "fsfilcnt_t" depend on the library and "f_fsid" has padding, so that layout is not written.
+#[cfg(not(target_pointer_width = "64"))]
+compile_error!("libfile's statvfs structure is only written for 64 bits targets");
+
+#[allow(non_camel_case_types)]
+type fsblkcnt_t = u64;
+#[allow(non_camel_case_types)]
+type fsfilcnt_t = u64;
+
+#[repr(C)]
+struct CStatvfs {
+    f_bsize: c_ulong,
+    f_frsize: c_ulong,
+    f_blocks: fsblkcnt_t,
+    f_bfree: fsblkcnt_t,
+    f_bavail: fsblkcnt_t,
+    f_files: fsfilcnt_t,
+    f_ffree: fsfilcnt_t,
+    f_favail: fsfilcnt_t,
+    f_fsid: c_ulong,
+    f_flag: c_ulong,
+    f_namemax: c_ulong,
+    f_spare: [c_int; 6],
+}
 
-        match len % 4 {
-            0 => (),
-            1 => output.push('='),
-            2 => output.push_str("=="),
-            _ => unreachable!(),
-        }
+extern "C" {
+    // The Rust std does not expose it, but the C library is always linked by std.
+    fn statvfs(path: *const c_char, buf: *mut CStatvfs) -> c_int;
+}
 
-        output
+/// Get the size, free and available space of the filesystem that contains "path".
+pub fn fs_stat(path: &Path) -> Result<FsStat, io::Error> {
+    let cpath = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(d) => d,
+        Err(_e) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte")),
+    };
+    let mut buf = CStatvfs {
+        f_bsize: 0,
+        f_frsize: 0,
+        f_blocks: 0,
+        f_bfree: 0,
+        f_bavail: 0,
+        f_files: 0,
+        f_ffree: 0,
+        f_favail: 0,
+        f_fsid: 0,
+        f_flag: 0,
+        f_namemax: 0,
+        f_spare: [0; 6],
+    };
+    // SAFETY: "cpath" is a valid NUL terminated string and "buf" is a valid statvfs struct
+    // which lives during the call.
+    let ret = unsafe { statvfs(cpath.as_ptr(), &mut buf) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
     }
-
+    // Blocks are counted in fragment size, which is 0 in some old kernels.
+    let bsize = if buf.f_frsize != 0 { buf.f_frsize } else { buf.f_bsize };
+    Ok(FsStat {
+        block_size: bsize,
+        total: buf.f_blocks * bsize,
+        free: buf.f_bfree * bsize,
+        available: buf.f_bavail * bsize,
+        files: buf.f_files,
+        files_free: buf.f_ffree,
+    })
 }
 
-// As which, detects where in PATH is located the binary
-
-pub fn which(binary: String) -> Vec<String> {
-    let mut results: Vec<String> = Vec::new();
-    // Get the environment variable PATH's value
-    let paths = String::from(var("PATH").expect("Error getting PATH environment variable."));
-    // Split each path and save it into a vector of str, because the return is provided by split we know the size when is passed to collect because
-    // of that we can use str instead of String.
-    let inv_paths: Vec<&str> = paths.split(':').collect();
-    // Read each directory
-    let mut entries: Vec<PathBuf> = Vec::new();
-    for ivalue in inv_paths {
-        for i in ivalue.to_string().readdir() {
-            entries.push(i.clone());
-
-            if i.clone().file_name().unwrap().to_os_string().into_string().unwrap() == binary {
-                results.push(i.display().to_string());
-            }
+/// Open a file for reading, if it is compressed with gzip (like rotated logs)
+/// the reader returns the decompressed content.
+pub fn open_decompressed(path: &Path) -> Result<Box<dyn Read>, io::Error> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 2];
+    let mut readed = 0;
+    while readed < magic.len() {
+        match file.read(&mut magic[readed..])? {
+            0 => break,
+            n => readed += n,
         }
     }
-    results
+    file.seek(SeekFrom::Start(0))?;
+    if readed == magic.len() && magic == GZIP_MAGIC {
+        Ok(Box::new(GzDecoder::new(BufReader::new(file))))
+    } else {
+        Ok(Box::new(file))
+    }
 }
 
-
-// Here ChatGPT helped me again with bits movement
 // Vec<u8> are the bits, and String if the error if be
 pub fn decode_base64(base64: &String) -> Result<Vec<u8>, String> {
-    const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
-                                   abcdefghijklmnopqrstuvwxyz\
-                                       0123456789+/";
-
-    let error_str: &str = "Error or invalid base64 character";
-    let endata: &str = "unexpected end of data";
-    let input = base64.clone();
-    let mut output = Vec::new();
-    let mut chars = input.chars().peekable();
-
-    while let Some(c1) = chars.next() {
-        if c1 == '=' {
-            break; // padding character, end of data
-        }
-        let i1 = BASE64_ALPHABET.iter().position(|&x| x == c1 as u8).ok_or_else(|| error_str.to_string()).unwrap();
-
-        let c2 = chars.next().ok_or(&endata).unwrap();
-        if c2 == '=' {
-            break; // padding character, end of data
-        }
-        let i2 = BASE64_ALPHABET.iter().position(|&x| x == c2 as u8).ok_or_else(|| error_str.to_string()).unwrap();
-
-        let c3 = chars.next().ok_or(&endata).unwrap();
-        let i3 = if c3 == '=' {
-            0 // padding character, equivalent to zero bits
-        } else {
-            BASE64_ALPHABET.iter().position(|&x| x == c3 as u8).ok_or(&error_str).unwrap()
-        };
-
-        let c4 = chars.next().ok_or(&endata).unwrap();
-        let i4 = if c4 == '=' {
-            0 // padding character, equivalent to zero bits
-        } else {
-            BASE64_ALPHABET.iter().position(|&x| x == c4 as u8).ok_or(&error_str).unwrap()
-        };
-
-        let byte1 = (i1 << 2) | (i2 >> 4);
-        let byte2 = ((i2 & 0x0f) << 4) | (i3 >> 2);
-        let byte3 = ((i3 & 0x03) << 6) | i4;
-        output.push(byte1);
-        if c3 != '=' {
-            output.push(byte2);
-        }
-        if c4 != '=' {
-            output.push(byte3);
-        }
-    }
-
-    // Convertir el vector de usize a u8
-    let output_u8: Vec<u8> = output.iter().map(|&x| x as u8).collect();
-
-    // Devolver el resultado como u8
-    Ok(output_u8)
+    BASE64.decode(base64.trim()).map_err(|e| e.to_string())
 }
@@ -0,0 +1,422 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Type of a file by its first bytes.
+//!
+//! Only the first 8 KiB are read. The magic numbers are checked first; ELF, gzip, PNG, device tree blobs,
+//! kernel images (arm64, RISC-V, x86 bzImage and arm zImage) and tar archives, by "ustar" or by the checksum of
+//! the header. The rest is text if it has a BOM of UTF-8 or UTF-16 and is valid in it, or if it has no NUL bytes
+//! and is valid UTF-8; bytes which are not UTF-8 without control characters are text in an unknown 8 bits encoding.
+//! A multibyte character cut at the end of the block is not an error, the file goes on after it.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use libcompress::GZIP_MAGIC;
+use libstream::{is_binary, BINARY_BLOCK};
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const DTB_MAGIC: [u8; 4] = [0xd0, 0x0d, 0xfe, 0xed];
+const UTF8_BOM: [u8; 3] = [0xef, 0xbb, 0xbf];
+
+/// Encoding of a text file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextEncoding {
+    Ascii,
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Not UTF-8, as ISO-8859-1 or Windows-1252.
+    Unknown8Bit,
+}
+
+impl TextEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Ascii => "ascii",
+            TextEncoding::Utf8 | TextEncoding::Utf8Bom => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+            TextEncoding::Unknown8Bit => "unknown 8 bits",
+        }
+    }
+}
+
+/// What the content of a file is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileType {
+    Empty,
+    Text(TextEncoding),
+    /// Nothing known.
+    Data,
+    /// "class" is 32 or 64, "kind" and "machine" are "e_type" and "e_machine".
+    Elf {
+        class: u8,
+        little_endian: bool,
+        kind: u16,
+        machine: u16,
+    },
+    /// The ELF magic without a whole header, or with an unknown class; a file cut while it was written.
+    TruncatedElf,
+    /// With the original name if the header has it.
+    Gzip { name: Option<String> },
+    Tar,
+    Png { width: u32, height: u32 },
+    /// Device tree blob, "size" is the one in its header.
+    Dtb { size: u32, version: u32 },
+    /// Boot image of Linux; "arm64", "riscv", "x86" or "arm".
+    KernelImage { arch: &'static str },
+}
+
+impl FileType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileType::Empty => "empty",
+            FileType::Text(_) => "text",
+            FileType::Data => "data",
+            FileType::Elf { .. } => "elf",
+            FileType::TruncatedElf => "truncated elf",
+            FileType::Gzip { .. } => "gzip",
+            FileType::Tar => "tar",
+            FileType::Png { .. } => "png",
+            FileType::Dtb { .. } => "dtb",
+            FileType::KernelImage { .. } => "kernel image",
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            FileType::Empty => "inode/x-empty",
+            FileType::Text(_) => "text/plain",
+            FileType::Elf { kind: 1, .. } => "application/x-object",
+            FileType::Elf { kind: 3, .. } => "application/x-sharedlib",
+            FileType::Elf { kind: 4, .. } => "application/x-coredump",
+            FileType::Elf { .. } => "application/x-executable",
+            FileType::Gzip { .. } => "application/gzip",
+            FileType::Tar => "application/x-tar",
+            FileType::Png { .. } => "image/png",
+            FileType::Dtb { .. } => "application/x-dtb",
+            FileType::Data | FileType::TruncatedElf | FileType::KernelImage { .. } => "application/octet-stream",
+        }
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self, FileType::Text(_) | FileType::Empty)
+    }
+
+    /// Information of the type as keys and values, after "type" and "mime".
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        let mut details = vec![("type", self.name().to_string()), ("mime", self.mime().to_string())];
+        match self {
+            FileType::Text(encoding) => {
+                details.push(("encoding", encoding.name().to_string()));
+                if matches!(encoding, TextEncoding::Utf8Bom | TextEncoding::Utf16Le | TextEncoding::Utf16Be) {
+                    details.push(("bom", "true".to_string()));
+                }
+            }
+            FileType::Elf {
+                class,
+                little_endian,
+                kind,
+                machine,
+            } => {
+                details.push(("class", class.to_string()));
+                details.push(("endian", if *little_endian { "little" } else { "big" }.to_string()));
+                details.push(("object", elf_kind_name(*kind).to_string()));
+                details.push(("machine", elf_machine_name(*machine)));
+            }
+            FileType::Gzip { name: Some(name) } => details.push(("name", name.clone())),
+            FileType::Png { width, height } => {
+                details.push(("width", width.to_string()));
+                details.push(("height", height.to_string()));
+            }
+            FileType::Dtb { size, version } => {
+                details.push(("size", size.to_string()));
+                details.push(("version", version.to_string()));
+            }
+            FileType::KernelImage { arch } => details.push(("arch", arch.to_string())),
+            FileType::Empty | FileType::Data | FileType::TruncatedElf | FileType::Gzip { name: None } | FileType::Tar => {}
+        }
+        details
+    }
+}
+
+/// Name of the ELF object type, "e_type".
+pub fn elf_kind_name(kind: u16) -> &'static str {
+    match kind {
+        1 => "relocatable",
+        2 => "executable",
+        3 => "shared object",
+        4 => "core",
+        _ => "unknown",
+    }
+}
+
+/// Name of the ELF machine, "e_machine", or its number.
+pub fn elf_machine_name(machine: u16) -> String {
+    match machine {
+        2 => "sparc",
+        3 => "x86",
+        8 => "mips",
+        20 => "powerpc",
+        21 => "powerpc64",
+        22 => "s390",
+        40 => "arm",
+        43 => "sparc64",
+        62 => "x86-64",
+        183 => "aarch64",
+        243 => "riscv",
+        258 => "loongarch",
+        _ => return machine.to_string(),
+    }
+    .to_string()
+}
+
+/// Type of the content by its first bytes, "complete" if they are the whole content.
+pub fn sniff(block: &[u8], complete: bool) -> FileType {
+    if block.is_empty() {
+        return FileType::Empty;
+    }
+    if let Some(d) = sniff_magic(block) {
+        return d;
+    }
+    match sniff_text(block, complete) {
+        Some(d) => FileType::Text(d),
+        None => FileType::Data,
+    }
+}
+
+/// Type of the content of the reader, it reads only the first block.
+pub fn sniff_reader<R: Read>(reader: &mut R) -> io::Result<FileType> {
+    let mut block = Vec::with_capacity(BINARY_BLOCK);
+    reader.take(BINARY_BLOCK as u64).read_to_end(&mut block)?;
+    Ok(sniff(&block, block.len() < BINARY_BLOCK))
+}
+
+pub fn sniff_file(path: &Path) -> io::Result<FileType> {
+    sniff_reader(&mut File::open(path)?)
+}
+
+fn u16_at(block: &[u8], at: usize, little_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = block.get(at..at + 2)?.try_into().ok()?;
+    Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+}
+
+fn u32_at(block: &[u8], at: usize, little_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = block.get(at..at + 4)?.try_into().ok()?;
+    Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+}
+
+fn sniff_magic(block: &[u8]) -> Option<FileType> {
+    if block.starts_with(&ELF_MAGIC) {
+        // EI_CLASS and EI_DATA, then e_type and e_machine in the byte order of the file. Without them it is
+        // still not text, "\x7fELF" are not usual characters
+        let class = match block.get(4) {
+            Some(1) => 32,
+            Some(2) => 64,
+            _ => return Some(FileType::TruncatedElf),
+        };
+        let little_endian = block.get(5) == Some(&1);
+        return Some(match (u16_at(block, 16, little_endian), u16_at(block, 18, little_endian)) {
+            (Some(kind), Some(machine)) => FileType::Elf {
+                class,
+                little_endian,
+                kind,
+                machine,
+            },
+            _ => FileType::TruncatedElf,
+        });
+    }
+    if block.starts_with(&GZIP_MAGIC) {
+        return Some(FileType::Gzip { name: gzip_name(block) });
+    }
+    if block.starts_with(&PNG_MAGIC) {
+        // The first chunk is always IHDR, with the width and height
+        return Some(FileType::Png {
+            width: u32_at(block, 16, false).unwrap_or(0),
+            height: u32_at(block, 20, false).unwrap_or(0),
+        });
+    }
+    if block.starts_with(&DTB_MAGIC) {
+        return Some(FileType::Dtb {
+            size: u32_at(block, 4, false).unwrap_or(0),
+            version: u32_at(block, 20, false).unwrap_or(0),
+        });
+    }
+    if let Some(arch) = kernel_arch(block) {
+        return Some(FileType::KernelImage { arch });
+    }
+    if is_tar(block) {
+        return Some(FileType::Tar);
+    }
+    None
+}
+
+// The header of the kernel boot images, "Documentation/arch/*/booting.rst"
+fn kernel_arch(block: &[u8]) -> Option<&'static str> {
+    if block.get(56..60) == Some(b"ARM\x64") {
+        return Some("arm64");
+    }
+    if block.get(56..60) == Some(b"RSC\x05") || block.get(48..56) == Some(b"RISCV\0\0\0") {
+        return Some("riscv");
+    }
+    // Boot sector signature and the setup header of the boot protocol
+    if block.get(0x1fe..0x200) == Some(&[0x55, 0xaa]) && block.get(0x202..0x206) == Some(b"HdrS") {
+        return Some("x86");
+    }
+    if u32_at(block, 0x24, true) == Some(0x016f2818) {
+        return Some("arm");
+    }
+    None
+}
+
+// Original name in the header, after the extra field
+fn gzip_name(block: &[u8]) -> Option<String> {
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    let flags = *block.get(3)?;
+    if flags & FNAME == 0 {
+        return None;
+    }
+    let mut at = 10;
+    if flags & FEXTRA != 0 {
+        at += 2 + u16_at(block, 10, true)? as usize;
+    }
+    let rest = block.get(at..)?;
+    let end = rest.iter().position(|e| *e == 0)?;
+    Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+}
+
+// "ustar" at 257, or an old header whose checksum is right
+fn is_tar(block: &[u8]) -> bool {
+    let header = match block.get(..512) {
+        Some(d) => d,
+        None => return false,
+    };
+    if &header[257..262] == b"ustar" {
+        return true;
+    }
+    if header[0] == 0 {
+        return false;
+    }
+    // Octal number ended by NUL or space, the sum of the header with the checksum field as spaces
+    let field = String::from_utf8_lossy(&header[148..156]);
+    let checksum = match u32::from_str_radix(field.trim_matches(|c: char| c == '\0' || c == ' '), 8) {
+        Ok(d) => d,
+        Err(_e) => return false,
+    };
+    let sum: u32 = header
+        .iter()
+        .enumerate()
+        .map(|(i, b)| if (148..156).contains(&i) { b' ' as u32 } else { *b as u32 })
+        .sum();
+    sum == checksum
+}
+
+fn sniff_text(block: &[u8], complete: bool) -> Option<TextEncoding> {
+    if let Some(rest) = block.strip_prefix(&UTF8_BOM) {
+        return valid_utf8(rest, complete).then_some(TextEncoding::Utf8Bom);
+    }
+    if let Some(rest) = block.strip_prefix(&[0xff, 0xfe]) {
+        return valid_utf16(rest, true, complete).then_some(TextEncoding::Utf16Le);
+    }
+    if let Some(rest) = block.strip_prefix(&[0xfe, 0xff]) {
+        return valid_utf16(rest, false, complete).then_some(TextEncoding::Utf16Be);
+    }
+    if is_binary(block) {
+        return None;
+    }
+    if block.is_ascii() {
+        return Some(TextEncoding::Ascii);
+    }
+    if valid_utf8(block, complete) {
+        return Some(TextEncoding::Utf8);
+    }
+    // Text in other encoding has no control characters, except the usual ones
+    let control = block
+        .iter()
+        .any(|e| (*e < 0x20 && !matches!(e, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || *e == 0x7f);
+    (!control).then_some(TextEncoding::Unknown8Bit)
+}
+
+fn valid_utf8(block: &[u8], complete: bool) -> bool {
+    match std::str::from_utf8(block) {
+        Ok(_d) => true,
+        // "error_len" is None when the bytes end in the middle of a character
+        Err(e) => !complete && e.error_len().is_none(),
+    }
+}
+
+// Code units in order, surrogates only in pairs and without NUL
+fn valid_utf16(block: &[u8], little_endian: bool, complete: bool) -> bool {
+    if complete && !block.len().is_multiple_of(2) {
+        return false;
+    }
+    let units = block
+        .chunks_exact(2)
+        .map(|e| if little_endian { u16::from_le_bytes([e[0], e[1]]) } else { u16::from_be_bytes([e[0], e[1]]) });
+    let mut high = false;
+    for unit in units {
+        match unit {
+            0 => return false,
+            0xd800..=0xdbff if !high => high = true,
+            0xdc00..=0xdfff if high => high = false,
+            0xd800..=0xdfff => return false,
+            _ if high => return false,
+            _ => {}
+        }
+    }
+    !high || !complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elf() {
+        let mut header = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&[3, 0, 62, 0]);
+        let elf = sniff(&header, true);
+        assert_eq!(
+            elf,
+            FileType::Elf {
+                class: 64,
+                little_endian: true,
+                kind: 3,
+                machine: 62,
+            }
+        );
+        assert_eq!(elf.mime(), "application/x-sharedlib");
+        assert_eq!(sniff(&header[..4], true), FileType::TruncatedElf);
+        assert_eq!(sniff(&header[..19], true), FileType::TruncatedElf);
+        header[4] = 7;
+        assert_eq!(sniff(&header, true), FileType::TruncatedElf);
+        assert!(!FileType::TruncatedElf.is_text());
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(sniff(b"", true), FileType::Empty);
+        assert_eq!(sniff(b"ELF\n", true), FileType::Text(TextEncoding::Ascii));
+        assert_eq!(sniff("año\n".as_bytes(), true), FileType::Text(TextEncoding::Utf8));
+        // A character cut at the end of the block
+        assert_eq!(sniff(&"añ".as_bytes()[..2], false), FileType::Text(TextEncoding::Utf8));
+        assert_eq!(sniff(&"añ".as_bytes()[..2], true), FileType::Text(TextEncoding::Unknown8Bit));
+        assert_eq!(sniff(b"\x00\x01\x02", true), FileType::Data);
+    }
+}
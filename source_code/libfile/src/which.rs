@@ -0,0 +1,150 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Commands resolved by PATH, as "which".
+//!
+//! A name with '/' (absolute or relative; "/bin/ls", "./build.sh", "bin/tool") is not searched, it is used as is.
+//! Any other name is searched in each directory of PATH in order, an empty directory ("/bin::/usr/bin") is the
+//! current one. Only regular files (or links to them) with some execute bit are commands, a file without it is
+//! skipped and the search goes on as the shells do.
+//! The directories are not listed, only the file with that name is checked in each one.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+fn is_executable(path: &Path) -> bool {
+    // "metadata" follows the links
+    match fs::metadata(path) {
+        Ok(d) => d.is_file() && d.permissions().mode() & 0o111 != 0,
+        Err(_e) => false,
+    }
+}
+
+fn not_found(name: &str) -> io::Error {
+    if name.contains('/') {
+        io::Error::new(io::ErrorKind::NotFound, format!("{name} is not an executable file"))
+    } else {
+        io::Error::new(io::ErrorKind::NotFound, format!("{name} not found in PATH"))
+    }
+}
+
+// Names with '/' don't need PATH
+fn path_variable(name: &str) -> io::Result<OsString> {
+    if name.contains('/') {
+        return Ok(OsString::new());
+    }
+    env::var_os("PATH").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "PATH environment variable is not set"))
+}
+
+// Each match in PATH, in order; the search stops with the first one if "all" is false
+fn search(name: &str, path: &OsString, all: bool) -> Vec<PathBuf> {
+    let mut results = Vec::new();
+    if name.is_empty() {
+        return results;
+    }
+    if name.contains('/') {
+        if is_executable(Path::new(name)) {
+            results.push(PathBuf::from(name));
+        }
+        return results;
+    }
+    for directory in env::split_paths(path) {
+        let directory = if directory.as_os_str().is_empty() { PathBuf::from(".") } else { directory };
+        let candidate = directory.join(name);
+        // The same directory twice in PATH gives the same command once
+        if is_executable(&candidate) && !results.contains(&candidate) {
+            results.push(candidate);
+            if !all {
+                break;
+            }
+        }
+    }
+    results
+}
+
+/// First command of PATH with that name, or the name itself if it has '/'.
+/// The error is "NotFound" when there is no command or PATH is not set.
+pub fn which(name: &str) -> io::Result<PathBuf> {
+    let path = path_variable(name)?;
+    search(name, &path, false).pop().ok_or_else(|| not_found(name))
+}
+
+/// Every command of PATH with that name, in the order of PATH; empty if there is none.
+pub fn which_all(name: &str) -> io::Result<Vec<PathBuf>> {
+    let path = path_variable(name)?;
+    Ok(search(name, &path, true))
+}
+
+/// Commands already resolved, as the "hash" table of the shells; each command is searched in PATH only once.
+///
+/// The whole table is forgotten when PATH changes, and a command is searched again when its file is not
+/// executable anymore. A new command with the same name before in PATH is not seen until "forget" or "clear".
+/// Names with '/' are never saved.
+#[derive(Clone, Debug, Default)]
+pub struct CommandCache {
+    path: Option<OsString>,
+    commands: BTreeMap<String, PathBuf>,
+}
+
+impl CommandCache {
+    pub const fn new() -> CommandCache {
+        CommandCache {
+            path: None,
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// As "which", using the table.
+    pub fn get(&mut self, name: &str) -> io::Result<PathBuf> {
+        if name.contains('/') {
+            return which(name);
+        }
+        let path = path_variable(name)?;
+        if self.path.as_ref() != Some(&path) {
+            self.commands.clear();
+            self.path = Some(path.clone());
+        }
+        if let Some(d) = self.commands.get(name) {
+            if is_executable(d) {
+                return Ok(d.clone());
+            }
+        }
+
+        let found = search(name, &path, false).pop().ok_or_else(|| not_found(name));
+        match &found {
+            Ok(d) => self.commands.insert(name.to_string(), d.clone()),
+            Err(_e) => self.commands.remove(name),
+        };
+        found
+    }
+
+    /// Removes a command, as when it fails to run.
+    pub fn forget(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Commands saved, sorted by name.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.commands.iter().map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+}
@@ -0,0 +1,285 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Compiler of the tree into a Thompson NFA.
+//!
+//! The program is a list of instructions, "Split" follows both ways with the first one preferred,
+//! which gives the leftmost first semantics and makes lazy repetitions possible.
+//! Counted repetitions are expanded, so the program size is limited.
+
+use crate::parse::{Look, Node, Parsed};
+use crate::RegexError;
+
+/// Most instructions in a program.
+const MAX_INSTRUCTIONS: usize = 100_000;
+
+// Target not known yet, patched later
+const HOLE: usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+pub(crate) enum Inst {
+    Char(u32, usize),
+    Class(Box<[(u32, u32)]>, usize),
+    Look(Look, usize),
+    /// Save the position in the slot.
+    Save(usize, usize),
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Program {
+    pub insts: Vec<Inst>,
+    pub start: usize,
+    /// Two slots per group, start and end.
+    pub slots: usize,
+    pub names: Vec<Option<String>>,
+    /// The pattern as bytes, when it is only literal text.
+    pub literal: Option<Vec<u8>>,
+}
+
+// Field of an instruction which waits for its target
+enum Hole {
+    Next(usize),
+    Second(usize),
+}
+
+struct Fragment {
+    start: usize,
+    holes: Vec<Hole>,
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+}
+
+pub(crate) fn compile(parsed: Parsed) -> Result<Program, RegexError> {
+    let mut compiler = Compiler { insts: Vec::new() };
+    let body = compiler.node(&Node::Group(Box::new(parsed.node.clone()), Some(0)))?;
+    let done = compiler.push(Inst::Match)?;
+    compiler.patch(body.holes, done);
+    Ok(Program {
+        insts: compiler.insts,
+        start: body.start,
+        slots: parsed.names.len() * 2,
+        names: parsed.names,
+        literal: literal(&parsed.node).map(String::into_bytes),
+    })
+}
+
+// Text of a pattern without special characters
+fn literal(node: &Node) -> Option<String> {
+    match node {
+        Node::Literal(c) => Some(c.to_string()),
+        Node::Concat(nodes) => nodes.iter().map(literal).collect(),
+        _ => None,
+    }
+}
+
+// True if the node can match without consuming characters
+fn matches_empty(node: &Node) -> bool {
+    match node {
+        Node::Empty | Node::Look(_) => true,
+        Node::Literal(_) | Node::Class(_) => false,
+        Node::Group(d, _) => matches_empty(d),
+        Node::Concat(nodes) => nodes.iter().all(matches_empty),
+        Node::Alternate(branches) => branches.iter().any(matches_empty),
+        Node::Repeat { node, min, .. } => *min == 0 || matches_empty(node),
+    }
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.insts.len() >= MAX_INSTRUCTIONS {
+            return Err(RegexError {
+                position: 0,
+                message: "pattern is too big".to_string(),
+            });
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    fn patch(&mut self, holes: Vec<Hole>, target: usize) {
+        for hole in holes {
+            match hole {
+                Hole::Next(i) => match &mut self.insts[i] {
+                    Inst::Char(_, next)
+                    | Inst::Class(_, next)
+                    | Inst::Look(_, next)
+                    | Inst::Save(_, next)
+                    | Inst::Jump(next) => *next = target,
+                    Inst::Split(first, _) => *first = target,
+                    Inst::Match => {}
+                },
+                Hole::Second(i) => {
+                    if let Inst::Split(_, second) = &mut self.insts[i] {
+                        *second = target;
+                    }
+                }
+            }
+        }
+    }
+
+    // Instruction with one way out
+    fn single(&mut self, inst: Inst) -> Result<Fragment, RegexError> {
+        let start = self.push(inst)?;
+        Ok(Fragment {
+            start,
+            holes: vec![Hole::Next(start)],
+        })
+    }
+
+    fn node(&mut self, node: &Node) -> Result<Fragment, RegexError> {
+        match node {
+            Node::Empty => self.single(Inst::Jump(HOLE)),
+            Node::Literal(c) => self.single(Inst::Char(*c as u32, HOLE)),
+            Node::Class(class) => self.single(Inst::Class(class.ranges.clone().into_boxed_slice(), HOLE)),
+            Node::Look(look) => self.single(Inst::Look(*look, HOLE)),
+            Node::Group(inner, None) => self.node(inner),
+            Node::Group(inner, Some(index)) => {
+                let open = self.push(Inst::Save(index * 2, HOLE))?;
+                let body = self.node(inner)?;
+                self.patch(vec![Hole::Next(open)], body.start);
+                let close = self.push(Inst::Save(index * 2 + 1, HOLE))?;
+                self.patch(body.holes, close);
+                Ok(Fragment {
+                    start: open,
+                    holes: vec![Hole::Next(close)],
+                })
+            }
+            Node::Concat(nodes) => {
+                let mut fragments = Vec::with_capacity(nodes.len());
+                for node in nodes {
+                    fragments.push(self.node(node)?);
+                }
+                Ok(self.chain(fragments))
+            }
+            Node::Alternate(branches) => {
+                let mut fragments = Vec::with_capacity(branches.len());
+                for branch in branches {
+                    fragments.push(self.node(branch)?);
+                }
+                // Splits from the last branch to the first, each one prefers its branch
+                let mut holes = Vec::new();
+                let last = fragments.pop().expect("alternation has branches");
+                let mut start = last.start;
+                holes.extend(last.holes);
+                for fragment in fragments.into_iter().rev() {
+                    start = self.push(Inst::Split(fragment.start, start))?;
+                    holes.extend(fragment.holes);
+                }
+                Ok(Fragment { start, holes })
+            }
+            Node::Repeat { node, min, max, greedy } => {
+                let mut fragments = Vec::new();
+                // "x{n,}" is n - 1 copies and "x+", "x{0,}" is "x*"
+                let copies = match max {
+                    None => min.saturating_sub(1),
+                    Some(_d) => *min,
+                };
+                for _ in 0..copies {
+                    fragments.push(self.node(node)?);
+                }
+                match max {
+                    None if *min == 0 => fragments.push(self.star(node, *greedy)?),
+                    None => fragments.push(self.plus(node, *greedy)?),
+                    Some(max) => {
+                        // Each optional copy can skip all the ones after it
+                        let mut skips = Vec::new();
+                        for _ in *min..*max {
+                            let body = self.node(node)?;
+                            let split = self.split(body.start, *greedy)?;
+                            skips.push(split.1);
+                            fragments.push(Fragment {
+                                start: split.0,
+                                holes: body.holes,
+                            });
+                        }
+                        let mut fragment = self.chain(fragments);
+                        fragment.holes.extend(skips);
+                        return Ok(fragment);
+                    }
+                }
+                Ok(self.chain(fragments))
+            }
+        }
+    }
+
+    // Split which prefers "body" if greedy, returns it and the hole of the other way
+    fn split(&mut self, body: usize, greedy: bool) -> Result<(usize, Hole), RegexError> {
+        if greedy {
+            let split = self.push(Inst::Split(body, HOLE))?;
+            Ok((split, Hole::Second(split)))
+        } else {
+            let split = self.push(Inst::Split(HOLE, body))?;
+            Ok((split, Hole::Next(split)))
+        }
+    }
+
+    // The body, then a split back to it or out. The way out is after each iteration, also an empty one, so
+    // "(|a)+" prefers the empty match as the loop can not enter the body twice in the same position
+    fn plus(&mut self, node: &Node, greedy: bool) -> Result<Fragment, RegexError> {
+        let body = self.node(node)?;
+        let (split, out) = self.split(body.start, greedy)?;
+        self.patch(body.holes, split);
+        Ok(Fragment {
+            start: body.start,
+            holes: vec![out],
+        })
+    }
+
+    // A split to the body or out, and the body back to the split. Only when the body can match empty it is
+    // "(x+)?", so an empty iteration still has its way out; with one split "(?:.*?)*(a)" would take the lazy
+    // loop of the body before trying to leave the outer one
+    fn star(&mut self, node: &Node, greedy: bool) -> Result<Fragment, RegexError> {
+        if !matches_empty(node) {
+            let body = self.node(node)?;
+            let (split, out) = self.split(body.start, greedy)?;
+            self.patch(body.holes, split);
+            return Ok(Fragment {
+                start: split,
+                holes: vec![out],
+            });
+        }
+        let plus = self.plus(node, greedy)?;
+        let (split, skip) = self.split(plus.start, greedy)?;
+        let mut holes = plus.holes;
+        holes.push(skip);
+        Ok(Fragment { start: split, holes })
+    }
+
+    // Fragments one after the other
+    fn chain(&mut self, fragments: Vec<Fragment>) -> Fragment {
+        let mut fragments = fragments.into_iter();
+        let mut result = match fragments.next() {
+            Some(d) => d,
+            None => {
+                // Nothing to match, only a jump to what follows
+                let start = self.insts.len();
+                self.insts.push(Inst::Jump(HOLE));
+                return Fragment {
+                    start,
+                    holes: vec![Hole::Next(start)],
+                };
+            }
+        };
+        for fragment in fragments {
+            self.patch(std::mem::take(&mut result.holes), fragment.start);
+            result.holes = fragment.holes;
+        }
+        result
+    }
+}
@@ -0,0 +1,173 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Lazy DFA, only to know if there is a match.
+//!
+//! Each state is the set of NFA instructions reached after a character, with the kind of that
+//! character for the assertions. States and transitions are built the first time they are needed
+//! and kept, so most of the text is matched with one lookup per character.
+//! When there are too many states the caller uses the Pike VM instead.
+
+use std::collections::HashMap;
+
+use crate::compile::{Inst, Program};
+use crate::input::{decode, kind_before, kind_of, look_holds, Kind};
+use crate::parse::class_contains;
+
+/// Most states kept.
+const MAX_STATES: usize = 10_000;
+
+// Transition not built yet
+const UNKNOWN: u32 = u32::MAX;
+
+struct State {
+    // Instructions to continue from, before following the ones that do not read characters
+    core: Vec<usize>,
+    before: Kind,
+    // Transitions for ASCII; next state shifted by one, with the lowest bit set if there was a
+    // match before the character
+    ascii: Vec<u32>,
+    end: Option<bool>,
+}
+
+#[derive(Default)]
+pub(crate) struct Dfa {
+    states: Vec<State>,
+    index: HashMap<(Vec<usize>, Kind), usize>,
+    // Transitions for the other characters
+    others: HashMap<(usize, u32), u32>,
+    // First state by the kind of the character before the start
+    starts: [Option<usize>; 4],
+    // Scratch space for "closure"
+    seen: Vec<bool>,
+    stack: Vec<usize>,
+}
+
+impl Dfa {
+    pub(crate) fn new() -> Dfa {
+        Dfa::default()
+    }
+
+    /// If there is a match from "start", None if the states are over the limit.
+    pub(crate) fn is_match(&mut self, program: &Program, text: &[u8], start: usize) -> Option<bool> {
+        let before = kind_before(text, start);
+        let mut state = match self.starts[before as usize] {
+            Some(d) => d,
+            None => {
+                let d = self.state(Vec::new(), before)?;
+                self.starts[before as usize] = Some(d);
+                d
+            }
+        };
+        let mut at = start;
+        while at < text.len() {
+            let (c, len) = decode(text, at);
+            let known = if c < 128 {
+                self.states[state].ascii[c as usize]
+            } else {
+                self.others.get(&(state, c)).copied().unwrap_or(UNKNOWN)
+            };
+            let transition = if known == UNKNOWN {
+                let d = self.transition(program, state, c)?;
+                if c < 128 {
+                    self.states[state].ascii[c as usize] = d;
+                } else {
+                    self.others.insert((state, c), d);
+                }
+                d
+            } else {
+                known
+            };
+            if transition & 1 == 1 {
+                return Some(true);
+            }
+            state = (transition >> 1) as usize;
+            at += len;
+        }
+        if let Some(d) = self.states[state].end {
+            return Some(d);
+        }
+        let (_, end) = self.closure(program, state, Kind::Edge);
+        self.states[state].end = Some(end);
+        Some(end)
+    }
+
+    fn state(&mut self, core: Vec<usize>, before: Kind) -> Option<usize> {
+        if let Some(d) = self.index.get(&(core.clone(), before)) {
+            return Some(*d);
+        }
+        if self.states.len() >= MAX_STATES {
+            return None;
+        }
+        self.index.insert((core.clone(), before), self.states.len());
+        self.states.push(State {
+            core,
+            before,
+            ascii: vec![UNKNOWN; 128],
+            end: None,
+        });
+        Some(self.states.len() - 1)
+    }
+
+    fn transition(&mut self, program: &Program, state: usize, c: u32) -> Option<u32> {
+        let after = kind_of(c);
+        let (found, matched) = self.closure(program, state, after);
+        let mut core = Vec::new();
+        for pc in found {
+            match &program.insts[pc] {
+                Inst::Char(d, next) if *d == c => core.push(*next),
+                Inst::Class(ranges, next) if class_contains(ranges, c) => core.push(*next),
+                _ => {}
+            }
+        }
+        core.sort_unstable();
+        core.dedup();
+        let next = self.state(core, after)?;
+        Some(((next as u32) << 1) | matched as u32)
+    }
+
+    // Instructions which read characters reached from the state and a new start, and if the
+    // match was reached
+    fn closure(&mut self, program: &Program, state: usize, after: Kind) -> (Vec<usize>, bool) {
+        let before = self.states[state].before;
+        self.seen.clear();
+        self.seen.resize(program.insts.len(), false);
+        self.stack.clear();
+        self.stack.push(program.start);
+        self.stack.extend(self.states[state].core.iter().rev());
+        let mut found = Vec::new();
+        let mut matched = false;
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] {
+                continue;
+            }
+            self.seen[pc] = true;
+            match &program.insts[pc] {
+                Inst::Jump(next) | Inst::Save(_, next) => self.stack.push(*next),
+                Inst::Split(first, second) => {
+                    self.stack.push(*second);
+                    self.stack.push(*first);
+                }
+                Inst::Look(look, next) => {
+                    if look_holds(*look, before, after) {
+                        self.stack.push(*next);
+                    }
+                }
+                Inst::Char(..) | Inst::Class(..) => found.push(pc),
+                Inst::Match => matched = true,
+            }
+        }
+        (found, matched)
+    }
+}
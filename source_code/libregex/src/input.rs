@@ -0,0 +1,97 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Reading characters from the text.
+//!
+//! The text is bytes, UTF-8 is decoded while matching. An invalid byte is read as one character
+//! which no class contains, so it never matches but the search continues after it.
+
+use crate::parse::Look;
+
+/// Character given for each invalid byte.
+pub(crate) const INVALID: u32 = u32::MAX;
+
+/// What is around a position, enough to check the assertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Kind {
+    /// Start or end of the text.
+    Edge,
+    Newline,
+    /// ASCII letters, digits and '_'.
+    Word,
+    Other,
+}
+
+/// Character at "at" and its length in bytes.
+pub(crate) fn decode(text: &[u8], at: usize) -> (u32, usize) {
+    let byte = text[at];
+    if byte < 0x80 {
+        return (byte as u32, 1);
+    }
+    let chunk = &text[at..(at + 4).min(text.len())];
+    let valid = match std::str::from_utf8(chunk) {
+        Ok(d) => d,
+        Err(e) => std::str::from_utf8(&chunk[..e.valid_up_to()]).unwrap_or_default(),
+    };
+    match valid.chars().next() {
+        Some(c) => (c as u32, c.len_utf8()),
+        None => (INVALID, 1),
+    }
+}
+
+pub(crate) fn kind_of(c: u32) -> Kind {
+    match char::from_u32(c) {
+        Some('\n') => Kind::Newline,
+        Some(d) if d.is_ascii_alphanumeric() || d == '_' => Kind::Word,
+        _ => Kind::Other,
+    }
+}
+
+/// Kind of the character at "at".
+pub(crate) fn kind_at(text: &[u8], at: usize) -> Kind {
+    if at >= text.len() {
+        return Kind::Edge;
+    }
+    kind_of(decode(text, at).0)
+}
+
+/// Kind of the character which ends at "at".
+pub(crate) fn kind_before(text: &[u8], at: usize) -> Kind {
+    if at == 0 {
+        return Kind::Edge;
+    }
+    // The character starts in one of the previous 4 bytes
+    for start in (at.saturating_sub(4)..at).rev() {
+        if text[start] & 0xC0 != 0x80 {
+            let (c, len) = decode(text, start);
+            if start + len == at {
+                return kind_of(c);
+            }
+            break;
+        }
+    }
+    Kind::Other
+}
+
+/// Check the assertion between the characters of kind "before" and "after".
+pub(crate) fn look_holds(look: Look, before: Kind, after: Kind) -> bool {
+    match look {
+        Look::StartText => before == Kind::Edge,
+        Look::EndText => after == Kind::Edge,
+        Look::StartLine => matches!(before, Kind::Edge | Kind::Newline),
+        Look::EndLine => matches!(after, Kind::Edge | Kind::Newline),
+        Look::WordBoundary => (before == Kind::Word) != (after == Kind::Word),
+        Look::NotWordBoundary => (before == Kind::Word) == (after == Kind::Word),
+    }
+}
@@ -0,0 +1,539 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//!
+//! RavnOS's libregex
+//! Regular expressions without backtracking, the time is always linear with the text.
+//!
+//! The pattern is compiled to a Thompson NFA. A lazy DFA answers if there is a match, and the Pike VM
+//! gives the positions of the match and its groups only when there is one.
+//!
+//! Syntax;
+//! - '.' any character but newline, '[abc]', '[^a-z]', '[[:alpha:]]', '\d', '\w', '\s' and '\D', '\W', '\S'.
+//! - '^', '$', '\A', '\z', '\b' and '\B'.
+//! - '(...)', '(?:...)', '(?<name>...)' or '(?P<name>...)' and 'a|b'.
+//! - '*', '+', '?', '{n}', '{n,}', '{n,m}', followed by '?' they are lazy.
+//! - Flags '(?i)' case insensitive, '(?m)' '^' and '$' match in each line, '(?s)' '.' matches newline;
+//!   '(?-i)' disables and '(?i:...)' applies only to the group.
+//! - Escapes '\n', '\t', '\r', '\f', '\v', '\0', '\xHH', '\x{HHHH}' and '\' before any punctuation.
+//!
+//! Classes, '\b' and POSIX classes are ASCII, case insensitive mode works with all Unicode.
+//! Texts are bytes; invalid UTF-8 does not match anything but does not stop the search.
+//! Back references and look-around are not supported, they need backtracking.
+
+mod compile;
+mod dfa;
+mod input;
+mod parse;
+mod pike;
+
+use std::fmt;
+use std::sync::{Arc, Mutex, TryLockError};
+
+use compile::Program;
+use dfa::Dfa;
+use parse::Flags;
+
+/// Error in the pattern, "position" is the byte offset where the problem is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegexError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "position {}; {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+/// Compiled regular expression.
+pub struct Regex {
+    pattern: String,
+    program: Arc<Program>,
+    // The DFA keeps its states between searches, when it is in use by another thread the Pike VM is used
+    dfa: Mutex<Box<Dfa>>,
+}
+
+/// Options to compile a regular expression.
+#[derive(Clone, Debug)]
+pub struct RegexBuilder {
+    pattern: String,
+    flags: Flags,
+}
+
+/// Position of a match in the text, as byte offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Positions of the match and its groups.
+#[derive(Clone, Debug)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+    names: Vec<Option<String>>,
+}
+
+/// Iterator over the matches which do not overlap.
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    text: &'t [u8],
+    at: usize,
+    last: Option<usize>,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    /// Compile a shell glob, it must match the whole text.
+    pub fn from_glob(glob: &str) -> Result<Regex, RegexError> {
+        Regex::new(&glob_to_regex(glob))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Number of groups, with the whole match as group 0.
+    pub fn captures_len(&self) -> usize {
+        self.program.names.len()
+    }
+
+    /// Name of each group, None for the groups without name.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.program.names.iter().map(|e| e.as_deref())
+    }
+
+    pub fn is_match<T: AsRef<[u8]> + ?Sized>(&self, text: &T) -> bool {
+        let text = text.as_ref();
+        match &self.program.literal {
+            Some(d) => find_literal(text, d).is_some(),
+            None => self.dfa_match(text, 0),
+        }
+    }
+
+    /// First match, the leftmost one.
+    pub fn find<T: AsRef<[u8]> + ?Sized>(&self, text: &T) -> Option<Match> {
+        self.find_at(text, 0)
+    }
+
+    /// First match from the byte offset "start", the text before is used for '\b' and '^'.
+    pub fn find_at<T: AsRef<[u8]> + ?Sized>(&self, text: &T, start: usize) -> Option<Match> {
+        let text = text.as_ref();
+        if start > text.len() {
+            return None;
+        }
+        if let Some(d) = &self.program.literal {
+            return find_literal(&text[start..], d).map(|e| Match {
+                start: start + e,
+                end: start + e + d.len(),
+            });
+        }
+        let slots = self.slots(text, start)?;
+        Some(Match {
+            start: slots[0]?,
+            end: slots[1]?,
+        })
+    }
+
+    pub fn find_iter<'r, 't, T: AsRef<[u8]> + ?Sized>(&'r self, text: &'t T) -> Matches<'r, 't> {
+        Matches {
+            regex: self,
+            text: text.as_ref(),
+            at: 0,
+            last: None,
+        }
+    }
+
+    /// First match with its groups.
+    pub fn captures<T: AsRef<[u8]> + ?Sized>(&self, text: &T) -> Option<Captures> {
+        let text = text.as_ref();
+        if self.program.literal.is_some() {
+            let found = self.find(text)?;
+            return Some(Captures {
+                slots: vec![Some(found.start), Some(found.end)],
+                names: self.program.names.clone(),
+            });
+        }
+        Some(Captures {
+            slots: self.slots(text, 0)?,
+            names: self.program.names.clone(),
+        })
+    }
+
+    // The DFA discards the texts without match, only the others go to the slower Pike VM
+    fn slots(&self, text: &[u8], start: usize) -> Option<Vec<Option<usize>>> {
+        if !self.dfa_match(text, start) {
+            return None;
+        }
+        pike::search(&self.program, text, start)
+    }
+
+    fn dfa_match(&self, text: &[u8], start: usize) -> bool {
+        let mut dfa = match self.dfa.try_lock() {
+            Ok(d) => d,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return pike::search(&self.program, text, start).is_some(),
+        };
+        match dfa.is_match(&self.program, text, start) {
+            Some(d) => d,
+            None => {
+                // Too many states, start again next time
+                **dfa = Dfa::new();
+                pike::search(&self.program, text, start).is_some()
+            }
+        }
+    }
+}
+
+impl Clone for Regex {
+    fn clone(&self) -> Regex {
+        Regex {
+            pattern: self.pattern.clone(),
+            program: Arc::clone(&self.program),
+            dfa: Mutex::new(Box::new(Dfa::new())),
+        }
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            flags: Flags::default(),
+        }
+    }
+
+    /// Same as "(?i)".
+    pub fn case_insensitive(mut self, enabled: bool) -> RegexBuilder {
+        self.flags.case_insensitive = enabled;
+        self
+    }
+
+    /// Same as "(?m)".
+    pub fn multi_line(mut self, enabled: bool) -> RegexBuilder {
+        self.flags.multi_line = enabled;
+        self
+    }
+
+    /// Same as "(?s)".
+    pub fn dot_all(mut self, enabled: bool) -> RegexBuilder {
+        self.flags.dot_all = enabled;
+        self
+    }
+
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        let program = compile::compile(parse::parse(&self.pattern, self.flags)?)?;
+        Ok(Regex {
+            pattern: self.pattern.clone(),
+            program: Arc::new(program),
+            dfa: Mutex::new(Box::new(Dfa::new())),
+        })
+    }
+}
+
+impl Match {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl Captures {
+    /// Group "index", None if it did not take part in the match.
+    pub fn get(&self, index: usize) -> Option<Match> {
+        Some(Match {
+            start: (*self.slots.get(index * 2)?)?,
+            end: (*self.slots.get(index * 2 + 1)?)?,
+        })
+    }
+
+    pub fn name(&self, name: &str) -> Option<Match> {
+        let index = self.names.iter().position(|e| e.as_deref() == Some(name))?;
+        self.get(index)
+    }
+
+    /// Number of groups, with the whole match as group 0.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            let found = self.regex.find_at(self.text, self.at)?;
+            if found.is_empty() {
+                // An empty match just after the previous one is skipped, and the next search
+                // starts after the character
+                self.at = match self.text.get(found.end) {
+                    Some(_) => found.end + input::decode(self.text, found.end).1,
+                    None => self.text.len() + 1,
+                };
+                if self.last == Some(found.end) {
+                    continue;
+                }
+            } else {
+                self.at = found.end;
+            }
+            self.last = Some(found.end);
+            return Some(found);
+        }
+    }
+}
+
+/// Regular expression from a shell glob; '*' any text, '?' any character and '[abc]', '[a-z]' or '[!abc]' sets.
+/// A '[' without closing is a literal, as in "glob_match".
+pub fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut pattern = String::from("(?s)\\A");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                // Find the closing ']', the first character of the set can be a ']'
+                let mut end = i + 1;
+                if chars.get(end).is_some_and(|e| *e == '!' || *e == '^') {
+                    end += 1;
+                }
+                end += 1;
+                while end < chars.len() && chars[end] != ']' {
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    pattern.push_str("\\[");
+                } else {
+                    pattern.push('[');
+                    let mut j = i + 1;
+                    if chars[j] == '!' || chars[j] == '^' {
+                        pattern.push('^');
+                        j += 1;
+                    }
+                    for c in &chars[j..end] {
+                        if matches!(c, '\\' | '[' | ']' | '^') {
+                            pattern.push('\\');
+                        }
+                        pattern.push(*c);
+                    }
+                    pattern.push(']');
+                    i = end;
+                }
+            }
+            c => pattern.push_str(&escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    pattern.push_str("\\z");
+    pattern
+}
+
+/// Text with the special characters escaped, so it matches as is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Position of the first "needle" in "haystack"
+fn find_literal(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let (first, rest) = match needle.split_first() {
+        Some(d) => d,
+        None => return Some(0),
+    };
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    let last = haystack.len() - needle.len();
+    let mut start = 0;
+    while let Some(i) = haystack[start..=last].iter().position(|b| b == first) {
+        let at = start + i;
+        if &haystack[at + 1..at + needle.len()] == rest {
+            return Some(at);
+        }
+        start = at + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Range of the first match
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        Regex::new(pattern).unwrap().find(text).map(|e| (e.start, e.end))
+    }
+
+    fn groups(pattern: &str, text: &str) -> Vec<Option<(usize, usize)>> {
+        let captures = Regex::new(pattern).unwrap().captures(text).unwrap();
+        (0..captures.len()).map(|i| captures.get(i).map(|e| (e.start, e.end))).collect()
+    }
+
+    #[test]
+    fn literals_and_classes() {
+        assert_eq!(find("bc", "abcd"), Some((1, 3)));
+        assert_eq!(find("a.c", "xa\nc abc"), Some((5, 8)));
+        assert_eq!(find("(?s)a.c", "xa\nc"), Some((1, 4)));
+        assert_eq!(find("[^a-c]+", "abcdeb"), Some((3, 5)));
+        assert_eq!(find("[[:digit:]]+", "ab123c"), Some((2, 5)));
+        assert_eq!(find(r"\w+\s\d", "-- foo 7"), Some((3, 8)));
+        assert_eq!(find(r"\x41\x{e9}", "Aé"), Some((0, 3)));
+        assert_eq!(find("(?i)straße", "STRASSE Straße"), Some((8, 15)));
+        assert_eq!(find("z", "abc"), None);
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(find("^b", "ab\nb"), None);
+        assert_eq!(find("(?m)^b", "ab\nb"), Some((3, 4)));
+        assert_eq!(find("a$", "a\na"), Some((2, 3)));
+        assert_eq!(find(r"\bis\b", "this is"), Some((5, 7)));
+        assert_eq!(find(r"\Bis", "this is"), Some((2, 4)));
+        assert_eq!(find(r"\Aa|b\z", "ab"), Some((0, 1)));
+    }
+
+    #[test]
+    fn leftmost_first() {
+        assert_eq!(find("a|ab", "ab"), Some((0, 1)));
+        assert_eq!(find("ab|a", "ab"), Some((0, 2)));
+        assert_eq!(find("a+", "baaa"), Some((1, 4)));
+        assert_eq!(find("a+?", "baaa"), Some((1, 2)));
+        assert_eq!(find("a{2,3}", "aaaa"), Some((0, 3)));
+        assert_eq!(find("a{2,3}?", "aaaa"), Some((0, 2)));
+        assert_eq!(find("a{2,}", "aaaa"), Some((0, 4)));
+        assert_eq!(find("(?:ab){2}", "ababab"), Some((0, 4)));
+    }
+
+    // A loop whose body can be empty prefers the empty iteration as written, "x+" is compiled as the
+    // body followed by the split back
+    #[test]
+    fn empty_loop_bodies() {
+        assert_eq!(find("(|a)+", "aa"), Some((0, 0)));
+        assert_eq!(find("(a??)+", "a"), Some((0, 0)));
+        assert_eq!(find("(?:a*?)+", "aa"), Some((0, 0)));
+        assert_eq!(find("(?:.??)+", "ab"), Some((0, 0)));
+        assert_eq!(find("(|a)*", "aa"), Some((0, 0)));
+        assert_eq!(find("(|a){2,}", "aa"), Some((0, 0)));
+        assert_eq!(find("(a|)+", "aa"), Some((0, 2)));
+        assert_eq!(find("(?:a*)+b", "aab"), Some((0, 3)));
+        assert_eq!(groups("(a|)+", "aa"), [Some((0, 2)), Some((1, 2))]);
+        assert_eq!(groups("(a*)+", "b"), [Some((0, 0)), Some((0, 0))]);
+    }
+
+    #[test]
+    fn lazy_loops_inside_loops() {
+        assert_eq!(groups("(?:.*?)*(a)", "bbaa"), [Some((0, 3)), Some((2, 3))]);
+        assert_eq!(groups("(?:[ab]*?)+(b)", "abbb"), [Some((0, 2)), Some((1, 2))]);
+        assert_eq!(groups("^(?:b*?)+(.)?$", "bb"), [Some((0, 2)), Some((1, 2))]);
+    }
+
+    #[test]
+    fn captures() {
+        let regex = Regex::new(r"(?<year>\d{4})-(\d{2})(x)?").unwrap();
+        assert_eq!(regex.captures_len(), 4);
+        assert_eq!(regex.capture_names().collect::<Vec<_>>(), [None, Some("year"), None, None]);
+        let captures = regex.captures("on 2024-05").unwrap();
+        assert_eq!(captures.name("year"), Some(Match { start: 3, end: 7 }));
+        assert_eq!(captures.get(2), Some(Match { start: 8, end: 10 }));
+        assert_eq!(captures.get(3), None);
+        // The last iteration of a repeated group
+        assert_eq!(groups("(?:(a)|b)+", "ab"), [Some((0, 2)), Some((0, 1))]);
+        assert_eq!(groups("(a)|b", "b"), [Some((0, 1)), None]);
+    }
+
+    #[test]
+    fn matches_iterator() {
+        let all = |pattern: &str, text: &str| -> Vec<(usize, usize)> {
+            Regex::new(pattern).unwrap().find_iter(text).map(|e| (e.start, e.end)).collect()
+        };
+        assert_eq!(all(r"\d+", "a1b22c333"), [(1, 2), (3, 5), (6, 9)]);
+        // Empty matches in each position, but not just after a match
+        assert_eq!(all("a*", "baa"), [(0, 0), (1, 3)]);
+        assert_eq!(all("", "é"), [(0, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let text: &[u8] = b"\xffab\xfe";
+        assert_eq!(Regex::new("ab").unwrap().find(text), Some(Match { start: 1, end: 3 }));
+        assert_eq!(Regex::new("^.").unwrap().find(text), None);
+        // The invalid byte is not a character for "."
+        assert!(!Regex::new("b.$").unwrap().is_match(text));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |pattern: &str| Regex::new(pattern).err().map(|e| e.position);
+        assert_eq!(error("(ab"), Some(0));
+        assert_eq!(error("ab)"), Some(2));
+        assert_eq!(error("a{3,2}"), Some(1));
+        assert!(error("*a").is_some());
+        // Look-arounds repeat as groups of them
+        assert_eq!(error(r"\b*"), None);
+        assert_eq!(error("$*"), None);
+        assert_eq!(find(r"a\B{2}", "a b"), None);
+        assert_eq!(find("^+a", "a"), Some((0, 1)));
+        assert!(error("[z-a]").is_some());
+        assert!(error(r"(a)\1").is_some());
+        assert!(Regex::new("(?:a{1000}){1000}").is_err());
+    }
+
+    #[test]
+    fn globs() {
+        let regex = Regex::from_glob("*.r[sa]").unwrap();
+        assert!(regex.is_match("main.rs"));
+        assert!(!regex.is_match("main.rs.bak"));
+        assert!(Regex::from_glob("[!a]?").unwrap().is_match("b."));
+        assert!(Regex::from_glob("[a").unwrap().is_match("[a"));
+        assert_eq!(escape("a.b*"), r"a\.b\*");
+    }
+}
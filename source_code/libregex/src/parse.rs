@@ -0,0 +1,686 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Parser of the pattern into a tree.
+//!
+//! The flags are applied while parsing; case insensitive literals become classes, '^', '$' and '.'
+//! are resolved by multi line and dot all modes. So the tree does not need to know about flags.
+
+use crate::RegexError;
+
+/// Last valid character.
+pub(crate) const MAX_CHAR: u32 = 0x10FFFF;
+/// Biggest count in "{n,m}".
+const MAX_REPEAT: u32 = 1000;
+/// Nested groups, to keep the recursion of parser and compiler bounded.
+const MAX_DEPTH: usize = 250;
+/// Cased characters stop before this one, so case folding does not need to look after it.
+const LAST_CASED: u32 = 0x1FFFF;
+
+/// Sorted ranges of characters, without overlapping.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Class {
+    pub ranges: Vec<(u32, u32)>,
+}
+
+impl Class {
+    fn new() -> Class {
+        Class { ranges: Vec::new() }
+    }
+
+    fn from_ranges(ranges: &[(u32, u32)]) -> Class {
+        let mut class = Class { ranges: ranges.to_vec() };
+        class.normalize();
+        class
+    }
+
+    fn push(&mut self, low: u32, high: u32) {
+        self.ranges.push((low, high));
+    }
+
+    fn union(&mut self, other: &Class) {
+        self.ranges.extend_from_slice(&other.ranges);
+    }
+
+    // Sort and merge the ranges which overlap or are next to each other
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(low, high) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if low <= last.1.saturating_add(1) => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    // Must be normalized
+    fn negate(&mut self) {
+        let mut negated = Vec::new();
+        let mut next = 0;
+        for &(low, high) in &self.ranges {
+            if low > next {
+                negated.push((next, low - 1));
+            }
+            next = high + 1;
+        }
+        if next <= MAX_CHAR {
+            negated.push((next, MAX_CHAR));
+        }
+        self.ranges = negated;
+    }
+
+    // Add the upper and lower case of each character, when it is only one character
+    fn fold_case(&mut self) {
+        let mut extra = Vec::new();
+        for &(low, high) in &self.ranges {
+            for c in (low..=high.min(LAST_CASED)).filter_map(char::from_u32) {
+                let (mut lower, mut upper) = (c.to_lowercase(), c.to_uppercase());
+                for other in [(lower.next(), lower.next()), (upper.next(), upper.next())] {
+                    if let (Some(d), None) = other {
+                        if d != c {
+                            extra.push((d as u32, d as u32));
+                        }
+                    }
+                }
+            }
+        }
+        self.ranges.extend(extra);
+        self.normalize();
+    }
+
+    fn is_single(&self) -> Option<u32> {
+        match self.ranges[..] {
+            [(low, high)] if low == high => Some(low),
+            _ => None,
+        }
+    }
+}
+
+/// Check if "c" is in the sorted ranges.
+pub(crate) fn class_contains(ranges: &[(u32, u32)], c: u32) -> bool {
+    ranges
+        .binary_search_by(|&(low, high)| {
+            if high < c {
+                std::cmp::Ordering::Less
+            } else if low > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Assertions, they match a position and not a character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Look {
+    StartText,
+    EndText,
+    StartLine,
+    EndLine,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Node {
+    Empty,
+    Literal(char),
+    Class(Class),
+    Look(Look),
+    /// Group with its capture index, None if does not capture.
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Flags {
+    pub case_insensitive: bool,
+    pub multi_line: bool,
+    pub dot_all: bool,
+}
+
+/// The tree and the name of each capture group; index 0 is the whole match and has no name.
+pub(crate) struct Parsed {
+    pub node: Node,
+    pub names: Vec<Option<String>>,
+}
+
+struct Parser {
+    // Characters with their byte offset in the pattern
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    len: usize,
+    names: Vec<Option<String>>,
+    depth: usize,
+}
+
+pub(crate) fn parse(pattern: &str, flags: Flags) -> Result<Parsed, RegexError> {
+    let mut parser = Parser {
+        chars: pattern.char_indices().collect(),
+        pos: 0,
+        len: pattern.len(),
+        names: vec![None],
+        depth: 0,
+    };
+    let mut flags = flags;
+    let node = parser.alternation(&mut flags)?;
+    if parser.peek().is_some() {
+        return Err(parser.error("')' without opening"));
+    }
+    Ok(Parsed {
+        node,
+        names: parser.names,
+    })
+}
+
+fn concat(mut nodes: Vec<Node>) -> Node {
+    match nodes.len() {
+        0 => Node::Empty,
+        1 => nodes.remove(0),
+        _ => Node::Concat(nodes),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|e| e.1)
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).map(|e| e.1)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.pos).map(|e| e.0).unwrap_or(self.len)
+    }
+
+    fn error(&self, message: &str) -> RegexError {
+        self.error_at(self.offset(), message)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> RegexError {
+        RegexError {
+            position,
+            message: message.to_string(),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), RegexError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("too many nested groups"));
+        }
+        Ok(())
+    }
+
+    // Branches until the end of the pattern or of the group; the ')' is not consumed.
+    // Flags set with "(?i)" change "flags" until the end of the group.
+    fn alternation(&mut self, flags: &mut Flags) -> Result<Node, RegexError> {
+        let mut branches = Vec::new();
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(')') => break,
+                Some('|') => {
+                    self.next();
+                    branches.push(concat(std::mem::take(&mut nodes)));
+                }
+                Some(_) => {
+                    if let Some(d) = self.repeat(flags)? {
+                        nodes.push(d);
+                    }
+                }
+            }
+        }
+        if branches.is_empty() {
+            return Ok(concat(nodes));
+        }
+        branches.push(concat(nodes));
+        Ok(Node::Alternate(branches))
+    }
+
+    fn repeat(&mut self, flags: &mut Flags) -> Result<Option<Node>, RegexError> {
+        let start = self.offset();
+        let node = match self.atom(flags)? {
+            Some(d) => d,
+            None => {
+                if matches!(self.peek(), Some('*' | '+' | '?')) {
+                    return Err(self.error("nothing to repeat"));
+                }
+                return Ok(None);
+            }
+        };
+        let (min, max) = match self.quantifier()? {
+            Some(d) => d,
+            None => return Ok(Some(node)),
+        };
+        let greedy = if self.peek() == Some('?') {
+            self.next();
+            false
+        } else {
+            true
+        };
+        // Look-arounds can be repeated as in groups, "\b*" as "(?:\b)*"
+        if let Node::Empty = node {
+            return Err(self.error_at(start, "nothing to repeat"));
+        }
+        // Only one quantifier, "a**" would nest repetitions without limit
+        let second = self.offset();
+        if self.quantifier()?.is_some() {
+            return Err(self.error_at(second, "nothing to repeat"));
+        }
+        Ok(Some(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        }))
+    }
+
+    // '*', '+', '?' or a counted repetition, consumed
+    fn quantifier(&mut self) -> Result<Option<(u32, Option<u32>)>, RegexError> {
+        let found = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.counted()? {
+                Some(d) => d,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        // The quantifier, or the '}' of a counted one
+        self.next();
+        Ok(Some(found))
+    }
+
+    // "{n}", "{n,}" or "{n,m}", the position is left at the '}'.
+    // Returns None and keeps the position if it is not a repetition, so the '{' is a literal.
+    fn counted(&mut self) -> Result<Option<(u32, Option<u32>)>, RegexError> {
+        let start = self.pos;
+        let offset = self.offset();
+        self.next();
+        let number = |parser: &mut Parser| -> Option<String> {
+            let mut digits = String::new();
+            while let Some(c) = parser.peek().filter(|c| c.is_ascii_digit()) {
+                parser.next();
+                digits.push(c);
+            }
+            Some(digits).filter(|e| !e.is_empty())
+        };
+        let min = number(self);
+        let max = if self.peek() == Some(',') {
+            self.next();
+            Some(number(self))
+        } else {
+            None
+        };
+        if min.is_none() || self.peek() != Some('}') {
+            self.pos = start;
+            return Ok(None);
+        }
+        let parse = |digits: String| -> Result<u32, RegexError> {
+            match digits.parse::<u32>() {
+                Ok(d) if d <= MAX_REPEAT => Ok(d),
+                _ => Err(RegexError {
+                    position: offset,
+                    message: format!("repetition count over {MAX_REPEAT}"),
+                }),
+            }
+        };
+        let min = parse(min.unwrap_or_default())?;
+        let max = match max {
+            // "{n}"
+            None => Some(min),
+            // "{n,}"
+            Some(None) => None,
+            Some(Some(d)) => Some(parse(d)?),
+        };
+        if max.is_some_and(|e| e < min) {
+            return Err(self.error_at(offset, "repetition range with minimum over maximum"));
+        }
+        Ok(Some((min, max)))
+    }
+
+    // One item; None for a group which only sets flags
+    fn atom(&mut self, flags: &mut Flags) -> Result<Option<Node>, RegexError> {
+        let c = match self.peek() {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+        let node = match c {
+            '(' => return self.group(flags),
+            '[' => self.class(flags)?,
+            '*' | '+' | '?' => return Err(self.error("nothing to repeat")),
+            '.' => {
+                self.next();
+                let mut class = Class::new();
+                if flags.dot_all {
+                    class.push(0, MAX_CHAR);
+                } else {
+                    class.push(0, '\n' as u32 - 1);
+                    class.push('\n' as u32 + 1, MAX_CHAR);
+                }
+                Node::Class(class)
+            }
+            '^' => {
+                self.next();
+                Node::Look(if flags.multi_line { Look::StartLine } else { Look::StartText })
+            }
+            '$' => {
+                self.next();
+                Node::Look(if flags.multi_line { Look::EndLine } else { Look::EndText })
+            }
+            '\\' => self.escape(flags)?,
+            c => {
+                self.next();
+                literal(c, flags)
+            }
+        };
+        Ok(Some(node))
+    }
+
+    fn group(&mut self, flags: &mut Flags) -> Result<Option<Node>, RegexError> {
+        let start = self.offset();
+        self.next();
+        let mut inner = *flags;
+        let mut index = None;
+        if self.peek() == Some('?') {
+            self.next();
+            match (self.peek(), self.peek_at(1)) {
+                (Some(':'), _) => {
+                    self.next();
+                }
+                (Some('=' | '!'), _) | (Some('<'), Some('=' | '!')) => {
+                    return Err(self.error_at(start, "look-around is not supported"));
+                }
+                (Some('P'), Some('<')) | (Some('<'), _) => {
+                    if self.next() == Some('P') {
+                        self.next();
+                    }
+                    index = Some(self.names.len());
+                    let name = self.group_name()?;
+                    self.names.push(Some(name));
+                }
+                _ => {
+                    // Flags; "(?im)", "(?-i)" or "(?i:...)"
+                    let mut negate = false;
+                    loop {
+                        let value = !negate;
+                        match self.next() {
+                            Some('i') => inner.case_insensitive = value,
+                            Some('m') => inner.multi_line = value,
+                            Some('s') => inner.dot_all = value,
+                            Some('-') if !negate => negate = true,
+                            Some(':') => break,
+                            Some(')') => {
+                                *flags = inner;
+                                return Ok(None);
+                            }
+                            None => return Err(self.error_at(start, "group without closing")),
+                            Some(c) => {
+                                self.pos -= 1;
+                                return Err(self.error(&format!("unknown flag '{c}'")));
+                            }
+                        }
+                    }
+                }
+            }
+        } else {
+            index = Some(self.names.len());
+            self.names.push(None);
+        }
+
+        self.enter()?;
+        let node = self.alternation(&mut inner)?;
+        self.depth -= 1;
+        if self.next() != Some(')') {
+            return Err(self.error_at(start, "group without closing"));
+        }
+        Ok(Some(Node::Group(Box::new(node), index)))
+    }
+
+    // Name of "(?<name>...)", until the '>'
+    fn group_name(&mut self) -> Result<String, RegexError> {
+        let start = self.offset();
+        let mut name = String::new();
+        loop {
+            match self.next() {
+                Some('>') => break,
+                Some(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                _ => return Err(self.error_at(start, "invalid group name")),
+            }
+        }
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error_at(start, "invalid group name"));
+        }
+        if self.names.iter().flatten().any(|e| *e == name) {
+            return Err(self.error_at(start, &format!("group name '{name}' is repeated")));
+        }
+        Ok(name)
+    }
+
+    // After '\', outside a class
+    fn escape(&mut self, flags: &Flags) -> Result<Node, RegexError> {
+        let start = self.offset();
+        self.next();
+        let node = match self.peek() {
+            None => return Err(self.error_at(start, "'\\' at the end of the pattern")),
+            Some('b') => Node::Look(Look::WordBoundary),
+            Some('B') => Node::Look(Look::NotWordBoundary),
+            Some('A') => Node::Look(Look::StartText),
+            Some('z') => Node::Look(Look::EndText),
+            Some('1'..='9') => return Err(self.error_at(start, "backreferences are not supported")),
+            Some(c) => match perl_class(c) {
+                Some(d) => Node::Class(d),
+                None => return Ok(literal(self.escaped_char(start)?, flags)),
+            },
+        };
+        self.next();
+        Ok(node)
+    }
+
+    // Character of an escape, the position is after the '\'
+    fn escaped_char(&mut self, start: usize) -> Result<char, RegexError> {
+        let c = match self.next() {
+            Some(d) => d,
+            None => return Err(self.error_at(start, "'\\' at the end of the pattern")),
+        };
+        let escaped = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'f' => '\x0C',
+            'v' => '\x0B',
+            '0' => '\0',
+            'x' => return self.hex(start),
+            c if !c.is_alphanumeric() => c,
+            c => return Err(self.error_at(start, &format!("unknown escape '\\{c}'"))),
+        };
+        Ok(escaped)
+    }
+
+    // "\xHH" or "\x{H...}", the position is after the 'x'
+    fn hex(&mut self, start: usize) -> Result<char, RegexError> {
+        let mut digits = String::new();
+        if self.peek() == Some('{') {
+            self.next();
+            loop {
+                match self.next() {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                    _ => return Err(self.error_at(start, "invalid '\\x{...}' escape")),
+                }
+            }
+        } else {
+            for _ in 0..2 {
+                match self.next() {
+                    Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                    _ => return Err(self.error_at(start, "'\\x' needs two hexadecimal digits")),
+                }
+            }
+        }
+        match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+            Some(d) => Ok(d),
+            None => Err(self.error_at(start, "invalid character in '\\x' escape")),
+        }
+    }
+
+    // "[...]"
+    fn class(&mut self, flags: &Flags) -> Result<Node, RegexError> {
+        let start = self.offset();
+        self.next();
+        let negate = self.peek() == Some('^');
+        if negate {
+            self.next();
+        }
+        let mut class = Class::new();
+        let mut first = true;
+        loop {
+            let item_start = self.offset();
+            let low = match self.next() {
+                None => return Err(self.error_at(start, "class without closing")),
+                Some(']') if !first => break,
+                Some('[') if self.peek() == Some(':') => match self.posix_class()? {
+                    Some(d) => {
+                        class.union(&d);
+                        first = false;
+                        continue;
+                    }
+                    None => '[',
+                },
+                Some('\\') => match self.peek().and_then(perl_class) {
+                    Some(d) => {
+                        self.next();
+                        class.union(&d);
+                        first = false;
+                        continue;
+                    }
+                    None => self.escaped_char(item_start)?,
+                },
+                Some(c) => c,
+            };
+            first = false;
+
+            // Range, unless the '-' is the last one of the class
+            if self.peek() == Some('-') && self.peek_at(1).is_some_and(|e| e != ']') {
+                self.next();
+                let high_start = self.offset();
+                let high = match self.next() {
+                    Some('\\') => {
+                        if self.peek().and_then(perl_class).is_some() {
+                            return Err(self.error_at(high_start, "class can not be the end of a range"));
+                        }
+                        self.escaped_char(high_start)?
+                    }
+                    Some(c) => c,
+                    None => return Err(self.error_at(start, "class without closing")),
+                };
+                if high < low {
+                    return Err(self.error_at(item_start, &format!("invalid range '{low}-{high}'")));
+                }
+                class.push(low as u32, high as u32);
+            } else {
+                class.push(low as u32, low as u32);
+            }
+        }
+        class.normalize();
+        if flags.case_insensitive {
+            class.fold_case();
+        }
+        if negate {
+            class.negate();
+        }
+        Ok(Node::Class(class))
+    }
+
+    // "[:name:]" inside a class, the position is after the '['.
+    // None and the position kept if there is no ":]", so the '[' is a literal.
+    fn posix_class(&mut self) -> Result<Option<Class>, RegexError> {
+        let start = self.pos;
+        let offset = self.offset();
+        self.next();
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_lowercase()) {
+            self.next();
+            name.push(c);
+        }
+        if self.peek() != Some(':') || self.peek_at(1) != Some(']') {
+            self.pos = start;
+            return Ok(None);
+        }
+        self.next();
+        self.next();
+        let ranges: &[(u32, u32)] = match name.as_str() {
+            "alnum" => &[(0x30, 0x39), (0x41, 0x5A), (0x61, 0x7A)],
+            "alpha" => &[(0x41, 0x5A), (0x61, 0x7A)],
+            "blank" => &[(0x09, 0x09), (0x20, 0x20)],
+            "cntrl" => &[(0x00, 0x1F), (0x7F, 0x7F)],
+            "digit" => &[(0x30, 0x39)],
+            "graph" => &[(0x21, 0x7E)],
+            "lower" => &[(0x61, 0x7A)],
+            "print" => &[(0x20, 0x7E)],
+            "punct" => &[(0x21, 0x2F), (0x3A, 0x40), (0x5B, 0x60), (0x7B, 0x7E)],
+            "space" => &[(0x09, 0x0D), (0x20, 0x20)],
+            "upper" => &[(0x41, 0x5A)],
+            "word" => &[(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)],
+            "xdigit" => &[(0x30, 0x39), (0x41, 0x46), (0x61, 0x66)],
+            _ => return Err(self.error_at(offset, &format!("unknown class '[:{name}:]'"))),
+        };
+        Ok(Some(Class::from_ranges(ranges)))
+    }
+}
+
+// "\d", "\w" and "\s" and their negations, ASCII only
+fn perl_class(c: char) -> Option<Class> {
+    let ranges: &[(u32, u32)] = match c.to_ascii_lowercase() {
+        'd' => &[(0x30, 0x39)],
+        'w' => &[(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)],
+        's' => &[(0x09, 0x0D), (0x20, 0x20)],
+        _ => return None,
+    };
+    let mut class = Class::from_ranges(ranges);
+    if c.is_ascii_uppercase() {
+        class.negate();
+    }
+    Some(class)
+}
+
+fn literal(c: char, flags: &Flags) -> Node {
+    if !flags.case_insensitive {
+        return Node::Literal(c);
+    }
+    let mut class = Class::from_ranges(&[(c as u32, c as u32)]);
+    class.fold_case();
+    match class.is_single() {
+        Some(_) => Node::Literal(c),
+        None => Node::Class(class),
+    }
+}
@@ -0,0 +1,160 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Pike VM, the NFA simulation which gives positions of the match and its groups.
+//!
+//! All the threads advance together one character at a time, a thread is dropped when another
+//! one with more priority is already in the same instruction. So the time is linear; text length
+//! by program size.
+
+use crate::compile::{Inst, Program};
+use crate::input::{decode, kind_at, kind_before, kind_of, look_holds, Kind};
+use crate::parse::class_contains;
+
+// Threads by instruction, in order of priority
+struct Threads {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+    // Slots of each instruction, "slots" per instruction
+    slots: Vec<Option<usize>>,
+}
+
+impl Threads {
+    fn new(insts: usize, slots: usize) -> Threads {
+        Threads {
+            dense: Vec::with_capacity(insts),
+            sparse: vec![0; insts],
+            slots: vec![None; insts * slots],
+        }
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        let i = self.sparse[pc];
+        i < self.dense.len() && self.dense[i] == pc
+    }
+
+    fn insert(&mut self, pc: usize) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+}
+
+enum Frame {
+    Explore(usize),
+    Restore(usize, Option<usize>),
+}
+
+struct Vm<'a> {
+    program: &'a Program,
+    stack: Vec<Frame>,
+}
+
+impl Vm<'_> {
+    // Follow the instructions which do not read characters from "pc", at position "at"
+    fn add(&mut self, threads: &mut Threads, pc: usize, at: usize, slots: &mut [Option<usize>], around: (Kind, Kind)) {
+        let width = slots.len();
+        self.stack.push(Frame::Explore(pc));
+        while let Some(frame) = self.stack.pop() {
+            let pc = match frame {
+                Frame::Explore(d) => d,
+                Frame::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            if threads.contains(pc) {
+                continue;
+            }
+            threads.insert(pc);
+            match &self.program.insts[pc] {
+                Inst::Jump(next) => self.stack.push(Frame::Explore(*next)),
+                Inst::Split(first, second) => {
+                    self.stack.push(Frame::Explore(*second));
+                    self.stack.push(Frame::Explore(*first));
+                }
+                Inst::Save(slot, next) => {
+                    self.stack.push(Frame::Restore(*slot, slots[*slot]));
+                    slots[*slot] = Some(at);
+                    self.stack.push(Frame::Explore(*next));
+                }
+                Inst::Look(look, next) => {
+                    if look_holds(*look, around.0, around.1) {
+                        self.stack.push(Frame::Explore(*next));
+                    }
+                }
+                Inst::Char(..) | Inst::Class(..) | Inst::Match => {
+                    threads.slots[pc * width..(pc + 1) * width].copy_from_slice(slots);
+                }
+            }
+        }
+    }
+}
+
+/// Leftmost first match from "start", with the slots of all the groups.
+pub(crate) fn search(program: &Program, text: &[u8], start: usize) -> Option<Vec<Option<usize>>> {
+    let width = program.slots;
+    let mut vm = Vm {
+        program,
+        stack: Vec::new(),
+    };
+    let mut current = Threads::new(program.insts.len(), width);
+    let mut next = Threads::new(program.insts.len(), width);
+    let mut slots: Vec<Option<usize>> = vec![None; width];
+    let mut matched: Option<Vec<Option<usize>>> = None;
+
+    let mut at = start;
+    let mut before = kind_before(text, start);
+    loop {
+        let (c, len) = if at < text.len() { decode(text, at) } else { (0, 0) };
+        let after = if len > 0 { kind_of(c) } else { Kind::Edge };
+        // A new thread starts in each position until something matches, with the lowest priority
+        if matched.is_none() {
+            slots.fill(None);
+            vm.add(&mut current, program.start, at, &mut slots, (before, after));
+        }
+        if current.dense.is_empty() {
+            break;
+        }
+        // Kinds around the position after this character
+        let next_around = (after, kind_at(text, at + len));
+        for i in 0..current.dense.len() {
+            let pc = current.dense[i];
+            let thread = &current.slots[pc * width..(pc + 1) * width];
+            let target = match &program.insts[pc] {
+                Inst::Match => {
+                    // Threads after this one have less priority
+                    matched = Some(thread.to_vec());
+                    break;
+                }
+                Inst::Char(d, target) if len > 0 && *d == c => *target,
+                Inst::Class(ranges, target) if len > 0 && class_contains(ranges, c) => *target,
+                _ => continue,
+            };
+            slots.copy_from_slice(thread);
+            vm.add(&mut next, target, at + len, &mut slots, next_around);
+        }
+        if len == 0 {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.clear();
+        at += len;
+        before = after;
+    }
+    matched
+}
@@ -0,0 +1,188 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Search of bytes in binary data.
+//!
+//! Boyer-Moore-Horspool; the pattern is compared from its end and, when it does not match, it moves forward as much
+//! as the byte under its last position allows. The reader is read in blocks and only the bytes which can still be
+//! part of a match are kept between them, so files of any size are searched with the same memory.
+//! All the offsets are given, also the ones of matches which overlap.
+
+use std::io::{self, Read};
+
+use crate::RavnError;
+
+// Bytes read each time
+const BLOCK: usize = 64 * 1024;
+
+/// Bytes to search, where any byte can be a wildcard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BytePattern {
+    bytes: Vec<Option<u8>>,
+}
+
+impl BytePattern {
+    /// The bytes as they are, without wildcards.
+    pub fn new(bytes: &[u8]) -> BytePattern {
+        BytePattern {
+            bytes: bytes.iter().map(|e| Some(*e)).collect(),
+        }
+    }
+
+    /// Bytes in hexadecimal, "??" is any byte; "de ad ?? ef", "DEADBEEF" or "7f454c46 02".
+    pub fn parse_hex(text: &str) -> Result<BytePattern, RavnError> {
+        let error = |column: usize, message: &str| RavnError {
+            line: 1,
+            column,
+            message: message.to_string(),
+        };
+        let digits: Vec<(usize, char)> = text.chars().enumerate().filter(|e| !e.1.is_whitespace()).collect();
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        for pair in digits.chunks(2) {
+            let (column, high) = pair[0];
+            let low = match pair.get(1) {
+                Some(d) => d.1,
+                None => return Err(error(column + 1, "half byte, the hexadecimal digits must be in pairs")),
+            };
+            if high == '?' && low == '?' {
+                bytes.push(None);
+                continue;
+            }
+            match (high.to_digit(16), low.to_digit(16)) {
+                (Some(h), Some(l)) => bytes.push(Some((h * 16 + l) as u8)),
+                _ => return Err(error(column + 1, &format!("'{high}{low}' is not a hexadecimal byte or '??'"))),
+            }
+        }
+        if bytes.is_empty() {
+            return Err(error(1, "empty pattern"));
+        }
+        Ok(BytePattern { bytes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// If the pattern is at the start of "data".
+    pub fn matches_at(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len()
+            && self.bytes.iter().zip(data).rev().all(|(p, b)| p.is_none_or(|p| p == *b))
+    }
+
+    // How much the pattern moves by the byte under its last position
+    fn shifts(&self) -> [usize; 256] {
+        let length = self.bytes.len();
+        let mut shifts = [length; 256];
+        // Only the positions before the last, the nearest to the end wins
+        for (i, byte) in self.bytes.iter().enumerate().take(length.saturating_sub(1)) {
+            let shift = length - 1 - i;
+            match byte {
+                Some(d) => shifts[*d as usize] = shift,
+                // A wildcard matches any byte, nothing can move beyond it
+                None => shifts.iter_mut().for_each(|e| *e = shift),
+            }
+        }
+        shifts
+    }
+}
+
+/// Iterator over the byte offsets of the pattern in the reader.
+pub struct ByteSearch<R: Read> {
+    reader: R,
+    pattern: BytePattern,
+    shifts: [usize; 256],
+    buffer: Vec<u8>,
+    // Offset in the reader of the start of the buffer
+    start: u64,
+    // Next position of the pattern in the buffer
+    at: usize,
+    eof: bool,
+}
+
+impl<R: Read> ByteSearch<R> {
+    pub fn new(reader: R, pattern: BytePattern) -> ByteSearch<R> {
+        ByteSearch {
+            reader,
+            shifts: pattern.shifts(),
+            pattern,
+            buffer: Vec::new(),
+            start: 0,
+            at: 0,
+            eof: false,
+        }
+    }
+
+    // Drops what was searched and reads the next block, false at the end
+    fn fill(&mut self) -> io::Result<bool> {
+        // The last move can go beyond the buffer, the rest is skipped in the next block
+        let searched = self.at.min(self.buffer.len());
+        self.buffer.drain(..searched);
+        self.start += searched as u64;
+        self.at -= searched;
+        let length = self.buffer.len();
+        self.buffer.resize(length + BLOCK, 0);
+        loop {
+            match self.reader.read(&mut self.buffer[length..]) {
+                Ok(read) => {
+                    self.buffer.truncate(length + read);
+                    return Ok(read > 0);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(length);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for ByteSearch<R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<io::Result<u64>> {
+        let length = self.pattern.len();
+        if length == 0 {
+            return None;
+        }
+        loop {
+            while self.at + length <= self.buffer.len() {
+                let at = self.at;
+                self.at += self.shifts[self.buffer[at + length - 1] as usize];
+                if self.pattern.matches_at(&self.buffer[at..]) {
+                    return Some(Ok(self.start + at as u64));
+                }
+            }
+            if self.eof {
+                return None;
+            }
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.eof = true;
+                    return None;
+                }
+                Err(e) => {
+                    // The reader can not continue after an error
+                    self.eof = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
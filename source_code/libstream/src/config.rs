@@ -0,0 +1,226 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! "key = value" configuration parser.
+//!
+//! - Each line is split on the first '=', so values can have '='; "arguments = --opt=1".
+//! - Lines starting with '#' or ';' are comments, '#' after a space is a comment until the end of the line.
+//! - Values can be quoted; "..." with the escapes \", \\, \n and \t, or '...' taken as is.
+//! - A '\' at the end of the line continues the value in the next one, joined with a space.
+//! - Keys can be repeated, "parse_config" returns them as a list.
+
+use crate::ravnkey::{RavnError, RavnValue};
+
+/// A "key = value" line, with the position (from 1) of its key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, line: usize, column: usize, message: String) -> RavnError {
+        RavnError { line, column, message }
+    }
+
+    // Spaces and tabs, not the end of the line
+    fn skip_blank(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n' && c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    // True if only blanks are left until the end of the line
+    fn at_line_end(&self) -> bool {
+        self.chars[self.pos..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .all(|c| c.is_whitespace())
+    }
+
+    fn quoted(&mut self, quote: char, value: &mut String) -> Result<(), RavnError> {
+        let (line, column) = (self.line, self.column);
+        self.next();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error(line, column, format!("'{quote}' without closing"))),
+                Some(c) if c == quote => return Ok(()),
+                Some('\\') if quote == '"' => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => return Err(self.error(line, column, format!("'{quote}' without closing"))),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    // Value until the end of the line, the '=' was consumed
+    fn value(&mut self) -> Result<String, RavnError> {
+        let mut value = String::new();
+        // Length without the blanks at the end, quoted text is kept
+        let mut keep = 0;
+        self.skip_blank();
+        while let Some(c) = self.peek() {
+            match c {
+                '\n' => {
+                    self.next();
+                    break;
+                }
+                '"' | '\'' => {
+                    self.quoted(c, &mut value)?;
+                    keep = value.len();
+                }
+                '#' if value.is_empty() || value.ends_with(char::is_whitespace) => {
+                    self.skip_line();
+                    break;
+                }
+                '\\' => {
+                    self.next();
+                    if self.at_line_end() {
+                        // Continuation, the next line is part of the value
+                        self.skip_line();
+                        self.skip_blank();
+                        value.truncate(keep);
+                        if !value.is_empty() {
+                            value.push(' ');
+                        }
+                        continue;
+                    }
+                    match self.next() {
+                        Some(e @ ('#' | '"' | '\'' | '\\')) => value.push(e),
+                        Some(e) => {
+                            value.push('\\');
+                            value.push(e);
+                        }
+                        None => value.push('\\'),
+                    }
+                    keep = value.len();
+                }
+                _ => {
+                    self.next();
+                    value.push(c);
+                    if !c.is_whitespace() {
+                        keep = value.len();
+                    }
+                }
+            }
+        }
+        value.truncate(keep);
+        Ok(value)
+    }
+}
+
+/// Parse the "key = value" lines in order, repeated keys are kept as they are.
+pub fn parse_config_entries(input: &str) -> Result<Vec<ConfigEntry>, RavnError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    let mut entries = Vec::new();
+    loop {
+        parser.skip_blank();
+        let (line, column) = (parser.line, parser.column);
+        match parser.peek() {
+            None => break,
+            Some('\n') => {
+                parser.next();
+            }
+            Some('#' | ';') => parser.skip_line(),
+            Some(_) => {
+                let mut key = String::new();
+                loop {
+                    match parser.peek() {
+                        Some('=') => {
+                            parser.next();
+                            break;
+                        }
+                        None | Some('\n') => {
+                            return Err(parser.error(line, column, format!("expected '=' after '{}'", key.trim())))
+                        }
+                        Some(c) => {
+                            parser.next();
+                            key.push(c);
+                        }
+                    }
+                }
+                let key = key.trim().to_string();
+                if key.is_empty() {
+                    return Err(parser.error(line, column, "missing key before '='".to_string()));
+                }
+                let value = parser.value()?;
+                entries.push(ConfigEntry { key, value, line, column });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Parse the configuration as a block, keys in the order they first appear and repeated ones as a list.
+pub fn parse_config(input: &str) -> Result<RavnValue, RavnError> {
+    Ok(group_entries(&parse_config_entries(input)?))
+}
+
+pub(crate) fn group_entries(entries: &[ConfigEntry]) -> RavnValue {
+    let mut block: Vec<(String, RavnValue)> = Vec::new();
+    for entry in entries {
+        let value = RavnValue::from(entry.value.as_str());
+        match block.iter_mut().find(|(k, _)| *k == entry.key) {
+            Some((_, RavnValue::List(items))) => items.push(value),
+            Some((_, first)) => *first = RavnValue::List(vec![first.clone(), value]),
+            None => block.push((entry.key.clone(), value)),
+        }
+    }
+    RavnValue::Block(block)
+}
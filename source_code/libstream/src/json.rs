@@ -0,0 +1,415 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! JSON (RFC 8259) parser and writer, and conversions with ravnkey.
+//!
+//! Numbers are kept as the text they were written with, so nothing is lost by rounding.
+//! From ravnkey; text is a string, a block is an object and a list is an array, repeated keys are joined in an array.
+//! To ravnkey; objects are blocks, arrays are lists, numbers and booleans are text and null is empty text.
+
+use std::fmt;
+
+use crate::ravnkey::{RavnError, RavnValue};
+
+// Objects and arrays nested more than this are an error, instead of running out of stack
+const MAX_DEPTH: usize = 512;
+
+/// JSON value, objects keep the order of their keys.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// The value of the first key, if it is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(d) => d.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The text, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(d) => Some(d),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(text: &str) -> JsonValue {
+        JsonValue::String(text.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(text: String) -> JsonValue {
+        JsonValue::String(text)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> JsonValue {
+        JsonValue::Bool(b)
+    }
+}
+
+impl From<i64> for JsonValue {
+    fn from(n: i64) -> JsonValue {
+        JsonValue::Number(n.to_string())
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", to_json(self))
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> RavnError {
+        RavnError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), RavnError> {
+        for c in word.chars() {
+            if self.peek() != Some(c) {
+                return Err(self.error(&format!("expected '{word}'")));
+            }
+            self.next();
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, RavnError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deep"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some('n') => self.expect("null").map(|_| JsonValue::Null),
+            Some('t') => self.expect("true").map(|_| JsonValue::Bool(true)),
+            Some('f') => self.expect("false").map(|_| JsonValue::Bool(false)),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('[') => {
+                self.next();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next();
+                    return Ok(JsonValue::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(JsonValue::Array(items)),
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.next();
+                let mut entries = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.next();
+                    return Ok(JsonValue::Object(entries));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a key between quotes"));
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.next() != Some(':') {
+                        return Err(self.error("expected ':' after the key"));
+                    }
+                    entries.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(JsonValue::Object(entries)),
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(c) => Err(self.error(&format!("unexpected '{c}'"))),
+        }
+    }
+
+    // -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Result<JsonValue, RavnError> {
+        let start = self.pos;
+        let digits = |parser: &mut Parser| {
+            let from = parser.pos;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.next();
+            }
+            parser.pos - from
+        };
+        if self.peek() == Some('-') {
+            self.next();
+        }
+        if self.peek() == Some('0') {
+            self.next();
+        } else if digits(self) == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.peek() == Some('.') {
+            self.next();
+            if digits(self) == 0 {
+                return Err(self.error("expected a digit after '.'"));
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.next();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.next();
+            }
+            if digits(self) == 0 {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+        Ok(JsonValue::Number(self.chars[start..self.pos].iter().collect()))
+    }
+
+    fn hex4(&mut self) -> Result<u32, RavnError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(d) => {
+                    code = code * 16 + d;
+                    self.next();
+                }
+                None => return Err(self.error("expected four hexadecimal digits after '\\u'")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, RavnError> {
+        self.next();
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("string without closing '\"'")),
+                Some('"') => return Ok(text),
+                Some('\\') => match self.next() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('/') => text.push('/'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('u') => {
+                        let mut code = self.hex4()?;
+                        // UTF-16 surrogate pair
+                        if (0xd800..0xdc00).contains(&code) {
+                            if self.next() != Some('\\') || self.next() != Some('u') {
+                                return Err(self.error("expected the low surrogate of the pair"));
+                            }
+                            let low = self.hex4()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(self.error("invalid low surrogate"));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => text.push(c),
+                            None => return Err(self.error("invalid unicode escape")),
+                        }
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(self.error("control character inside string")),
+                Some(c) => text.push(c),
+            }
+        }
+    }
+}
+
+/// Parse a JSON document, errors have the line and column (from 1).
+pub fn parse_json(input: &str) -> Result<JsonValue, RavnError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected data after the value"));
+    }
+    Ok(value)
+}
+
+fn escape(text: &str, output: &mut String) {
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+// With indent each item goes in its own line, with a tab for each level
+fn write(value: &JsonValue, indent: Option<usize>, output: &mut String) {
+    let newline = |output: &mut String, level: usize| {
+        if indent.is_some() {
+            output.push('\n');
+            output.extend(std::iter::repeat_n('\t', level));
+        }
+    };
+    let level = indent.unwrap_or(0);
+    let inner = indent.map(|l| l + 1);
+    match value {
+        JsonValue::Null => output.push_str("null"),
+        JsonValue::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => output.push_str(n),
+        JsonValue::String(s) => escape(s, output),
+        JsonValue::Array(items) if items.is_empty() => output.push_str("[]"),
+        JsonValue::Object(entries) if entries.is_empty() => output.push_str("{}"),
+        JsonValue::Array(items) => {
+            output.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, level + 1);
+                write(item, inner, output);
+            }
+            newline(output, level);
+            output.push(']');
+        }
+        JsonValue::Object(entries) => {
+            output.push('{');
+            for (i, (key, item)) in entries.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, level + 1);
+                escape(key, output);
+                output.push_str(if indent.is_some() { ": " } else { ":" });
+                write(item, inner, output);
+            }
+            newline(output, level);
+            output.push('}');
+        }
+    }
+}
+
+/// Compact JSON, in one line.
+pub fn to_json(value: &JsonValue) -> String {
+    let mut output = String::new();
+    write(value, None, &mut output);
+    output
+}
+
+/// JSON with an item per line, indented with tabs.
+pub fn to_json_pretty(value: &JsonValue) -> String {
+    let mut output = String::new();
+    write(value, Some(0), &mut output);
+    output.push('\n');
+    output
+}
+
+/// Ravnkey as JSON, repeated keys are joined in an array in the place of the first one.
+pub fn ravnkey_to_json(value: &RavnValue) -> JsonValue {
+    match value {
+        RavnValue::Text(d) => JsonValue::String(d.clone()),
+        RavnValue::List(items) => JsonValue::Array(items.iter().map(ravnkey_to_json).collect()),
+        RavnValue::Block(entries) => {
+            let mut object: Vec<(String, JsonValue)> = Vec::new();
+            for (key, data) in entries {
+                let count = entries.iter().filter(|(k, _)| k == key).count();
+                if count == 1 {
+                    object.push((key.clone(), ravnkey_to_json(data)));
+                } else if !object.iter().any(|(k, _)| k == key) {
+                    let items = value.get_all(key).into_iter().map(ravnkey_to_json).collect();
+                    object.push((key.clone(), JsonValue::Array(items)));
+                }
+            }
+            JsonValue::Object(object)
+        }
+    }
+}
+
+/// JSON as ravnkey; numbers and booleans as text, null as empty text.
+pub fn json_to_ravnkey(value: &JsonValue) -> RavnValue {
+    match value {
+        JsonValue::Null => RavnValue::Text(String::new()),
+        JsonValue::Bool(b) => RavnValue::Text(b.to_string()),
+        JsonValue::Number(n) => RavnValue::Text(n.clone()),
+        JsonValue::String(s) => RavnValue::Text(s.clone()),
+        JsonValue::Array(items) => RavnValue::List(items.iter().map(json_to_ravnkey).collect()),
+        JsonValue::Object(entries) => {
+            RavnValue::Block(entries.iter().map(|(k, v)| (k.clone(), json_to_ravnkey(v))).collect())
+        }
+    }
+}
@@ -21,11 +21,36 @@ use std::collections::HashMap;
 /// Filesystem System lib
 use std::fs::{self, File};
 /// Input Output lib
-use std::io::{self, Read};
+use std::io;
 /// Standard path
 use std::path::PathBuf;
 use std::path::Path;
 
+mod bytes;
+mod config;
+mod json;
+mod lines;
+mod query;
+mod ravnkey;
+mod schema;
+mod time;
+mod walk;
+pub use bytes::{ByteSearch, BytePattern};
+pub use config::{parse_config, parse_config_entries, ConfigEntry};
+pub use json::{json_to_ravnkey, parse_json, ravnkey_to_json, to_json, to_json_pretty, JsonValue};
+pub use lines::{
+    find_bytes, is_binary, is_binary_file, search_file, search_lines, LineMatch, LineMatcher, LineSearch, Substring,
+    BINARY_BLOCK,
+};
+pub use query::{query_ravnkey, RavnQuery};
+pub use ravnkey::{escape_ravnkey, parse_ravnkey, to_ravnkey, RavnError, RavnValue, RavnWriter};
+pub use schema::{RavnSchema, SchemaDiagnostic, SchemaReport, SchemaType, Severity};
+pub use time::{
+    civil_from_days, days_from_civil, days_in_month, is_leap_year, parse_timestamp, weekday_from_days, DateTime,
+    TimeZone,
+};
+pub use walk::{WalkEntry, WalkError, Walker};
+
 /// Struct for recursive reading
 // With the derive(Clone) we allow it to be cloned
 #[derive(Clone, Debug)]
@@ -73,288 +98,77 @@ pub trait Epoch {
 }
 
 impl Epoch for i64 {
-    // I based the below code in;
-    // https://www.geeksforgeeks.org/convert-unix-timestamp-to-dd-mm-yyyy-hhmmss-format/
-    // Thanks guys! :D
-
+    /// Seconds since 1970 as "YYYY-MM-DD HH:MM:SS UTC".
     fn epoch_to_human(&self) -> String {
-        let daysm = vec![31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-        let mut curr_year: i64;
-        let mut days_till_now: i64;
-        let extra_time: i64;
-        let mut extra_days: i64;
-        let mut index: usize = 0;
-        let date: i64;
-        let mut month: usize = 0;
-        let hours: i64;
-        let minutes: i64;
-        let secondss: i64;
-        // is not storing information yet, because of that we can avoidd "mut" keyword
-        let flag: usize;
-
-        // Calculate total days unix time T
-        days_till_now = self.clone() / (24 * 60 * 60);
-        extra_time = (self.clone() % (24 * 60 * 60)) as i64;
-        curr_year = 1970;
-
-        // Calculating current year
-        loop {
-            if curr_year % 400 == 0 || (curr_year % 4 == 0 && curr_year % 100 != 0) {
-                if days_till_now < 366 {
-                    break;
-                }
-                days_till_now -= 366;
-            } else {
-                if days_till_now < 365 {
-                    break;
-                }
-                days_till_now -= 365;
-            }
-            curr_year += 1;
-        }
-        // Updating extradays because it
-        // will give days till previous day
-        // and we have include current day
-        extra_days = (days_till_now + 1) as i64;
-
-        if curr_year % 400 == 0 || (curr_year % 4 == 0 && curr_year % 100 != 0) {
-            flag = 1;
-        } else {
-            flag = 0;
-        }
-
-        if flag == 1 {
-            loop {
-                if index == 1 {
-                    if extra_days - 29 < 0 {
-                        break;
-                    }
-                    month += 1;
-                    extra_days -= 29;
-                } else {
-                    if extra_days - daysm[index] < 0 {
-                        break;
-                    }
-                    month += 1;
-                    extra_days -= daysm[index];
-                }
-                index += 1;
-            }
-        } else {
-            loop {
-                if extra_days - daysm[index] < 0 {
-                    break;
-                }
-                month += 1;
-                extra_days -= daysm[index];
-                index += 1;
-            }
-        }
-
-        // Current Month
-        if extra_days > 0 {
-            month += 1;
-            date = extra_days;
-        } else {
-            if month == 2 && flag == 1 {
-                date = 29;
-            } else {
-                date = daysm[month - 1];
-            }
-        }
-
-        // Calculating HH:MM:YYYY
-        hours = extra_time / 3600;
-        minutes = (extra_time % 3600) / 60;
-        secondss = (extra_time % 3600) % 60;
-
-        format!(
-            "{}/{}/{} {}:{}:{} UTC-0",
-            &date, &month, &curr_year, &hours, &minutes, &secondss
-        )
+        DateTime::from_timestamp(*self, 0).format("%Y-%m-%d %H:%M:%S %Z")
     }
 }
 
 impl Stream for String {
-    /// The self string is the configurations
+    /// The self string is the configurations, "key = value" lines.
+    /// The return is a HashMap with syntax <key,value>, with repeated keys the last one is kept.
     fn readconfig(&self)-> HashMap<String, String>{
-        let hmap:  HashMap<String, String>;
-        let mut buff2: Vec<&str> = Vec::new();
-        for tline in self.lines(){
-            let borrow: Vec<&str> = tline.split('=').map(|e| e.trim()).collect();
-            for i in borrow {
-                buff2.push(i);
+        match parse_config_entries(self) {
+            Ok(d) => d.into_iter().map(|e| (e.key, e.value)).collect(),
+            Err(e) => {
+                eprintln!("Error parsing configuration; {e}");
+                HashMap::new()
             }
         }
-        hmap = buff2.chunks_exact(2)
-            .map(|chunk| (chunk[0].to_string(), chunk[1].to_string()))
-            .collect::<HashMap<_, _>>();
-        hmap
     }
     /// The self string is the data.
     /// The return is a HashMap with syntax <key,data>
+    /// Only the top level keys are returned, nested blocks are kept as ravnkey text in data.
     fn readkey(&self) -> HashMap<String, String> {
-        // Split the string into chars slice.
-        // Must be mutable so we can iterate with "next" method.
-        let char_collector = self.chars();
-        let mut str_collector: Vec<String> = Vec::new();
-        let mut keys: Vec<String> = Vec::new();
-        let mut data: Vec<String> = Vec::new();
-        let mut hmap: HashMap<String, String> = HashMap::new();
-
-        let mut buffer_k: String = String::new();
-        let mut buffer_d: String = String::new();
-
-        for value in char_collector {
-            str_collector.push(value.to_string());
-        }
-
-        for value in str_collector {
-            // Check if the value not start with { or }
-            // this is for the key
-            if value != "{" && value != "}" {
-                if data.len() < keys.len() || data.len() == keys.len() {
-                    buffer_d = buffer_d.clone() + &value.to_string();
-                }
-            } else {
-                keys.push(buffer_k.trim().to_string());
-                // Shadowing
-                buffer_k = String::new();
-                data.push(buffer_d.trim().to_string());
-                // Shadowing
-                buffer_d = String::new();
-            }
-        }
-
-        for value in 0..data.len() {
-            // check if position is odd
-            if let 0 = value % 2 {
-                hmap.insert(data[value].clone(), data[value + 1].clone());
+        match ravnkey::flat_keys(self) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error parsing ravnkey; {e}");
+                HashMap::new()
             }
         }
-
-        hmap
     }
 
     /// Read directories and returns PathBuf with each file and directory.
+    /// If the directory can not be read the error is printed and the vector is empty.
     fn readdir(&self) -> Vec<PathBuf> {
-        let pathname = &(self.to_string());
-        // Read the directory
-        let entries: Vec<PathBuf> = match fs::read_dir(&self) {
-            Err(_e) => {
-                eprintln!("Fail/Error reading path; {}", &pathname);
-                // Create a temp dir just for read something empty
-                std::fs::create_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap();
-                let temp = fs::read_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap().map(|res| res.map(|e| e.path()))
-                // Here we customice the collect method to returns as Result<V,E>
-                .collect::<Result<Vec<_>, io::Error>>()
-                .unwrap();
-
-                // Remove the temp dir to clean
-                std::fs::remove_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap();
-                temp
-            },
-            Ok(d) => {
-                d.map(|res| res.map(|e| e.path()))
-                // Here we customice the collect method to returns as Result<V,E>
-                .collect::<Result<Vec<_>, io::Error>>()
-                .unwrap()
+        let entries = match fs::read_dir(self) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Fail/Error reading path; {}; {e}", self);
+                return Vec::new();
             }
         };
         entries
+            .filter_map(|entry| match entry {
+                Ok(d) => Some(d.path()),
+                Err(e) => {
+                    eprintln!("Fail/Error reading entry of; {}; {e}", self);
+                    None
+                }
+            })
+            .collect()
     }
 
-    // Read dir recursive
+    /// Read dir recursive, the directories and the other files found under the path, sorted.
+    /// Symbolic links are not followed, errors are printed and the read continues.
     fn readdir_recursive(&self) -> DirStructure {
-        // I must use a closure here to not re write readdir function
-        let readdir = |path: String| -> Vec<PathBuf> {
-            let pathname = &(path.to_string());
-            // Read the directory
-            let entries: Vec<PathBuf> = match fs::read_dir(path) {
-                Err(_e) => {
-                    eprintln!("Fail/Error reading path; {}", &pathname);
-                    // Create a temp dir just for read something empty
-                    std::fs::create_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap();
-                    let temp = fs::read_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap().map(|res| res.map(|e| e.path()))
-                    // Here we customice the collect method to returns as Result<V,E>
-                    .collect::<Result<Vec<_>, io::Error>>()
-                    .unwrap();
-
-                    // Remove the temp dir to clean
-                    std::fs::remove_dir("/tmp/.temp_dir_err_readdir_recursive").unwrap();
-                    temp
-                },
-                Ok(d) => {
-                    d.map(|res| res.map(|e| e.path()))
-                    // Here we customice the collect method to returns as Result<V,E>
-                    .collect::<Result<Vec<_>, io::Error>>()
-                    .unwrap()
-                }
-            };
-            entries
-        };
-
-        // Path buff
-        let mut vec: Vec<PathBuf> = readdir(self.clone());
-
-        // Structure
-        let mut dstructure_complete = DirStructure {
+        let mut dstructure = DirStructure {
             dbuff: Vec::new(),
             fbuff: Vec::new(),
         };
-
-        let mut dstructure: Vec<String> = Vec::new();
-        let mut fstructure: Vec<String> = Vec::new();
-
-        // We must use another variable to use as check
-        // This is becasue we must verify if already readed the directory before.
-        let mut dstructure_check: Vec<String> = Vec::new();
-
-        // Verification variable
-        // alod = at least one directory
-        let mut alod = true;
-
-        // While "alod" is true keeps in loop
-        while alod {
-            // Iterate over each "vec" value.
-            // As then is overwritted we must use it by reference.
-            for entry in &vec {
-                // Check if is dir.
-                let metadata = match fs::metadata(entry) {
-                    Ok(r) => r,
-                    Err(_e) => {
-                        eprintln!("{}; Error reading metadata.", entry.display());
-                        continue;
-                    }
-                };
-
-                if metadata.is_dir() {
-                    if !dstructure.contains(&entry.display().to_string()) {
-                        dstructure.push(entry.clone().display().to_string());
-                    }
-                } else {
-                    // If is file cast it to string and save it in vector.
-                    fstructure.push(entry.display().to_string());
-                }
-            }
-
-            for entry_n in &dstructure {
-                if !dstructure_check.contains(&entry_n) {
-                    dstructure_check.push(entry_n.to_string());
-                    alod = true;
-                    for ndir in readdir(entry_n.clone()) {
-                        vec.push(ndir);
-                    }
-                } else {
-                    alod = false;
+        for entry in Walker::new(self).sorted(true) {
+            match entry {
+                Ok(d) if d.depth == 0 => {}
+                // Links to directories are listed with them, but not entered
+                Ok(d) if d.is_dir() || (d.symlink && d.path.is_dir()) => {
+                    dstructure.dbuff.push(d.path.display().to_string())
                 }
+                Ok(d) => dstructure.fbuff.push(d.path.display().to_string()),
+                Err(e) => eprintln!("{e}"),
             }
         }
-
-        dstructure_complete.dbuff = dstructure.clone();
-        dstructure_complete.fbuff = fstructure.clone();
-
-        dstructure_complete
+        dstructure
     }
 
     /// Count words and letters
@@ -506,38 +320,24 @@ impl Stream for String {
 
 /// Filename is the file's name to open.
 /// Input is the string to search
-/// Search for "input" into the file and returns strings.
+/// Search for "input" into the file and returns the lines which contain it.
+/// The file is read line by line, invalid UTF-8 is replaced by U+FFFD.
 pub fn file_filter(filename: &String, input: String) -> Vec<String> {
-    // Read the file as string and then save to lines buffer.
-    let mut buffer1 = String::new();
-
-    // Open file
-    let mut file = File::open(filename).unwrap();
-
-    // Read file to string and save in buffer1
-    match file.read_to_string(&mut buffer1) {
-        // If provides error
-        Err(_e) => {
-            // Show an error about the specific file and cleans the buffer to not break all process.
-            eprintln!("Error to read file; {filename} do not contains valid UTF-8 data");
-            buffer1 = String::new();
-            1
-        }
+    let matches = match search_file(filename, &input) {
         Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error to read file; {filename}; {e}");
+            return Vec::new();
+        }
     };
-
-    // Split in lines
-    let lbuffer = buffer1.lines();
-
-    // If you return directly from "for" loop will be taken as '()'
-    // instead String.
     let mut rstr: Vec<String> = Vec::new();
-
-    // Goes over each line
-    for word in lbuffer {
-        // Verify if the line contains the word
-        if word.contains(&input) {
-            rstr.push(word.to_string());
+    for found in matches {
+        match found {
+            Ok(d) => rstr.push(d.text().to_string()),
+            Err(e) => {
+                eprintln!("Error to read file; {filename}; {e}");
+                break;
+            }
         }
     }
     rstr
@@ -620,35 +420,108 @@ pub fn getprocs() -> Vec<String> {
 }
 
 // Binary search
-pub fn binary_search<'a>(filename: &String, mut file: File, ssearch: String) -> Result<(), &'a str> {
-    if Path::new(filename).is_file() {
-            let mut buffer_file: Vec<u8> = Vec::new();
-            file.read_to_end(&mut buffer_file).expect("Fail reading file; filename}");
-            // Drop from memory the file opened since the data is in buffer_file now
-            drop(file);
-            // TRansform the string ssearch to bytes (binary)
-            let dtsearch = ssearch.as_bytes();
-            let mut j = 0;
-            // i itertare over all buffer_file bytes, remember "len" calculate the size in bytes
-            for i in 0..buffer_file.len() {
-                // If the "i" byte of buffer match with "j" byte in dtsearch, add 1 to j for next and
-                // then check if "j" is the end of dtsearch
-                // And so on in which this iterate over each
-                if buffer_file[i] == dtsearch[j] {
-                    j += 1;
-
-                    if j == dtsearch.len() {
-                        return Ok(());
+/// If the bytes of "ssearch" are in the file.
+pub fn binary_search<'a>(filename: &String, file: File, ssearch: String) -> Result<(), &'a str> {
+    if !Path::new(filename).is_file() {
+        return Err("Is not a file");
+    }
+    match ByteSearch::new(file, BytePattern::new(ssearch.as_bytes())).next() {
+        Some(Ok(_d)) => Ok(()),
+        Some(Err(_e)) => Err("Fail reading file"),
+        None => Err("Not found in binary"),
+    }
+}
+
+/// Match "input" against a shell like glob "pattern".
+/// Supports; '*' any string, '?' any character and '[abc]', '[a-z]' or '[!abc]' character sets.
+pub fn glob_match(pattern: &str, input: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let input: Vec<char> = input.chars().collect();
+    // Position in pattern and input
+    let (mut p, mut i) = (0, 0);
+    // Last '*' seen in pattern and the input position where it started to match
+    let mut star: Option<(usize, usize)> = None;
+
+    while i < input.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    star = Some((p, i));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    i += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = glob_class(&pattern, p, input[i]) {
+                        if matched {
+                            p = next;
+                            i += 1;
+                            continue;
+                        }
+                    } else if input[i] == '[' {
+                        // Not closed, so is taken as literal
+                        p += 1;
+                        i += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == input[i] {
+                        p += 1;
+                        i += 1;
+                        continue;
                     }
-                } else {
-                    j = 0;
                 }
             }
-            Err("Not found in binary")
+        }
+        // Mismatch, go back to the last '*' and let it take one more character
+        match star {
+            Some((sp, si)) => {
+                star = Some((sp, si + 1));
+                p = sp + 1;
+                i = si + 1;
+            }
+            None => return false,
+        }
+    }
 
-    } else {
-        Err("Is not a file")
+    // Only '*' can remain in pattern
+    pattern[p..].iter().all(|e| *e == '*')
+}
+
+// Check if "c" is inside the "[...]" set which starts at "start" in pattern.
+// Returns if matched and the position after ']', or None if the set is not closed.
+fn glob_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut p = start + 1;
+    let negate = p < pattern.len() && (pattern[p] == '!' || pattern[p] == '^');
+    if negate {
+        p += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while p < pattern.len() {
+        // ']' as first character of the set is literal
+        if pattern[p] == ']' && !first {
+            return Some((matched != negate, p + 1));
+        }
+        if p + 2 < pattern.len() && pattern[p + 1] == '-' && pattern[p + 2] != ']' {
+            if pattern[p] <= c && c <= pattern[p + 2] {
+                matched = true;
+            }
+            p += 3;
+        } else {
+            if pattern[p] == c {
+                matched = true;
+            }
+            p += 1;
+        }
+        first = false;
     }
+    None
 }
 
 // Search and replace a string
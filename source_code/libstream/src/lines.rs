@@ -0,0 +1,292 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Line by line search over any reader.
+//!
+//! The lines are read into one buffer which is reused, only the lines that match (and the context ones) are copied.
+//! Lines are bytes, so files with invalid UTF-8 are searched too; "LineMatch::text" replaces the invalid sequences.
+//! As grep, the search can be inverted, stop after a number of matches and give lines of context around them.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Decides if a line matches, returning the byte offset of the match in the line.
+pub trait LineMatcher {
+    fn find(&mut self, line: &[u8]) -> Option<usize>;
+}
+
+impl<F: FnMut(&[u8]) -> Option<usize>> LineMatcher for F {
+    fn find(&mut self, line: &[u8]) -> Option<usize> {
+        self(line)
+    }
+}
+
+/// Matches the lines which contain the text.
+#[derive(Clone, Debug)]
+pub struct Substring {
+    needle: Vec<u8>,
+}
+
+impl Substring {
+    pub fn new(needle: &str) -> Substring {
+        Substring {
+            needle: needle.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl LineMatcher for Substring {
+    fn find(&mut self, line: &[u8]) -> Option<usize> {
+        find_bytes(line, &self.needle)
+    }
+}
+
+/// Position of the first "needle" in "haystack".
+pub fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let (first, rest) = match needle.split_first() {
+        Some(d) => d,
+        None => return Some(0),
+    };
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    let last = haystack.len() - needle.len();
+    let mut start = 0;
+    // Jump between the occurrences of the first byte and compare the rest there
+    while let Some(i) = haystack[start..=last].iter().position(|b| b == first) {
+        let at = start + i;
+        if &haystack[at + 1..at + needle.len()] == rest {
+            return Some(at);
+        }
+        start = at + 1;
+    }
+    None
+}
+
+/// A line that matched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineMatch {
+    /// Line number, from 1.
+    pub number: u64,
+    /// Byte offset of the line from the start of the input.
+    pub offset: u64,
+    /// Byte offset of the match in the line, 0 for inverted searches and context lines.
+    pub column: usize,
+    /// The line without its "\n" or "\r\n".
+    pub bytes: Vec<u8>,
+    /// True if the line is context before or after a match, and not a match.
+    pub context: bool,
+}
+
+impl LineMatch {
+    /// The line as text, invalid UTF-8 is replaced by U+FFFD.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+/// Iterator over the lines of a reader that match.
+pub struct LineSearch<R: BufRead, M: LineMatcher> {
+    reader: R,
+    matcher: M,
+    buffer: Vec<u8>,
+    number: u64,
+    offset: u64,
+    done: bool,
+    invert: bool,
+    before: usize,
+    after: usize,
+    max_count: Option<u64>,
+    matches: u64,
+    // Lines which can be context of the next match
+    previous: VecDeque<LineMatch>,
+    // Context lines still to give after the last match
+    after_left: usize,
+    // Lines ready to be given
+    ready: VecDeque<LineMatch>,
+}
+
+impl<R: BufRead, M: LineMatcher> LineSearch<R, M> {
+    pub fn new(reader: R, matcher: M) -> LineSearch<R, M> {
+        LineSearch {
+            reader,
+            matcher,
+            buffer: Vec::new(),
+            number: 0,
+            offset: 0,
+            done: false,
+            invert: false,
+            before: 0,
+            after: 0,
+            max_count: None,
+            matches: 0,
+            previous: VecDeque::new(),
+            after_left: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Give the lines which do not match.
+    pub fn invert(mut self, invert: bool) -> LineSearch<R, M> {
+        self.invert = invert;
+        self
+    }
+
+    /// Give also "before" lines before each match and "after" lines after it, as context.
+    pub fn context(mut self, before: usize, after: usize) -> LineSearch<R, M> {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Stop after "max" matches, the context after the last one is still given.
+    pub fn max_count(mut self, max: Option<u64>) -> LineSearch<R, M> {
+        self.max_count = max;
+        self
+    }
+
+    // Next line without the end of line, None at the end
+    fn read_line(&mut self) -> Option<io::Result<(u64, u64)>> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    let offset = self.offset;
+                    self.offset += read as u64;
+                    self.number += 1;
+                    if self.buffer.ends_with(b"\n") {
+                        self.buffer.pop();
+                        if self.buffer.ends_with(b"\r") {
+                            self.buffer.pop();
+                        }
+                    }
+                    return Some(Ok((self.number, offset)));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<R: BufRead, M: LineMatcher> Iterator for LineSearch<R, M> {
+    type Item = io::Result<LineMatch>;
+
+    fn next(&mut self) -> Option<io::Result<LineMatch>> {
+        loop {
+            if let Some(d) = self.ready.pop_front() {
+                return Some(Ok(d));
+            }
+            let full = self.max_count.is_some_and(|e| self.matches >= e);
+            if self.done || (full && self.after_left == 0) {
+                self.done = true;
+                return None;
+            }
+            let (number, offset) = match self.read_line() {
+                Some(Ok(d)) => d,
+                Some(Err(e)) => {
+                    // The reader can not continue after an error
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            };
+
+            let found = match (self.matcher.find(&self.buffer), self.invert) {
+                (Some(d), false) => Some(d),
+                (None, true) => Some(0),
+                _ => None,
+            };
+            let mut line = LineMatch {
+                number,
+                offset,
+                column: 0,
+                bytes: Vec::new(),
+                context: true,
+            };
+            match found {
+                Some(column) if !full => {
+                    self.matches += 1;
+                    self.after_left = self.after;
+                    line.column = column;
+                    line.context = false;
+                    line.bytes = self.buffer.clone();
+                    self.ready.extend(self.previous.drain(..));
+                    self.ready.push_back(line);
+                }
+                _ if self.after_left > 0 => {
+                    self.after_left -= 1;
+                    line.bytes = self.buffer.clone();
+                    self.ready.push_back(line);
+                }
+                _ if self.before > 0 => {
+                    // The oldest line is dropped, its buffer is reused
+                    let mut bytes = Vec::new();
+                    if self.previous.len() == self.before {
+                        bytes = self.previous.pop_front().map(|e| e.bytes).unwrap_or_default();
+                        bytes.clear();
+                    }
+                    bytes.extend_from_slice(&self.buffer);
+                    line.bytes = bytes;
+                    self.previous.push_back(line);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Search the text in each line of the reader.
+pub fn search_lines<R: BufRead>(reader: R, pattern: &str) -> LineSearch<R, Substring> {
+    LineSearch::new(reader, Substring::new(pattern))
+}
+
+/// Search the text in each line of the file.
+pub fn search_file<P: AsRef<Path>>(path: P, pattern: &str) -> io::Result<LineSearch<BufReader<File>, Substring>> {
+    Ok(search_lines(BufReader::new(File::open(path)?), pattern))
+}
+
+/// Bytes read by "is_binary_file" to decide.
+pub const BINARY_BLOCK: usize = 8192;
+
+/// If the block looks like binary data, as grep it is when there is a NUL byte.
+/// Eight bytes are checked at once.
+pub fn is_binary(block: &[u8]) -> bool {
+    const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+    let mut chunks = block.chunks_exact(8);
+    for chunk in &mut chunks {
+        let word = u64::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7]]);
+        // Only a zero byte sets its highest bit after the subtraction and not before
+        if word.wrapping_sub(ONES) & !word & HIGHS != 0 {
+            return true;
+        }
+    }
+    chunks.remainder().contains(&0)
+}
+
+/// Reads the first block of the reader and checks it with "is_binary".
+/// The block is returned to continue reading from it, as the reader can not go back.
+pub fn is_binary_file<R: Read>(reader: &mut R) -> io::Result<(bool, Vec<u8>)> {
+    let mut block = Vec::with_capacity(BINARY_BLOCK);
+    reader.take(BINARY_BLOCK as u64).read_to_end(&mut block)?;
+    Ok((is_binary(&block), block))
+}
@@ -0,0 +1,360 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Path queries over ravnkey documents.
+//!
+//! A query is a list of steps separated by dots, each one applied to the values selected by the previous;
+//! - "key"; values with that key, glob patterns ('*' and '?') are allowed, "sshd" or "ssh*".
+//! - "key#n"; only the n occurrence (from 0) of a repeated key, negative numbers count from the end.
+//! - "*"; all the children, entries of blocks and items of lists.
+//! - "**"; the value and all its descendants.
+//! - "[n]" and "[*]"; the n child (negative from the end) or all of them.
+//! - "[path=text]", "[path!=text]", "[path~glob]" and "[path]"; keep values whose child at "path" has
+//!   that text, has not that text, matches the glob or just exists. With empty path the value's own text is used.
+//!
+//! Keys with dots, brackets or '#' are written between double quotes, "\"example.com\".port".
+//! The path of each selected value is written with the same syntax, repeated keys after the first with
+//! their occurrence; "listen", "listen#1".
+//!
+//! Example; "services.*[user=root].binary" returns the binary of each service run by root.
+
+use crate::glob_match;
+use crate::ravnkey::{RavnError, RavnValue};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Exists,
+    Eq,
+    Ne,
+    Glob,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key { name: String, glob: bool, occurrence: Option<i64> },
+    Any,
+    Recursive,
+    Index(i64),
+    Filter { path: RavnQuery, op: Op, value: String },
+}
+
+/// Parsed query, it can be applied to several documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RavnQuery {
+    steps: Vec<Step>,
+}
+
+fn error(column: usize, message: &str) -> RavnError {
+    RavnError {
+        line: 1,
+        column,
+        message: message.to_string(),
+    }
+}
+
+// Position from the start, or from the end if negative
+fn pick<T>(items: Vec<T>, index: i64) -> Option<T> {
+    let len = items.len() as i64;
+    let index = if index < 0 { len + index } else { index };
+    if index < 0 || index >= len {
+        return None;
+    }
+    items.into_iter().nth(index as usize)
+}
+
+// Children with the label used to build their path
+fn children(value: &RavnValue) -> Vec<(String, &RavnValue)> {
+    match value {
+        RavnValue::Block(entries) => {
+            // Repeated keys as in the queries, "key", "key#1", "key#2"
+            let mut seen: Vec<&str> = Vec::new();
+            entries
+                .iter()
+                .map(|(k, v)| {
+                    let occurrence = seen.iter().filter(|e| **e == k.as_str()).count();
+                    seen.push(k);
+                    match occurrence {
+                        0 => (key_label(k), v),
+                        n => (format!("{}#{n}", key_label(k)), v),
+                    }
+                })
+                .collect()
+        }
+        RavnValue::List(items) => items.iter().enumerate().map(|(i, v)| (format!("[{i}]"), v)).collect(),
+        RavnValue::Text(_) => Vec::new(),
+    }
+}
+
+pub(crate) fn key_label(key: &str) -> String {
+    if key.contains(['.', '[', ']', '#', '"']) || key == "*" || key == "**" {
+        format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        key.to_string()
+    }
+}
+
+pub(crate) fn join(path: &str, label: &str) -> String {
+    if path.is_empty() || label.starts_with('[') {
+        format!("{path}{label}")
+    } else {
+        format!("{path}.{label}")
+    }
+}
+
+fn descendants<'a>(path: String, value: &'a RavnValue, output: &mut Vec<(String, &'a RavnValue)>) {
+    output.push((path.clone(), value));
+    for (label, child) in children(value) {
+        descendants(join(&path, &label), child, output);
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    // Key until '.', '[' or '#', returns it and if it was quoted
+    fn key(&mut self) -> Result<(String, bool), RavnError> {
+        let mut key = String::new();
+        if self.peek() == Some('"') {
+            let start = self.pos + 1;
+            self.pos += 1;
+            loop {
+                match self.peek() {
+                    None => return Err(error(start, "quote without closing")),
+                    Some('"') => {
+                        self.pos += 1;
+                        return Ok((key, true));
+                    }
+                    Some('\\') => {
+                        self.pos += 1;
+                        if let Some(c) = self.peek() {
+                            key.push(c);
+                            self.pos += 1;
+                        }
+                    }
+                    Some(c) => {
+                        key.push(c);
+                        self.pos += 1;
+                    }
+                }
+            }
+        }
+        while let Some(c) = self.peek() {
+            if matches!(c, '.' | '[' | '#') {
+                break;
+            }
+            if c == ']' {
+                return Err(error(self.pos + 1, "']' without opening '['"));
+            }
+            key.push(c);
+            self.pos += 1;
+        }
+        Ok((key.trim().to_string(), false))
+    }
+
+    fn number(&mut self) -> Result<i64, RavnError> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<i64>().map_err(|_e| error(start + 1, "expected a number"))
+    }
+
+    // Content of "[...]", the '[' was consumed
+    fn bracket(&mut self) -> Result<Step, RavnError> {
+        let start = self.pos;
+        let mut quoted = false;
+        let mut content = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(error(start, "'[' without closing ']'")),
+                Some(']') if !quoted => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) => {
+                    if c == '"' {
+                        quoted = !quoted;
+                    }
+                    content.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        let content = content.trim();
+        if content == "*" {
+            return Ok(Step::Any);
+        }
+        if let Ok(n) = content.parse::<i64>() {
+            return Ok(Step::Index(n));
+        }
+
+        // Filter; the first operator out of quotes splits the path and the text
+        let chars: Vec<char> = content.chars().collect();
+        let mut quoted = false;
+        let mut split: Option<(usize, usize, Op)> = None;
+        for i in 0..chars.len() {
+            match chars[i] {
+                '"' => quoted = !quoted,
+                '!' if !quoted && chars.get(i + 1) == Some(&'=') => {
+                    split = Some((i, 2, Op::Ne));
+                    break;
+                }
+                '=' if !quoted => {
+                    split = Some((i, 1, Op::Eq));
+                    break;
+                }
+                '~' if !quoted => {
+                    split = Some((i, 1, Op::Glob));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (path, op, value) = match split {
+            Some((i, len, op)) => {
+                let path: String = chars[..i].iter().collect();
+                let value: String = chars[i + len..].iter().collect();
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                (path, op, value.to_string())
+            }
+            None => (content.to_string(), Op::Exists, String::new()),
+        };
+        let path = RavnQuery::parse(path.trim()).map_err(|e| error(start + e.column, &e.message))?;
+        Ok(Step::Filter { path, op, value })
+    }
+}
+
+impl RavnQuery {
+    pub fn parse(query: &str) -> Result<RavnQuery, RavnError> {
+        let mut parser = Parser {
+            chars: query.chars().collect(),
+            pos: 0,
+        };
+        let mut steps = Vec::new();
+        // A leading dot is allowed, ".services" is the same as "services"
+        if parser.peek() == Some('.') {
+            parser.pos += 1;
+        }
+        while parser.peek().is_some() {
+            let column = parser.pos + 1;
+            let (name, quoted) = parser.key()?;
+            match (name.as_str(), quoted) {
+                ("**", false) => steps.push(Step::Recursive),
+                ("*", false) => steps.push(Step::Any),
+                ("", false) => {
+                    if parser.peek() != Some('[') {
+                        return Err(error(column, "empty key"));
+                    }
+                }
+                _ => {
+                    let mut occurrence = None;
+                    if parser.peek() == Some('#') {
+                        parser.pos += 1;
+                        occurrence = Some(parser.number()?);
+                    }
+                    let glob = !quoted && name.contains(['*', '?']);
+                    steps.push(Step::Key { name, glob, occurrence });
+                }
+            }
+            while parser.peek() == Some('[') {
+                parser.pos += 1;
+                steps.push(parser.bracket()?);
+            }
+            match parser.peek() {
+                None => break,
+                Some('.') => {
+                    parser.pos += 1;
+                    if parser.peek().is_none() {
+                        return Err(error(parser.pos, "query ends with '.'"));
+                    }
+                }
+                Some(c) => return Err(error(parser.pos + 1, &format!("unexpected '{c}'"))),
+            }
+        }
+        Ok(RavnQuery { steps })
+    }
+
+    /// Values selected with their path in the document, in the document's order.
+    pub fn select<'a>(&self, document: &'a RavnValue) -> Vec<(String, &'a RavnValue)> {
+        let mut current: Vec<(String, &'a RavnValue)> = vec![(String::new(), document)];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for (path, value) in current {
+                match step {
+                    Step::Key { name, glob, occurrence } => {
+                        let matched: Vec<(String, &RavnValue)> = value
+                            .entries()
+                            .unwrap_or_default()
+                            .iter()
+                            .zip(children(value))
+                            .filter(|((k, _), _)| if *glob { glob_match(name, k) } else { k == name })
+                            .map(|(_, (label, v))| (join(&path, &label), v))
+                            .collect();
+                        match occurrence {
+                            Some(n) => next.extend(pick(matched, *n)),
+                            None => next.extend(matched),
+                        }
+                    }
+                    Step::Any => {
+                        for (label, child) in children(value) {
+                            next.push((join(&path, &label), child));
+                        }
+                    }
+                    Step::Recursive => descendants(path, value, &mut next),
+                    Step::Index(n) => {
+                        if let Some((label, child)) = pick(children(value), *n) {
+                            next.push((join(&path, &label), child));
+                        }
+                    }
+                    Step::Filter { path: filter, op, value: text } => {
+                        let found = filter.select(value);
+                        let keep = match op {
+                            Op::Exists => !found.is_empty(),
+                            Op::Eq => found.iter().any(|(_, v)| v.as_text() == Some(text.as_str())),
+                            Op::Ne => !found.iter().any(|(_, v)| v.as_text() == Some(text.as_str())),
+                            Op::Glob => found
+                                .iter()
+                                .any(|(_, v)| v.as_text().is_some_and(|t| glob_match(text, t))),
+                        };
+                        if keep {
+                            next.push((path, value));
+                        }
+                    }
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// Parse the query and apply it to the document.
+pub fn query_ravnkey<'a>(document: &'a RavnValue, query: &str) -> Result<Vec<(String, &'a RavnValue)>, RavnError> {
+    Ok(RavnQuery::parse(query)?.select(document))
+}
@@ -0,0 +1,439 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Ravnkey parser and writer.
+//!
+//! A document is a sequence of "[key] { [data] }" entries, where data is one of;
+//! - text; "name { sshd }", the text is trimmed.
+//! - block; "sshd { binary { /bin/sshd } arguments { -D } }", entries keep their order and keys can be repeated.
+//! - list; "ports { { 22 } { 80 } }", items without key.
+//!
+//! Braces and backslashes inside text or keys are escaped with a backslash; "\{", "\}" and "\\".
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::query::{join, key_label};
+
+/// Value of a ravnkey entry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RavnValue {
+    Text(String),
+    Block(Vec<(String, RavnValue)>),
+    List(Vec<RavnValue>),
+}
+
+impl RavnValue {
+    /// The text, if the value is text.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            RavnValue::Text(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Entries in order, if the value is a block.
+    pub fn entries(&self) -> Option<&[(String, RavnValue)]> {
+        match self {
+            RavnValue::Block(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Items, if the value is a list.
+    pub fn items(&self) -> Option<&[RavnValue]> {
+        match self {
+            RavnValue::List(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// First value with the key, if the value is a block.
+    pub fn get(&self, key: &str) -> Option<&RavnValue> {
+        self.entries()?.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Add an entry at the end, only if the value is a block.
+    pub fn push<K: Into<String>>(&mut self, key: K, value: RavnValue) {
+        if let RavnValue::Block(entries) = self {
+            entries.push((key.into(), value));
+        }
+    }
+
+    /// Add an item at the end, only if the value is a list.
+    pub fn push_item(&mut self, value: RavnValue) {
+        if let RavnValue::List(items) = self {
+            items.push(value);
+        }
+    }
+
+    /// All the values with the key (repeated keys), if the value is a block.
+    pub fn get_all(&self, key: &str) -> Vec<&RavnValue> {
+        match self.entries() {
+            Some(entries) => entries.iter().filter(|(k, _)| k == key).map(|(_, v)| v).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl From<&str> for RavnValue {
+    fn from(text: &str) -> RavnValue {
+        RavnValue::Text(text.to_string())
+    }
+}
+
+impl From<String> for RavnValue {
+    fn from(text: String) -> RavnValue {
+        RavnValue::Text(text)
+    }
+}
+
+impl fmt::Display for RavnValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", to_ravnkey(self))
+    }
+}
+
+/// Parse error with the position (from 1) where it was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RavnError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RavnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}; {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for RavnError {}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    // Path of the entry being parsed and where each entry's key starts
+    path: Vec<String>,
+    positions: Positions,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, line: usize, column: usize, message: String) -> RavnError {
+        RavnError { line, column, message }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    // Read the character after a backslash, only braces and backslashes are escaped
+    fn escaped(&mut self, text: &mut String) {
+        match self.peek() {
+            Some(c @ ('{' | '}' | '\\')) => {
+                self.next();
+                text.push(c);
+            }
+            _ => text.push('\\'),
+        }
+    }
+
+    // Start an entry, its path is the same used by queries; "key", "key#1" if repeated and "[n]" for list items
+    fn enter(&mut self, key: &str, occurrence: usize, line: usize, column: usize) {
+        let label = match (key.is_empty(), occurrence) {
+            (true, n) => format!("[{n}]"),
+            (false, 0) => key_label(key),
+            (false, n) => format!("{}#{n}", key_label(key)),
+        };
+        let path = join(self.path.last().map_or("", |p| p.as_str()), &label);
+        self.positions.keys.entry(path.clone()).or_insert((line, column));
+        self.path.push(path);
+    }
+
+    // Read the key until its '{', which is consumed, returns the key and the brace position
+    fn key(&mut self) -> Result<(String, usize, usize), RavnError> {
+        let (line, column) = (self.line, self.column);
+        let mut key = String::new();
+        loop {
+            let (bline, bcolumn) = (self.line, self.column);
+            match self.next() {
+                Some('{') => return Ok((key.trim().to_string(), bline, bcolumn)),
+                Some('\\') => self.escaped(&mut key),
+                Some('}') | None => {
+                    return Err(self.error(line, column, format!("expected '{{' after '{}'", key.trim())))
+                }
+                Some(c) => key.push(c),
+            }
+        }
+    }
+
+    // Parse the data after a '{' until its '}'
+    fn value(&mut self, line: usize, column: usize) -> Result<RavnValue, RavnError> {
+        let mut text = String::new();
+        let mut start = None;
+        loop {
+            let (kline, kcolumn) = (self.line, self.column);
+            match self.next() {
+                None => return Err(self.error(line, column, "'{' without closing '}'".to_string())),
+                Some('\\') => self.escaped(&mut text),
+                Some('}') => {
+                    if let (Some(start), Some(path)) = (start, self.path.last()) {
+                        self.positions.texts.insert(path.clone(), start);
+                    }
+                    return Ok(RavnValue::Text(text.trim().to_string()));
+                }
+                Some('{') => {
+                    // The text read was the key of the first nested entry
+                    let key = text.trim().to_string();
+                    let (sline, scolumn) = start.unwrap_or((kline, kcolumn));
+                    self.enter(&key, 0, sline, scolumn);
+                    let first = (key, self.value(kline, kcolumn)?);
+                    self.path.pop();
+                    return self.entries(vec![first], Some((line, column)));
+                }
+                Some(c) => text.push(c),
+            }
+            if start.is_none() && !text.trim().is_empty() {
+                start = Some((kline, kcolumn));
+            }
+        }
+    }
+
+    // Parse entries until the '}' which closes the block (or the end of the input at the top level)
+    fn entries(
+        &mut self,
+        mut entries: Vec<(String, RavnValue)>,
+        open: Option<(usize, usize)>,
+    ) -> Result<RavnValue, RavnError> {
+        loop {
+            self.skip_whitespace();
+            match (self.peek(), open) {
+                (None, None) => break,
+                (None, Some((line, column))) => {
+                    return Err(self.error(line, column, "'{' without closing '}'".to_string()))
+                }
+                (Some('}'), Some(_)) => {
+                    self.next();
+                    break;
+                }
+                (Some('}'), None) => {
+                    return Err(self.error(self.line, self.column, "'}' without opening '{'".to_string()))
+                }
+                _ => {
+                    let (line, column) = (self.line, self.column);
+                    let (key, bline, bcolumn) = self.key()?;
+                    if key.is_empty() && open.is_none() {
+                        return Err(self.error(line, column, "entry without key".to_string()));
+                    }
+                    let occurrence = if key.is_empty() {
+                        entries.len()
+                    } else {
+                        entries.iter().filter(|(k, _)| *k == key).count()
+                    };
+                    self.enter(&key, occurrence, line, column);
+                    let value = self.value(bline, bcolumn)?;
+                    self.path.pop();
+                    entries.push((key, value));
+                }
+            }
+        }
+
+        // Items without key are a list, they can not be mixed with keys
+        let unnamed = entries.iter().filter(|(k, _)| k.is_empty()).count();
+        if unnamed == 0 {
+            Ok(RavnValue::Block(entries))
+        } else if unnamed == entries.len() {
+            Ok(RavnValue::List(entries.into_iter().map(|(_, v)| v).collect()))
+        } else {
+            let (line, column) = open.unwrap_or((1, 1));
+            Err(self.error(line, column, "block mixes items with and without key".to_string()))
+        }
+    }
+}
+
+/// Parse a ravnkey document, the result is a block with the top level entries.
+pub fn parse_ravnkey(input: &str) -> Result<RavnValue, RavnError> {
+    Ok(parse_positions(input)?.0)
+}
+
+/// Line and column where the key of each entry starts and where its text starts, by the entry's query path.
+#[derive(Default)]
+pub(crate) struct Positions {
+    pub keys: HashMap<String, (usize, usize)>,
+    pub texts: HashMap<String, (usize, usize)>,
+}
+
+/// Parse a document and also return the positions of its entries.
+pub(crate) fn parse_positions(input: &str) -> Result<(RavnValue, Positions), RavnError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+        path: Vec::new(),
+        positions: Positions::default(),
+    };
+    let document = parser.entries(Vec::new(), None)?;
+    Ok((document, parser.positions))
+}
+
+/// Escape braces and backslashes so the text can be used as ravnkey key or data.
+pub fn escape_ravnkey(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Writes ravnkey, one entry per line with a tab of indentation for each level.
+/// Text with more than one line is written from the next line after the '{'.
+pub struct RavnWriter<W: Write> {
+    inner: W,
+    depth: usize,
+}
+
+impl<W: Write> RavnWriter<W> {
+    pub fn new(inner: W) -> RavnWriter<W> {
+        RavnWriter { inner, depth: 0 }
+    }
+
+    fn indent(&mut self) -> io::Result<()> {
+        for _ in 0..self.depth {
+            self.inner.write_all(b"\t")?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, key: &str) -> io::Result<()> {
+        self.indent()?;
+        if key.is_empty() {
+            self.inner.write_all(b"{")
+        } else {
+            write!(self.inner, "{} {{", escape_ravnkey(key))
+        }
+    }
+
+    /// "[key] { [text] }", with empty key is a list item.
+    pub fn text(&mut self, key: &str, text: &str) -> io::Result<()> {
+        self.open(key)?;
+        let text = escape_ravnkey(text.trim());
+        if text.contains('\n') {
+            self.inner.write_all(b"\n")?;
+            writeln!(self.inner, "{text}")?;
+            self.indent()?;
+            self.inner.write_all(b"}\n")
+        } else {
+            writeln!(self.inner, " {text} }}")
+        }
+    }
+
+    /// Start the block "[key] {", with empty key is a list item.
+    pub fn begin(&mut self, key: &str) -> io::Result<()> {
+        self.open(key)?;
+        self.inner.write_all(b"\n")?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Close the last block started.
+    pub fn end(&mut self) -> io::Result<()> {
+        self.depth = self.depth.saturating_sub(1);
+        self.indent()?;
+        self.inner.write_all(b"}\n")
+    }
+
+    /// Write the value with its key, with empty key is a list item.
+    pub fn value(&mut self, key: &str, value: &RavnValue) -> io::Result<()> {
+        match value {
+            RavnValue::Text(d) => self.text(key, d),
+            RavnValue::Block(entries) => {
+                self.begin(key)?;
+                for (k, v) in entries {
+                    self.value(k, v)?;
+                }
+                self.end()
+            }
+            RavnValue::List(items) => {
+                self.begin(key)?;
+                for v in items {
+                    self.value("", v)?;
+                }
+                self.end()
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Serialize a value as ravnkey document; the entries of a block, the items of a list or the text.
+/// The output of a block is read back by "parse_ravnkey" as the same value, with its texts trimmed. Ravnkey
+/// has no syntax for an empty block or list, so inside a block they are written as "[key] { }" and read back
+/// as empty text. A list is written as items without key, which are only read inside a block.
+pub fn to_ravnkey(value: &RavnValue) -> String {
+    let mut writer = RavnWriter::new(Vec::new());
+    // Writing into a Vec<u8> can not fail
+    let _ = match value {
+        RavnValue::Text(d) => writer.inner.write_all(escape_ravnkey(d).as_bytes()),
+        RavnValue::Block(entries) => entries.iter().try_for_each(|(k, v)| writer.value(k, v)),
+        RavnValue::List(items) => items.iter().try_for_each(|v| writer.value("", v)),
+    };
+    String::from_utf8_lossy(&writer.into_inner()).to_string()
+}
+
+/// Top level entries as <key, data>, as "Stream::readkey" always did.
+/// Blocks are returned as ravnkey text and with repeated keys the last one is kept.
+pub(crate) fn flat_keys(input: &str) -> Result<HashMap<String, String>, RavnError> {
+    let document = parse_ravnkey(input)?;
+    let mut hmap = HashMap::new();
+    for (key, value) in document.entries().unwrap_or_default() {
+        let data = match value {
+            RavnValue::Text(d) => d.clone(),
+            _ => to_ravnkey(value).trim_end().to_string(),
+        };
+        hmap.insert(key.clone(), data);
+    }
+    Ok(hmap)
+}
@@ -0,0 +1,499 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Schemas for ravnkey documents.
+//!
+//! A schema declares the keys of a block, the type of their data, if they are required and their default.
+//! The types are; "string", "int", "bool" (true, false, yes, no, on, off, 1 or 0), "path", "any",
+//! "list [type]" and blocks with their own schema.
+//!
+//! Schemas can be written in ravnkey too, each key with its type followed by "required" or "default [text]",
+//! blocks are nested schemas and "*" gives the type of any other key;
+//! "* { binary { path required } arguments { string } restart { bool default false } }".
+//!
+//! Where a block is expected, text with "key = value" lines is validated as a block with those keys.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{group_entries, parse_config_entries};
+use crate::query::{join, key_label};
+use crate::ravnkey::{parse_positions, Positions, RavnError, RavnValue};
+
+/// Type of the data of a key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaType {
+    String,
+    Int,
+    Bool,
+    Path,
+    Any,
+    List(Box<SchemaType>),
+    Block(RavnSchema),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SchemaField {
+    key: String,
+    kind: SchemaType,
+    required: bool,
+    default: Option<RavnValue>,
+}
+
+/// Keys expected in a block.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RavnSchema {
+    fields: Vec<SchemaField>,
+    others: Option<Box<SchemaType>>,
+    open: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Problem found validating, with the path of the entry and the position (from 1) of its key.
+/// Line and column are 0 when the document was validated without its text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaDiagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}, column {}; ", self.line, self.column)?;
+        }
+        match self.severity {
+            Severity::Error => write!(f, "error")?,
+            Severity::Warning => write!(f, "warning")?,
+        }
+        if !self.path.is_empty() {
+            write!(f, " in '{}'", self.path)?;
+        }
+        write!(f, "; {}", self.message)
+    }
+}
+
+/// Result of a validation; the document with the defaults added and what was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaReport {
+    pub value: RavnValue,
+    pub diagnostics: Vec<SchemaDiagnostic>,
+}
+
+impl SchemaReport {
+    /// True if there are no errors, warnings are allowed.
+    pub fn is_valid(&self) -> bool {
+        !self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    /// Entries of the document without errors inside, in order; to use the right blocks of a document with some
+    /// wrong ones, as the services of huginn. After a syntax error there are none.
+    pub fn valid_entries(&self) -> Vec<(&str, &RavnValue)> {
+        let mut valid = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+        for (key, data) in self.value.entries().unwrap_or_default() {
+            let occurrence = seen.iter().filter(|k| **k == key.as_str()).count();
+            seen.push(key);
+            let label = match occurrence {
+                0 => key_label(key),
+                n => format!("{}#{n}", key_label(key)),
+            };
+            let wrong = self.diagnostics.iter().any(|d| {
+                d.severity == Severity::Error
+                    && (d.path.is_empty()
+                        || d.path.strip_prefix(&label).is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '['])))
+            });
+            if !wrong {
+                valid.push((key.as_str(), data));
+            }
+        }
+        valid
+    }
+}
+
+impl RavnSchema {
+    /// Schema without keys, other keys are errors unless "others" or "open" are used.
+    pub fn new() -> RavnSchema {
+        RavnSchema::default()
+    }
+
+    fn field(mut self, key: &str, kind: SchemaType, required: bool, default: Option<RavnValue>) -> RavnSchema {
+        self.fields.push(SchemaField {
+            key: key.to_string(),
+            kind,
+            required,
+            default,
+        });
+        self
+    }
+
+    pub fn required(self, key: &str, kind: SchemaType) -> RavnSchema {
+        self.field(key, kind, true, None)
+    }
+
+    pub fn optional(self, key: &str, kind: SchemaType) -> RavnSchema {
+        self.field(key, kind, false, None)
+    }
+
+    /// Optional key, the default is added to the validated document when it is missing.
+    pub fn with_default<V: Into<RavnValue>>(self, key: &str, kind: SchemaType, value: V) -> RavnSchema {
+        self.field(key, kind, false, Some(value.into()))
+    }
+
+    /// Type of the keys not declared, for blocks whose keys are names; services, users, etc.
+    pub fn others(mut self, kind: SchemaType) -> RavnSchema {
+        self.others = Some(Box::new(kind));
+        self
+    }
+
+    /// Accept keys not declared as they are.
+    pub fn open(mut self) -> RavnSchema {
+        self.open = true;
+        self
+    }
+
+    /// Read a schema written in ravnkey.
+    pub fn parse(input: &str) -> Result<RavnSchema, RavnError> {
+        let (document, positions) = parse_positions(input)?;
+        schema_from(&document, "", &positions)
+    }
+
+    /// Validate a document already parsed, the diagnostics have no position.
+    pub fn validate(&self, document: &RavnValue) -> SchemaReport {
+        let positions = Positions::default();
+        let mut checker = Checker {
+            positions: &positions,
+            lines: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        let value = checker.block(self, document, "");
+        SchemaReport {
+            value,
+            diagnostics: checker.diagnostics,
+        }
+    }
+
+    /// Parse and validate a document, the syntax errors are diagnostics too.
+    pub fn check(&self, input: &str) -> SchemaReport {
+        let (document, positions) = match parse_positions(input) {
+            Ok(d) => d,
+            Err(e) => {
+                return SchemaReport {
+                    value: RavnValue::Block(Vec::new()),
+                    diagnostics: vec![SchemaDiagnostic {
+                        severity: Severity::Error,
+                        path: String::new(),
+                        line: e.line,
+                        column: e.column,
+                        message: e.message,
+                    }],
+                }
+            }
+        };
+        let mut checker = Checker {
+            positions: &positions,
+            lines: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        let value = checker.block(self, &document, "");
+        SchemaReport {
+            value,
+            diagnostics: checker.diagnostics,
+        }
+    }
+}
+
+// Type from words like "list int" or "path required", the rest of the words are returned
+fn type_from<'a>(words: &'a [&'a str]) -> Result<(SchemaType, &'a [&'a str]), String> {
+    let (first, rest) = match words.split_first() {
+        Some(d) => d,
+        None => return Err("missing type".to_string()),
+    };
+    let kind = match *first {
+        "string" => SchemaType::String,
+        "int" => SchemaType::Int,
+        "bool" => SchemaType::Bool,
+        "path" => SchemaType::Path,
+        "any" => SchemaType::Any,
+        "list" => {
+            let (inner, rest) = type_from(rest)?;
+            return Ok((SchemaType::List(Box::new(inner)), rest));
+        }
+        other => return Err(format!("unknown type '{other}'")),
+    };
+    Ok((kind, rest))
+}
+
+fn schema_from(
+    value: &RavnValue,
+    path: &str,
+    positions: &Positions,
+) -> Result<RavnSchema, RavnError> {
+    let mut schema = RavnSchema::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for (key, data) in value.entries().unwrap_or_default() {
+        let occurrence = seen.iter().filter(|k| **k == key.as_str()).count();
+        seen.push(key);
+        let label = match occurrence {
+            0 => key_label(key),
+            n => format!("{}#{n}", key_label(key)),
+        };
+        let epath = join(path, &label);
+        let error = |message: String| {
+            let (line, column) = positions.keys.get(&epath).copied().unwrap_or((0, 0));
+            RavnError { line, column, message }
+        };
+
+        let (kind, required, default) = match data {
+            RavnValue::Block(_) => (SchemaType::Block(schema_from(data, &epath, positions)?), false, None),
+            RavnValue::List(_) => return Err(error(format!("'{key}' must be a type or a block"))),
+            RavnValue::Text(text) => {
+                let words: Vec<&str> = text.split_whitespace().collect();
+                let (kind, rest) = type_from(&words).map_err(|e| error(format!("{e} for '{key}'")))?;
+                match rest {
+                    [] | ["optional"] => (kind, false, None),
+                    ["required"] => (kind, true, None),
+                    ["default", value @ ..] if !value.is_empty() => (kind, false, Some(value.join(" "))),
+                    _ => return Err(error(format!("expected 'required' or 'default [text]' after the type of '{key}'"))),
+                }
+            }
+        };
+        schema = if key == "*" {
+            schema.others(kind)
+        } else {
+            schema.field(key, kind, required, default.map(RavnValue::from))
+        };
+    }
+    Ok(schema)
+}
+
+// Edit distance, to suggest the declared key when one is misspelled
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for j in 0..b.len() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == b[j] {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+struct Checker<'a> {
+    positions: &'a Positions,
+    // Positions of the keys read from "key = value" lines
+    lines: HashMap<String, (usize, usize)>,
+    diagnostics: Vec<SchemaDiagnostic>,
+}
+
+impl Checker<'_> {
+    // The position of the entry, or of the nearest parent for missing keys
+    fn report(&mut self, severity: Severity, path: &str, message: String) {
+        let mut search = path;
+        let (line, column) = loop {
+            if let Some(position) = self.positions.keys.get(search).or_else(|| self.lines.get(search)) {
+                break *position;
+            }
+            match search.rfind(['.', '[']) {
+                Some(i) => search = &search[..i],
+                None => break (0, 0),
+            }
+        };
+        self.diagnostics.push(SchemaDiagnostic {
+            severity,
+            path: path.to_string(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    fn block(&mut self, schema: &RavnSchema, value: &RavnValue, path: &str) -> RavnValue {
+        // Text of "key = value" lines, as read by "parse_config", is a block too
+        let lines: Vec<(String, RavnValue)>;
+        let entries = match value {
+            RavnValue::Block(d) => d.as_slice(),
+            RavnValue::Text(d) if d.is_empty() || d.contains('=') => {
+                // Positions in the text are moved to where the text starts in the document
+                let (tline, tcolumn) = self.positions.texts.get(path).copied().unwrap_or((0, 0));
+                let locate = |line: usize, column: usize| match (tline, line) {
+                    (0, _) => (0, 0),
+                    (_, 1) => (tline, tcolumn + column - 1),
+                    (_, _) => (tline + line - 1, column),
+                };
+                let config = match parse_config_entries(d) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        let (line, column) = locate(e.line, e.column);
+                        self.diagnostics.push(SchemaDiagnostic {
+                            severity: Severity::Error,
+                            path: path.to_string(),
+                            line,
+                            column,
+                            message: e.message,
+                        });
+                        return value.clone();
+                    }
+                };
+                // Repeated keys are a list, each item has the position of its line
+                for (n, entry) in config.iter().enumerate() {
+                    let epath = join(path, &key_label(&entry.key));
+                    let position = locate(entry.line, entry.column);
+                    if config.iter().filter(|c| c.key == entry.key).count() > 1 {
+                        let index = config[..n].iter().filter(|c| c.key == entry.key).count();
+                        self.lines.insert(format!("{epath}[{index}]"), position);
+                    }
+                    self.lines.entry(epath).or_insert(position);
+                }
+                lines = group_entries(&config).entries().unwrap_or_default().to_vec();
+                &lines
+            }
+            _ => {
+                self.report(Severity::Error, path, "expected a block of keys".to_string());
+                return value.clone();
+            }
+        };
+
+        let mut output = RavnValue::Block(Vec::new());
+        let mut seen: Vec<&str> = Vec::new();
+        for (key, data) in entries {
+            let occurrence = seen.iter().filter(|k| **k == key.as_str()).count();
+            seen.push(key);
+            let label = match occurrence {
+                0 => key_label(key),
+                n => format!("{}#{n}", key_label(key)),
+            };
+            let epath = join(path, &label);
+
+            match schema.fields.iter().find(|f| f.key == *key) {
+                Some(field) => {
+                    if occurrence == 1 && !matches!(field.kind, SchemaType::List(_) | SchemaType::Any) {
+                        self.report(Severity::Warning, &epath, format!("'{key}' is repeated"));
+                    }
+                    let data = self.value(&field.kind, data, &epath);
+                    output.push(key.as_str(), data);
+                }
+                None => match (&schema.others, schema.open) {
+                    (Some(kind), _) => {
+                        let data = self.value(kind, data, &epath);
+                        output.push(key.as_str(), data);
+                    }
+                    (None, true) => output.push(key.as_str(), data.clone()),
+                    (None, false) => {
+                        let suggestion = schema
+                            .fields
+                            .iter()
+                            .map(|f| (distance(key, &f.key), &f.key))
+                            .filter(|(d, k)| *d <= 2 && *d < k.chars().count())
+                            .min_by_key(|(d, _)| *d);
+                        let message = match suggestion {
+                            Some((_, k)) => format!("unknown key '{key}', did you mean '{k}'?"),
+                            None => format!("unknown key '{key}'"),
+                        };
+                        self.report(Severity::Error, &epath, message);
+                        output.push(key.as_str(), data.clone());
+                    }
+                },
+            }
+        }
+
+        for field in &schema.fields {
+            if seen.contains(&field.key.as_str()) {
+                continue;
+            }
+            match &field.default {
+                Some(default) => output.push(field.key.as_str(), default.clone()),
+                None if field.required => {
+                    let epath = join(path, &key_label(&field.key));
+                    self.report(Severity::Error, &epath, format!("missing required key '{}'", field.key));
+                }
+                None => {}
+            }
+        }
+        output
+    }
+
+    fn value(&mut self, kind: &SchemaType, value: &RavnValue, path: &str) -> RavnValue {
+        let text = match (kind, value) {
+            (SchemaType::Any, _) => return value.clone(),
+            (SchemaType::Block(schema), _) => return self.block(schema, value, path),
+            (SchemaType::List(inner), RavnValue::List(items)) => {
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| self.value(inner, v, &format!("{path}[{i}]")))
+                    .collect();
+                return RavnValue::List(items);
+            }
+            (SchemaType::List(_), RavnValue::Text(d)) if d.is_empty() => return RavnValue::List(Vec::new()),
+            (SchemaType::List(_), _) => {
+                self.report(Severity::Error, path, "expected a list, '{ [item] } { [item] }'".to_string());
+                return value.clone();
+            }
+            (_, RavnValue::Text(d)) => d,
+            (_, _) => {
+                self.report(Severity::Error, path, "expected text, not a block or list".to_string());
+                return value.clone();
+            }
+        };
+
+        match kind {
+            SchemaType::Int if text.parse::<i64>().is_err() => {
+                self.report(Severity::Error, path, format!("'{text}' is not an integer"));
+            }
+            SchemaType::Bool => match parse_bool(text) {
+                Some(b) => return RavnValue::from(b.to_string()),
+                None => self.report(
+                    Severity::Error,
+                    path,
+                    format!("'{text}' is not a boolean; true, false, yes, no, on, off, 1 or 0"),
+                ),
+            },
+            SchemaType::Path if text.is_empty() => {
+                self.report(Severity::Error, path, "empty path".to_string());
+            }
+            // Relative paths depend of the directory or PATH, only absolute ones are checked
+            SchemaType::Path if text.starts_with('/') && !Path::new(text).exists() => {
+                self.report(Severity::Warning, path, format!("'{text}' does not exist"));
+            }
+            _ => {}
+        }
+        value.clone()
+    }
+}
@@ -0,0 +1,852 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Dates and times.
+//!
+//! - Civil dates (proleptic Gregorian calendar) to days since 1970-01-01 and back, negative days are before 1970.
+//! - "DateTime"; a Unix timestamp as date and time with an UTC offset, formatted as RFC 3339 or with strftime-like formats.
+//! - "parse_timestamp"; RFC 3339 / ISO 8601 dates and times, or "@[seconds]".
+//! - "TimeZone"; TZif files (RFC 8536) from /usr/share/zoneinfo, with the POSIX TZ rule of their footer.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::ravnkey::RavnError;
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+pub fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of the date, the month and day start at 1.
+// Based on Howard Hinnant's "days_from_civil"; the year starts in March so the leap day is the last one
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date (year, month, day) of the days since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Day of the week, 0 is Sunday.
+pub fn weekday_from_days(days: i64) -> u32 {
+    // 1970-01-01 was Thursday
+    (days + 4).rem_euclid(7) as u32
+}
+
+/// Date and time at an offset (seconds east of UTC) with the name of the zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+    pub offset: i32,
+    pub zone: String,
+}
+
+impl DateTime {
+    /// The timestamp in UTC.
+    pub fn from_timestamp(seconds: i64, nanosecond: u32) -> DateTime {
+        DateTime::with_offset(seconds, nanosecond, 0, "UTC")
+    }
+
+    /// The timestamp at a fixed offset.
+    pub fn with_offset(seconds: i64, nanosecond: u32, offset: i32, zone: &str) -> DateTime {
+        let local = seconds + offset as i64;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let time = local.rem_euclid(86400) as u32;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            nanosecond,
+            offset,
+            zone: zone.to_string(),
+        }
+    }
+
+    /// The timestamp in the zone, with the offset and name used at that moment.
+    pub fn in_zone(seconds: i64, nanosecond: u32, zone: &TimeZone) -> DateTime {
+        let (offset, name) = zone.offset_at(seconds);
+        DateTime::with_offset(seconds, nanosecond, offset, &name)
+    }
+
+    /// Current time in UTC.
+    pub fn now() -> DateTime {
+        let (seconds, nanosecond) = now_timestamp();
+        DateTime::from_timestamp(seconds, nanosecond)
+    }
+
+    /// Current time in the zone.
+    pub fn now_in(zone: &TimeZone) -> DateTime {
+        let (seconds, nanosecond) = now_timestamp();
+        DateTime::in_zone(seconds, nanosecond, zone)
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC.
+    pub fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.offset as i64
+    }
+
+    /// Day of the week, 0 is Sunday.
+    pub fn weekday(&self) -> u32 {
+        weekday_from_days(days_from_civil(self.year, self.month, self.day))
+    }
+
+    /// Day of the year, from 1.
+    pub fn ordinal(&self) -> u32 {
+        (days_from_civil(self.year, self.month, self.day) - days_from_civil(self.year, 1, 1)) as u32 + 1
+    }
+
+    /// "2024-01-02T03:04:05Z" or "2024-01-02T03:04:05.5+01:00", the fraction only if there is one.
+    pub fn to_rfc3339(&self) -> String {
+        let mut output = self.format("%FT%T");
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            output.push('.');
+            output.push_str(fraction.trim_end_matches('0'));
+        }
+        if self.offset == 0 {
+            output.push('Z');
+        } else {
+            output.push_str(&self.format("%:z"));
+        }
+        output
+    }
+
+    fn offset_text(&self, colon: bool) -> String {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.unsigned_abs();
+        let separator = if colon { ":" } else { "" };
+        format!("{sign}{:02}{separator}{:02}", offset / 3600, offset / 60 % 60)
+    }
+
+    /// Format like strftime;
+    /// %Y %C %y year, %m %B %b %h month, %d %e day, %j day of the year, %a %A %u %w day of the week,
+    /// %H %I %M %S %N %p time, %z %:z %Z zone, %s timestamp, %F %T %D %R %c %x %X, %n %t and %%.
+    pub fn format(&self, format: &str) -> String {
+        let mut output = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue;
+            }
+            let hour12 = match self.hour % 12 {
+                0 => 12,
+                h => h,
+            };
+            let text = match chars.next() {
+                Some('Y') => format!("{:04}", self.year),
+                Some('C') => format!("{:02}", self.year.div_euclid(100)),
+                Some('y') => format!("{:02}", self.year.rem_euclid(100)),
+                Some('m') => format!("{:02}", self.month),
+                Some('B') => MONTHS[self.month as usize - 1].to_string(),
+                Some('b' | 'h') => MONTHS[self.month as usize - 1][..3].to_string(),
+                Some('d') => format!("{:02}", self.day),
+                Some('e') => format!("{:2}", self.day),
+                Some('j') => format!("{:03}", self.ordinal()),
+                Some('A') => WEEKDAYS[self.weekday() as usize].to_string(),
+                Some('a') => WEEKDAYS[self.weekday() as usize][..3].to_string(),
+                Some('u') => match self.weekday() {
+                    0 => "7".to_string(),
+                    d => d.to_string(),
+                },
+                Some('w') => self.weekday().to_string(),
+                Some('H') => format!("{:02}", self.hour),
+                Some('I') => format!("{hour12:02}"),
+                Some('M') => format!("{:02}", self.minute),
+                Some('S') => format!("{:02}", self.second),
+                Some('N') => format!("{:09}", self.nanosecond),
+                Some('p') => if self.hour < 12 { "AM" } else { "PM" }.to_string(),
+                Some('z') => self.offset_text(false),
+                Some(':') => match chars.next() {
+                    Some('z') => self.offset_text(true),
+                    Some(other) => format!("%:{other}"),
+                    None => "%:".to_string(),
+                },
+                Some('Z') if self.zone.is_empty() => self.offset_text(true),
+                Some('Z') => self.zone.clone(),
+                Some('s') => self.timestamp().to_string(),
+                Some('F') => self.format("%Y-%m-%d"),
+                Some('T') | Some('X') => self.format("%H:%M:%S"),
+                Some('D') | Some('x') => self.format("%m/%d/%y"),
+                Some('R') => self.format("%H:%M"),
+                Some('c') => self.format("%a %b %e %H:%M:%S %Y"),
+                Some('n') => "\n".to_string(),
+                Some('t') => "\t".to_string(),
+                Some('%') => "%".to_string(),
+                Some(other) => format!("%{other}"),
+                None => "%".to_string(),
+            };
+            output.push_str(&text);
+        }
+        output
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_rfc3339())
+    }
+}
+
+fn now_timestamp() -> (i64, u32) {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            // Before 1970
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    }
+}
+
+struct Scanner<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    text: &'a str,
+}
+
+impl Scanner<'_> {
+    fn error(&self, message: &str) -> RavnError {
+        RavnError {
+            line: 1,
+            column: self.pos + 1,
+            message: format!("{message} in '{}'", self.text),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn accept(&mut self, options: &[char]) -> Option<char> {
+        let c = self.peek().filter(|c| options.contains(c))?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    // Exactly "count" digits
+    fn digits(&mut self, count: usize, what: &str) -> Result<u32, RavnError> {
+        let mut value = 0;
+        for _ in 0..count {
+            match self.peek().and_then(|c| c.to_digit(10)) {
+                Some(d) => {
+                    value = value * 10 + d;
+                    self.pos += 1;
+                }
+                None => return Err(self.error(&format!("expected {count} digits for the {what}"))),
+            }
+        }
+        Ok(value)
+    }
+
+    fn range(&mut self, count: usize, what: &str, min: u32, max: u32) -> Result<u32, RavnError> {
+        let start = self.pos;
+        let value = self.digits(count, what)?;
+        if value < min || value > max {
+            self.pos = start;
+            return Err(self.error(&format!("the {what} must be from {min} to {max}")));
+        }
+        Ok(value)
+    }
+}
+
+/// Parse "YYYY-MM-DD", "YYYY-MM-DDTHH:MM[:SS[.fraction]]" with 'T' or a space and an optional zone
+/// ('Z', "+HH:MM", "+HHMM" or "+HH"), or "@[seconds]" since 1970. Without zone the time is UTC.
+pub fn parse_timestamp(text: &str) -> Result<DateTime, RavnError> {
+    let text = text.trim();
+    let mut scanner = Scanner {
+        chars: text.chars().collect(),
+        pos: 0,
+        text,
+    };
+
+    if let Some(seconds) = text.strip_prefix('@') {
+        let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+        let whole: i64 = whole.parse().map_err(|_e| scanner.error("expected the seconds after '@'"))?;
+        if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(scanner.error("expected up to 9 digits of fraction"));
+        }
+        let nanosecond: u32 = format!("{fraction:0<9}").parse().unwrap_or(0);
+        // "@-1.5" is 1.5 seconds before 1970; -2 seconds plus 0.5
+        if seconds.starts_with('-') && nanosecond > 0 {
+            return Ok(DateTime::from_timestamp(whole - 1, 1_000_000_000 - nanosecond));
+        }
+        return Ok(DateTime::from_timestamp(whole, nanosecond));
+    }
+
+    let year = scanner.digits(4, "year")? as i64;
+    if scanner.accept(&['-']).is_none() {
+        return Err(scanner.error("expected '-' after the year"));
+    }
+    let month = scanner.range(2, "month", 1, 12)?;
+    if scanner.accept(&['-']).is_none() {
+        return Err(scanner.error("expected '-' after the month"));
+    }
+    let day = scanner.range(2, "day", 1, days_in_month(year, month))?;
+
+    let (mut hour, mut minute, mut second, mut nanosecond) = (0, 0, 0, 0);
+    let mut offset = 0;
+    if scanner.accept(&['T', 't', ' ']).is_some() {
+        hour = scanner.range(2, "hour", 0, 23)?;
+        if scanner.accept(&[':']).is_none() {
+            return Err(scanner.error("expected ':' after the hour"));
+        }
+        minute = scanner.range(2, "minute", 0, 59)?;
+        if scanner.accept(&[':']).is_some() {
+            // 60 is a leap second
+            second = scanner.range(2, "second", 0, 60)?;
+            if scanner.accept(&['.', ',']).is_some() {
+                let mut digits = 0;
+                while let Some(d) = scanner.peek().and_then(|c| c.to_digit(10)) {
+                    if digits < 9 {
+                        nanosecond = nanosecond * 10 + d;
+                        digits += 1;
+                    }
+                    scanner.pos += 1;
+                }
+                if digits == 0 {
+                    return Err(scanner.error("expected digits after the decimal point"));
+                }
+                nanosecond *= 10u32.pow(9 - digits);
+            }
+        }
+        match scanner.accept(&['Z', 'z', '+', '-']) {
+            Some('+') | Some('-') => {
+                let sign = if scanner.chars[scanner.pos - 1] == '-' { -1 } else { 1 };
+                let hours = scanner.range(2, "offset hours", 0, 23)?;
+                let minutes = match scanner.peek() {
+                    Some(':') => {
+                        scanner.pos += 1;
+                        scanner.range(2, "offset minutes", 0, 59)?
+                    }
+                    Some(c) if c.is_ascii_digit() => scanner.range(2, "offset minutes", 0, 59)?,
+                    _ => 0,
+                };
+                offset = sign * (hours * 3600 + minutes * 60) as i32;
+            }
+            _ => {}
+        }
+    }
+    if scanner.pos < scanner.chars.len() {
+        return Err(scanner.error("unexpected text"));
+    }
+
+    let zone = if offset == 0 { "UTC".to_string() } else { String::new() };
+    Ok(DateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        nanosecond,
+        offset,
+        zone,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct LocalType {
+    offset: i32,
+    name: String,
+}
+
+// Day of a POSIX TZ rule
+#[derive(Clone, Debug, PartialEq)]
+enum RuleDay {
+    // Jn; 1 to 365, February 29 is never counted
+    Julian(i64),
+    // n; 0 to 365, February 29 is counted
+    Zero(i64),
+    // Mm.w.d; day d (0 Sunday) of the week w (5 is the last) of the month m
+    Month(u32, u32, u32),
+}
+
+// Day and seconds in local time of a change
+type RuleTime = (RuleDay, i64);
+
+#[derive(Clone, Debug, PartialEq)]
+struct PosixRule {
+    std: LocalType,
+    // Daylight saving type with its start and end
+    dst: Option<(LocalType, RuleTime, RuleTime)>,
+}
+
+impl RuleDay {
+    fn days(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, 1, 1);
+        match *self {
+            RuleDay::Julian(n) => first + n - 1 + if is_leap_year(year) && n >= 60 { 1 } else { 0 },
+            RuleDay::Zero(n) => first + n,
+            RuleDay::Month(month, week, weekday) => {
+                let start = days_from_civil(year, month, 1);
+                let mut day = (weekday as i64 - weekday_from_days(start) as i64).rem_euclid(7) + (week as i64 - 1) * 7;
+                while day >= days_in_month(year, month) as i64 {
+                    day -= 7;
+                }
+                start + day
+            }
+        }
+    }
+}
+
+impl PosixRule {
+    fn local_type(&self, timestamp: i64) -> &LocalType {
+        let (dst, start, end) = match &self.dst {
+            Some(d) => d,
+            None => return &self.std,
+        };
+        let (year, _, _) = civil_from_days((timestamp + self.std.offset as i64).div_euclid(86400));
+        // The start is in standard time and the end in daylight saving time
+        let begin = start.0.days(year) * 86400 + start.1 - self.std.offset as i64;
+        let finish = end.0.days(year) * 86400 + end.1 - dst.offset as i64;
+        let in_dst = if begin < finish {
+            timestamp >= begin && timestamp < finish
+        } else {
+            // Southern hemisphere, daylight saving time crosses the new year
+            timestamp < finish || timestamp >= begin
+        };
+        if in_dst {
+            dst
+        } else {
+            &self.std
+        }
+    }
+}
+
+// Parser of POSIX TZ strings; "CET-1CEST,M3.5.0,M10.5.0/3" or "<-03>3"
+struct RuleParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl RuleParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let mut name = String::new();
+        if self.peek() == Some('<') {
+            self.pos += 1;
+            while let Some(c) = self.peek() {
+                self.pos += 1;
+                if c == '>' {
+                    return Some(name);
+                }
+                name.push(c);
+            }
+            return None;
+        }
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+            name.push(c);
+            self.pos += 1;
+        }
+        if name.len() < 3 {
+            return None;
+        }
+        Some(name)
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn dot(&mut self) -> Option<()> {
+        if self.peek() != Some('.') {
+            return None;
+        }
+        self.pos += 1;
+        Some(())
+    }
+
+    // [+-]hh[:mm[:ss]] in seconds
+    fn time(&mut self) -> Option<i64> {
+        let sign = match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                -1
+            }
+            Some('+') => {
+                self.pos += 1;
+                1
+            }
+            _ => 1,
+        };
+        let mut seconds = self.number()? * 3600;
+        for unit in [60, 1] {
+            if self.peek() != Some(':') {
+                break;
+            }
+            self.pos += 1;
+            seconds += self.number()? * unit;
+        }
+        Some(sign * seconds)
+    }
+
+    fn day(&mut self) -> Option<RuleTime> {
+        let day = match self.peek()? {
+            'J' => {
+                self.pos += 1;
+                RuleDay::Julian(self.number()?)
+            }
+            'M' => {
+                self.pos += 1;
+                let month = self.number()? as u32;
+                self.dot()?;
+                let week = self.number()? as u32;
+                self.dot()?;
+                let weekday = self.number()? as u32;
+                if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                    return None;
+                }
+                RuleDay::Month(month, week, weekday)
+            }
+            _ => RuleDay::Zero(self.number()?),
+        };
+        let time = if self.peek() == Some('/') {
+            self.pos += 1;
+            self.time()?
+        } else {
+            7200
+        };
+        Some((day, time))
+    }
+
+    fn rule(&mut self) -> Option<PosixRule> {
+        let name = self.name()?;
+        // POSIX offsets are west of UTC
+        let offset = -self.time()? as i32;
+        let std = LocalType {
+            offset,
+            name,
+        };
+        if self.peek().is_none() {
+            return Some(PosixRule { std, dst: None });
+        }
+        let name = self.name()?;
+        let dst_offset = match self.peek() {
+            Some(',') | None => offset + 3600,
+            _ => -self.time()? as i32,
+        };
+        let dst = LocalType {
+            offset: dst_offset,
+            name,
+        };
+        // Without rules the United States ones are used, as POSIX suggests
+        let (start, end) = if self.peek() == Some(',') {
+            self.pos += 1;
+            let start = self.day()?;
+            if self.peek() != Some(',') {
+                return None;
+            }
+            self.pos += 1;
+            (start, self.day()?)
+        } else {
+            ((RuleDay::Month(3, 2, 0), 7200), (RuleDay::Month(11, 1, 0), 7200))
+        };
+        if self.peek().is_some() {
+            return None;
+        }
+        Some(PosixRule {
+            std,
+            dst: Some((dst, start, end)),
+        })
+    }
+}
+
+/// Time zone; the offsets from UTC along the time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeZone {
+    name: String,
+    // Moments (UTC) where the local time type changes, with the index of the new type
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalType>,
+    rule: Option<PosixRule>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Big endian reader over the TZif data
+struct TzifReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> TzifReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|e| *e <= self.data.len());
+        match end {
+            Some(end) => {
+                let bytes = &self.data[self.pos..end];
+                self.pos = end;
+                Ok(bytes)
+            }
+            None => Err(invalid("TZif data is truncated")),
+        }
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn time(&mut self, long: bool) -> io::Result<i64> {
+        if long {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(self.take(8)?);
+            Ok(i64::from_be_bytes(bytes))
+        } else {
+            Ok(self.u32()? as i32 as i64)
+        }
+    }
+
+    // Header, returns the version and the counts; isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt
+    fn header(&mut self) -> io::Result<(u8, [usize; 6])> {
+        if self.take(4)? != b"TZif" {
+            return Err(invalid("not a TZif file"));
+        }
+        let version = self.take(1)?[0];
+        self.take(15)?;
+        let mut counts = [0usize; 6];
+        for count in counts.iter_mut() {
+            *count = self.u32()? as usize;
+        }
+        Ok((version, counts))
+    }
+}
+
+impl TimeZone {
+    pub fn utc() -> TimeZone {
+        TimeZone {
+            name: "UTC".to_string(),
+            transitions: Vec::new(),
+            types: vec![LocalType {
+                offset: 0,
+                name: "UTC".to_string(),
+            }],
+            rule: None,
+        }
+    }
+
+    /// Name given when the zone was loaded; "Europe/Madrid", "UTC", etc.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse TZif data, version 1, 2 or 3.
+    pub fn from_tzif(name: &str, data: &[u8]) -> io::Result<TimeZone> {
+        let mut reader = TzifReader { data, pos: 0 };
+        let (version, mut counts) = reader.header()?;
+        let mut long = false;
+        if version >= b'2' {
+            // Skip the version 1 block, the 64 bits one follows
+            let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+            reader.take(timecnt * 5 + typecnt * 6 + charcnt + leapcnt * 8 + isstdcnt + isutcnt)?;
+            counts = reader.header()?.1;
+            long = true;
+        }
+        let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+        if typecnt == 0 {
+            return Err(invalid("TZif data without local time types"));
+        }
+
+        let mut times = Vec::with_capacity(timecnt);
+        for _ in 0..timecnt {
+            times.push(reader.time(long)?);
+        }
+        let indices = reader.take(timecnt)?.to_vec();
+        let mut raw_types = Vec::with_capacity(typecnt);
+        for _ in 0..typecnt {
+            let offset = reader.u32()? as i32;
+            let info = reader.take(2)?;
+            raw_types.push((offset, info[1] as usize));
+        }
+        let names = reader.take(charcnt)?;
+        reader.take(leapcnt * if long { 12 } else { 8 } + isstdcnt + isutcnt)?;
+
+        let mut types = Vec::with_capacity(typecnt);
+        for (offset, index) in raw_types {
+            let name = names.get(index..).ok_or_else(|| invalid("TZif abbreviation out of range"))?;
+            let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            types.push(LocalType {
+                offset,
+                name: String::from_utf8_lossy(&name[..end]).to_string(),
+            });
+        }
+        let mut transitions = Vec::with_capacity(timecnt);
+        for (time, index) in times.into_iter().zip(indices) {
+            if index as usize >= types.len() {
+                return Err(invalid("TZif transition to a local time type that does not exist"));
+            }
+            transitions.push((time, index as usize));
+        }
+
+        // Footer; the POSIX TZ rule for the times after the last transition
+        let mut rule = None;
+        if long {
+            let footer = &data[reader.pos..];
+            if let Some(text) = footer.strip_prefix(b"\n") {
+                let end = text.iter().position(|b| *b == b'\n').unwrap_or(text.len());
+                rule = parse_rule(&String::from_utf8_lossy(&text[..end]));
+            }
+        }
+
+        Ok(TimeZone {
+            name: name.to_string(),
+            transitions,
+            types,
+            rule,
+        })
+    }
+
+    /// Zone from a POSIX TZ string, "CET-1CEST,M3.5.0,M10.5.0/3".
+    pub fn from_posix(text: &str) -> Option<TimeZone> {
+        let rule = parse_rule(text)?;
+        Some(TimeZone {
+            name: text.to_string(),
+            transitions: Vec::new(),
+            types: vec![rule.std.clone()],
+            rule: Some(rule),
+        })
+    }
+
+    /// Load a zone by name from /usr/share/zoneinfo ("America/Argentina/Buenos_Aires") or by absolute path.
+    pub fn load(name: &str) -> io::Result<TimeZone> {
+        let path = if name.starts_with('/') {
+            Path::new(name).to_path_buf()
+        } else {
+            if name.split('/').any(|c| c == ".." || c.is_empty()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid zone name '{name}'")));
+            }
+            Path::new(ZONEINFO).join(name)
+        };
+        TimeZone::from_tzif(name, &fs::read(path)?)
+    }
+
+    /// The local zone; TZ ("Europe/Madrid", ":Europe/Madrid", a path or a POSIX TZ string),
+    /// else /etc/localtime and else UTC.
+    pub fn local() -> TimeZone {
+        if let Ok(tz) = std::env::var("TZ") {
+            let tz = tz.strip_prefix(':').unwrap_or(&tz);
+            if !tz.is_empty() {
+                if let Ok(zone) = TimeZone::load(tz) {
+                    return zone;
+                }
+                if let Some(zone) = TimeZone::from_posix(tz) {
+                    return zone;
+                }
+            }
+        }
+        match fs::read("/etc/localtime") {
+            Ok(data) => {
+                // The name is where the link points, after the zoneinfo directory
+                let name = fs::read_link("/etc/localtime")
+                    .ok()
+                    .and_then(|p| p.to_str().and_then(|p| p.split_once("zoneinfo/")).map(|(_, n)| n.to_string()))
+                    .unwrap_or_else(|| "localtime".to_string());
+                TimeZone::from_tzif(&name, &data).unwrap_or_else(|_e| TimeZone::utc())
+            }
+            Err(_e) => TimeZone::utc(),
+        }
+    }
+
+    /// Offset (seconds east of UTC) and abbreviation at the timestamp.
+    pub fn offset_at(&self, timestamp: i64) -> (i32, String) {
+        let count = self.transitions.partition_point(|(t, _)| *t <= timestamp);
+        let local = match (count, &self.rule) {
+            // After the last transition the rule is used
+            (n, Some(rule)) if n == self.transitions.len() => rule.local_type(timestamp),
+            (0, _) => &self.types[0],
+            (n, _) => &self.types[self.transitions[n - 1].1],
+        };
+        (local.offset, local.name.clone())
+    }
+}
+
+fn parse_rule(text: &str) -> Option<PosixRule> {
+    RuleParser {
+        chars: text.chars().collect(),
+        pos: 0,
+    }
+    .rule()
+}
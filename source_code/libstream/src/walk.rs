@@ -0,0 +1,263 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! Directory walker.
+//!
+//! The tree is walked depth first with a stack of open directories, so deep trees do not use the call stack
+//! and only one directory per level is open at the same time.
+//! Each directory is given before its content. Errors are given as items and the walk continues after them.
+//! When symbolic links are followed, a directory which is already one of the parents (same device and inode)
+//! is given as an error and not entered again.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, FileType, Metadata, ReadDir};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+/// A file or directory found by the walker.
+#[derive(Clone, Debug)]
+pub struct WalkEntry {
+    pub path: PathBuf,
+    /// 0 for the root, 1 for its content and so on.
+    pub depth: usize,
+    /// Metadata of the target when the symbolic links are followed, of the link itself if not.
+    pub metadata: Metadata,
+    /// True if the path is a symbolic link, followed or not.
+    pub symlink: bool,
+}
+
+impl WalkEntry {
+    pub fn file_type(&self) -> FileType {
+        self.metadata.file_type()
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+
+    /// Last component of the path, the whole path for the root when it has not one.
+    pub fn file_name(&self) -> &std::ffi::OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+}
+
+/// Error reading an entry or a directory, the walk continues with the next one.
+#[derive(Debug)]
+pub struct WalkError {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub error: io::Error,
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for WalkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+// Content of a directory still to give
+enum Entries {
+    Stream(ReadDir),
+    Sorted(vec::IntoIter<io::Result<PathBuf>>),
+}
+
+impl Iterator for Entries {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Entries::Stream(d) => d.next().map(|e| e.map(|e| e.path())),
+            Entries::Sorted(d) => d.next(),
+        }
+    }
+}
+
+struct Frame {
+    path: PathBuf,
+    entries: Entries,
+    // Device and inode of the directory
+    id: (u64, u64),
+    // Depth of the entries
+    depth: usize,
+}
+
+/// Iterator over a directory tree.
+///
+/// By default the depth has no limit, symbolic links are not followed (the root always is) and the entries
+/// are given in the order of the filesystem.
+pub struct Walker {
+    root: PathBuf,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    sorted: bool,
+    started: bool,
+    stack: Vec<Frame>,
+    // If the last entry given is a directory which was opened
+    entered: bool,
+    // Error opening a directory, given after the directory itself
+    pending: Option<WalkError>,
+}
+
+impl Walker {
+    pub fn new<P: AsRef<Path>>(root: P) -> Walker {
+        Walker {
+            root: root.as_ref().to_path_buf(),
+            max_depth: None,
+            follow_links: false,
+            sorted: false,
+            started: false,
+            stack: Vec::new(),
+            entered: false,
+            pending: None,
+        }
+    }
+
+    /// Deepest level given, 0 gives only the root.
+    pub fn max_depth(mut self, depth: Option<usize>) -> Walker {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Enter the directories pointed by symbolic links and give the metadata of the targets.
+    pub fn follow_links(mut self, follow: bool) -> Walker {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Give the entries of each directory sorted by name.
+    pub fn sorted(mut self, sorted: bool) -> Walker {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Do not enter the directory given last, as when it is ignored.
+    pub fn skip_dir(&mut self) {
+        if self.entered {
+            self.entered = false;
+            self.stack.pop();
+        }
+        // The error opening it does not matter now
+        self.pending = None;
+    }
+
+    // Reads the entry and opens it if it is a directory to enter
+    fn visit(&mut self, path: PathBuf, depth: usize) -> Result<WalkEntry, WalkError> {
+        let error = |path: &Path, error: io::Error| WalkError {
+            path: path.to_path_buf(),
+            depth,
+            error,
+        };
+        let link = fs::symlink_metadata(&path).map_err(|e| error(&path, e))?;
+        let symlink = link.file_type().is_symlink();
+        let metadata = if symlink && (self.follow_links || depth == 0) {
+            match fs::metadata(&path) {
+                Ok(d) => d,
+                // Broken link, given as it is
+                Err(e) if e.kind() == io::ErrorKind::NotFound => link,
+                Err(e) => return Err(error(&path, e)),
+            }
+        } else {
+            link
+        };
+
+        if metadata.is_dir() && self.max_depth.is_none_or(|d| depth < d) {
+            let id = (metadata.dev(), metadata.ino());
+            if self.stack.iter().any(|f| f.id == id) {
+                return Err(error(
+                    &path,
+                    io::Error::other("filesystem loop, directory already visited"),
+                ));
+            }
+            match self.open(&path) {
+                Ok(entries) => {
+                    self.stack.push(Frame {
+                        path: path.clone(),
+                        entries,
+                        id,
+                        depth: depth + 1,
+                    });
+                    self.entered = true;
+                }
+                Err(e) => self.pending = Some(error(&path, e)),
+            }
+        }
+
+        Ok(WalkEntry {
+            path,
+            depth,
+            metadata,
+            symlink,
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Entries> {
+        let entries = fs::read_dir(path)?;
+        if !self.sorted {
+            return Ok(Entries::Stream(entries));
+        }
+        let mut entries: Vec<io::Result<PathBuf>> = entries.map(|e| e.map(|e| e.path())).collect();
+        // Errors first, they have not a name to sort
+        entries.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.file_name().cmp(&b.file_name()),
+            (Err(_), Ok(_)) => Ordering::Less,
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => Ordering::Equal,
+        });
+        Ok(Entries::Sorted(entries.into_iter()))
+    }
+}
+
+impl Iterator for Walker {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entered = false;
+        if let Some(e) = self.pending.take() {
+            return Some(Err(e));
+        }
+        if !self.started {
+            self.started = true;
+            let root = self.root.clone();
+            return Some(self.visit(root, 0));
+        }
+        loop {
+            let frame = self.stack.last_mut()?;
+            let depth = frame.depth;
+            match frame.entries.next() {
+                Some(Ok(path)) => return Some(self.visit(path, depth)),
+                Some(Err(e)) => {
+                    return Some(Err(WalkError {
+                        path: frame.path.clone(),
+                        depth: depth - 1,
+                        error: e,
+                    }))
+                }
+                // Directory done, continue with its parent
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
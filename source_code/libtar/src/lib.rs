@@ -0,0 +1,877 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//! You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//!
+//! RavnOS's libtar
+//! This file contains the reader and writer of tar archives; ustar format with pax extended headers
+//! for names, links and sizes which do not fit in ustar fields.
+
+/// HashMap lib
+use std::collections::HashMap;
+/// Filesystem lib
+use std::fs::{self, File};
+/// Input Output lib
+use std::io::{self, Read, Write};
+/// Unix lib
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+/// Standard path
+use std::path::{Component, Path, PathBuf};
+/// Time lib
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Tar's block size, headers and data are always aligned to it.
+const BLOCK: usize = 512;
+
+/// Type of each entry inside the archive.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntryType {
+    File,
+    HardLink,
+    Symlink,
+    Directory,
+    /// Character and block devices, fifos, etc; they are listed but not extracted.
+    Other(u8),
+}
+
+impl EntryType {
+    fn from_flag(flag: u8) -> EntryType {
+        match flag {
+            b'0' | 0 | b'7' => EntryType::File,
+            b'1' => EntryType::HardLink,
+            b'2' => EntryType::Symlink,
+            b'5' => EntryType::Directory,
+            d => EntryType::Other(d),
+        }
+    }
+
+    fn flag(&self) -> u8 {
+        match self {
+            EntryType::File => b'0',
+            EntryType::HardLink => b'1',
+            EntryType::Symlink => b'2',
+            EntryType::Directory => b'5',
+            EntryType::Other(d) => *d,
+        }
+    }
+}
+
+/// Header of each entry.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: String,
+    pub kind: EntryType,
+    /// Permissions, including setuid, setgid and sticky bits.
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    /// Data size in bytes, zero for everything except files.
+    pub size: u64,
+    /// Modification time in seconds since UNIX epoch.
+    pub mtime: i64,
+    /// Target of symlinks and hard links.
+    pub link: String,
+    pub uname: String,
+    pub gname: String,
+}
+
+impl Entry {
+    /// Creates the header of "path" using its metadata (symlinks are not followed).
+    /// "name" is the path stored inside the archive.
+    pub fn from_path(path: &Path, name: &str) -> io::Result<Entry> {
+        let metadata = fs::symlink_metadata(path)?;
+        let ftype = metadata.file_type();
+        let (kind, size, link) = if ftype.is_symlink() {
+            (EntryType::Symlink, 0, fs::read_link(path)?.display().to_string())
+        } else if ftype.is_dir() {
+            (EntryType::Directory, 0, String::new())
+        } else if ftype.is_file() {
+            (EntryType::File, metadata.len(), String::new())
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{}; only files, directories and symlinks are supported", path.display()),
+            ));
+        };
+        Ok(Entry {
+            path: name.to_string(),
+            kind,
+            mode: metadata.mode() & 0o7777,
+            uid: metadata.uid() as u64,
+            gid: metadata.gid() as u64,
+            size,
+            mtime: metadata.mtime(),
+            link,
+            uname: String::new(),
+            gname: String::new(),
+        })
+    }
+}
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// Number of zero bytes needed after "size" bytes to complete the block
+fn padding(size: u64) -> usize {
+    let rest = (size % BLOCK as u64) as usize;
+    if rest == 0 {
+        0
+    } else {
+        BLOCK - rest
+    }
+}
+
+// Take a NUL terminated string from the header field
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|e| *e == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+// Numbers are stored in octal as ASCII, or in base-256 (GNU) when the first bit is set.
+fn field_num(field: &[u8]) -> io::Result<u64> {
+    if let Some(first) = field.first() {
+        if first & 0x80 != 0 {
+            let mut value: u64 = (first & 0x7f) as u64;
+            for byte in &field[1..] {
+                value = value
+                    .checked_mul(256)
+                    .ok_or_else(|| invalid("numeric field overflow"))?
+                    + *byte as u64;
+            }
+            return Ok(value);
+        }
+    }
+    let text = field_str(field);
+    let text = text.trim_matches(|e: char| e == ' ' || e == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_e| invalid(format!("invalid octal number; {text}")))
+}
+
+// Write "value" in octal filling the field, with a NUL at the end.
+// Returns false if it does not fit.
+fn put_num(field: &mut [u8], value: u64) -> bool {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    if digits.len() > field.len() - 1 {
+        return false;
+    }
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+    true
+}
+
+fn put_str(field: &mut [u8], value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if bytes.len() > field.len() {
+        return false;
+    }
+    field[..bytes.len()].copy_from_slice(bytes);
+    true
+}
+
+// The checksum is the sum of all header bytes taking the checksum field as spaces
+fn checksum(header: &[u8; BLOCK]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, e)| if (148..156).contains(&i) { b' ' as u64 } else { *e as u64 })
+        .sum()
+}
+
+// Split a long name in ustar's prefix (155 bytes) and name (100 bytes) at some '/'.
+fn split_name(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= 100 {
+        return Some(("", path));
+    }
+    for (i, c) in path.char_indices() {
+        if c == '/' && i <= 155 && path.len() - i - 1 <= 100 && i > 0 {
+            return Some((&path[..i], &path[i + 1..]));
+        }
+    }
+    None
+}
+
+// A pax record is "[length] [key]=[value]\n" where length counts the whole record, itself included.
+fn pax_record(key: &str, value: &str) -> String {
+    let base = key.len() + value.len() + 3;
+    let mut len = base + base.to_string().len();
+    // Adding the length digits can add one more digit
+    if len.to_string().len() + base != len {
+        len = base + len.to_string().len();
+    }
+    format!("{len} {key}={value}\n")
+}
+
+fn parse_pax(data: &[u8]) -> io::Result<HashMap<String, String>> {
+    let mut records = HashMap::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest
+            .iter()
+            .position(|e| *e == b' ')
+            .ok_or_else(|| invalid("invalid pax record"))?;
+        let len: usize = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|e| e.parse().ok())
+            .ok_or_else(|| invalid("invalid pax record length"))?;
+        if len <= space || len > rest.len() {
+            return Err(invalid("invalid pax record length"));
+        }
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        let record = String::from_utf8_lossy(record);
+        if let Some((k, v)) = record.split_once('=') {
+            records.insert(k.to_string(), v.to_string());
+        }
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+/// Tar archive writer.
+pub struct TarWriter<W: Write> {
+    inner: W,
+    // Hard links already written, by device and inode
+    links: HashMap<(u64, u64), String>,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(inner: W) -> TarWriter<W> {
+        TarWriter {
+            inner,
+            links: HashMap::new(),
+        }
+    }
+
+    // Write a raw header block
+    fn write_header(&mut self, entry: &Entry, path: &str, link: &str, flag: u8) -> io::Result<bool> {
+        let mut header = [0u8; BLOCK];
+        let mut fits = true;
+        match split_name(path) {
+            Some((prefix, name)) => {
+                put_str(&mut header[0..100], name);
+                put_str(&mut header[345..500], prefix);
+            }
+            None => {
+                fits = false;
+                // Truncated name for old readers, the pax header has the complete one
+                let mut end = 100;
+                while !path.is_char_boundary(end) {
+                    end -= 1;
+                }
+                put_str(&mut header[0..100], &path[..end]);
+            }
+        }
+        put_num(&mut header[100..108], entry.mode as u64);
+        fits &= put_num(&mut header[108..116], entry.uid);
+        fits &= put_num(&mut header[116..124], entry.gid);
+        fits &= put_num(&mut header[124..136], entry.size);
+        fits &= put_num(&mut header[136..148], entry.mtime.max(0) as u64);
+        header[156] = flag;
+        fits &= put_str(&mut header[157..257], link);
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        put_str(&mut header[265..297], &entry.uname);
+        put_str(&mut header[297..329], &entry.gname);
+        let sum = checksum(&header);
+        // Six octal digits, NUL and space
+        header[148..154].copy_from_slice(format!("{:06o}", sum).as_bytes());
+        header[154] = 0;
+        header[155] = b' ';
+        self.inner.write_all(&header)?;
+        Ok(fits)
+    }
+
+    /// Append an entry reading its content (for files) from "data".
+    pub fn append(&mut self, entry: &Entry, data: &mut dyn Read) -> io::Result<()> {
+        let mut path = entry.path.trim_start_matches('/').to_string();
+        if entry.kind == EntryType::Directory && !path.ends_with('/') {
+            path.push('/');
+        }
+
+        // If something does not fit in ustar fields, add a pax header before
+        let mut pax = String::new();
+        if split_name(&path).is_none() {
+            pax += &pax_record("path", &path);
+        }
+        if entry.link.len() > 100 {
+            pax += &pax_record("linkpath", &entry.link);
+        }
+        if entry.size > 0o77777777777 {
+            pax += &pax_record("size", &entry.size.to_string());
+        }
+        if entry.uid > 0o7777777 {
+            pax += &pax_record("uid", &entry.uid.to_string());
+        }
+        if entry.gid > 0o7777777 {
+            pax += &pax_record("gid", &entry.gid.to_string());
+        }
+        if entry.mtime < 0 || entry.mtime as u64 > 0o77777777777 {
+            pax += &pax_record("mtime", &entry.mtime.to_string());
+        }
+        let mut ustar = entry.clone();
+        if !pax.is_empty() {
+            let pax_entry = Entry {
+                path: String::new(),
+                kind: EntryType::Other(b'x'),
+                mode: 0o644,
+                uid: 0,
+                gid: 0,
+                size: pax.len() as u64,
+                mtime: entry.mtime.max(0),
+                link: String::new(),
+                uname: String::new(),
+                gname: String::new(),
+            };
+            let mut pax_name = format!("PaxHeaders/{}", path.trim_end_matches('/'));
+            let mut end = pax_name.len().min(100);
+            while !pax_name.is_char_boundary(end) {
+                end -= 1;
+            }
+            pax_name.truncate(end);
+            self.write_header(&pax_entry, &pax_name, "", b'x')?;
+            self.inner.write_all(pax.as_bytes())?;
+            self.inner.write_all(&vec![0u8; padding(pax.len() as u64)])?;
+            // The pax header has the real values, the ustar one keeps what fits
+            ustar.size = if entry.size > 0o77777777777 { 0 } else { entry.size };
+            ustar.uid = entry.uid.min(0o7777777);
+            ustar.gid = entry.gid.min(0o7777777);
+            ustar.mtime = entry.mtime.clamp(0, 0o77777777777);
+        }
+        let link = if entry.link.len() > 100 {
+            ""
+        } else {
+            entry.link.as_str()
+        };
+        self.write_header(&ustar, &path, link, entry.kind.flag())?;
+
+        if entry.kind == EntryType::File && entry.size > 0 {
+            let copied = io::copy(&mut data.take(entry.size), &mut self.inner)?;
+            if copied != entry.size {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{}; file changed its size while reading", entry.path),
+                ));
+            }
+            self.inner.write_all(&vec![0u8; padding(entry.size)])?;
+        }
+        Ok(())
+    }
+
+    /// Append a file, directory or symlink from the filesystem as "name".
+    /// Directories are appended with all their content.
+    pub fn append_path(&mut self, path: &Path, name: &str) -> io::Result<()> {
+        let mut entry = Entry::from_path(path, name)?;
+        let metadata = fs::symlink_metadata(path)?;
+
+        if entry.kind == EntryType::File && metadata.nlink() > 1 {
+            let key = (metadata.dev(), metadata.ino());
+            if let Some(target) = self.links.get(&key) {
+                entry.kind = EntryType::HardLink;
+                entry.link = target.clone();
+                entry.size = 0;
+                return self.append(&entry, &mut io::empty());
+            }
+            self.links.insert(key, entry.path.trim_start_matches('/').to_string());
+        }
+
+        match entry.kind {
+            EntryType::File => {
+                let mut file = File::open(path)?;
+                self.append(&entry, &mut file)
+            }
+            EntryType::Directory => {
+                self.append(&entry, &mut io::empty())?;
+                let mut children: Vec<PathBuf> = fs::read_dir(path)?
+                    .map(|res| res.map(|e| e.path()))
+                    .collect::<Result<Vec<_>, io::Error>>()?;
+                // Same order in each run
+                children.sort();
+                for child in children {
+                    let child_name = match child.file_name() {
+                        Some(d) => format!("{}/{}", name.trim_end_matches('/'), d.to_string_lossy()),
+                        None => continue,
+                    };
+                    self.append_path(&child, &child_name)?;
+                }
+                Ok(())
+            }
+            _ => self.append(&entry, &mut io::empty()),
+        }
+    }
+
+    /// Write the end of archive (two zero blocks) and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[0u8; BLOCK * 2])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Tar archive reader.
+/// Each "next_entry" call returns the next header, its data can be read with "read_data" before
+/// the next call, if not is skipped.
+pub struct TarReader<R: Read> {
+    inner: R,
+    // Data bytes not read yet of the current entry, and the padding after them
+    remaining: u64,
+    pad: usize,
+    // Global pax records ('g' headers) apply to each entry
+    global: HashMap<String, String>,
+}
+
+impl<R: Read> TarReader<R> {
+    pub fn new(inner: R) -> TarReader<R> {
+        TarReader {
+            inner,
+            remaining: 0,
+            pad: 0,
+            global: HashMap::new(),
+        }
+    }
+
+    fn skip(&mut self, mut count: u64) -> io::Result<()> {
+        let mut buffer = [0u8; BLOCK * 8];
+        while count > 0 {
+            let chunk = count.min(buffer.len() as u64) as usize;
+            self.inner.read_exact(&mut buffer[..chunk])?;
+            count -= chunk as u64;
+        }
+        Ok(())
+    }
+
+    // Read the data of the current entry completely, used for pax and GNU long names
+    fn read_all(&mut self, size: u64) -> io::Result<Vec<u8>> {
+        if size > 1024 * 1024 {
+            return Err(invalid("extended header too big"));
+        }
+        let mut data = vec![0u8; size as usize];
+        self.inner.read_exact(&mut data)?;
+        self.skip(padding(size) as u64)?;
+        Ok(data)
+    }
+
+    /// Returns the next entry or None at the end of archive.
+    pub fn next_entry(&mut self) -> io::Result<Option<Entry>> {
+        // Skip the data that was not read
+        let rest = self.remaining + self.pad as u64;
+        self.skip(rest)?;
+        self.remaining = 0;
+        self.pad = 0;
+
+        let mut pax: HashMap<String, String> = HashMap::new();
+        let mut long_name: Option<String> = None;
+        let mut long_link: Option<String> = None;
+
+        loop {
+            let mut header = [0u8; BLOCK];
+            match self.inner.read_exact(&mut header) {
+                Ok(()) => (),
+                // Some archives do not have the end blocks
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if header.iter().all(|e| *e == 0) {
+                return Ok(None);
+            }
+            if field_num(&header[148..156])? != checksum(&header) {
+                return Err(invalid("invalid header checksum"));
+            }
+
+            let size = field_num(&header[124..136])?;
+            match header[156] {
+                b'x' => {
+                    let data = self.read_all(size)?;
+                    pax.extend(parse_pax(&data)?);
+                    continue;
+                }
+                b'g' => {
+                    let data = self.read_all(size)?;
+                    self.global.extend(parse_pax(&data)?);
+                    continue;
+                }
+                // GNU long names
+                b'L' => {
+                    let data = self.read_all(size)?;
+                    long_name = Some(field_str(&data));
+                    continue;
+                }
+                b'K' => {
+                    let data = self.read_all(size)?;
+                    long_link = Some(field_str(&data));
+                    continue;
+                }
+                _ => (),
+            }
+
+            let mut path = field_str(&header[0..100]);
+            if &header[257..262] == b"ustar" {
+                let prefix = field_str(&header[345..500]);
+                if !prefix.is_empty() {
+                    path = format!("{prefix}/{path}");
+                }
+            }
+            let mut entry = Entry {
+                path,
+                kind: EntryType::from_flag(header[156]),
+                mode: field_num(&header[100..108])? as u32,
+                uid: field_num(&header[108..116])?,
+                gid: field_num(&header[116..124])?,
+                size,
+                mtime: field_num(&header[136..148])? as i64,
+                link: field_str(&header[157..257]),
+                uname: field_str(&header[265..297]),
+                gname: field_str(&header[297..329]),
+            };
+            // Old archives mark directories only with the '/' at the end
+            if entry.kind == EntryType::File && entry.path.ends_with('/') {
+                entry.kind = EntryType::Directory;
+            }
+            if let Some(d) = long_name {
+                entry.path = d;
+            }
+            if let Some(d) = long_link {
+                entry.link = d;
+            }
+
+            for records in [&self.global, &pax] {
+                for (k, v) in records {
+                    match k.as_str() {
+                        "path" => entry.path = v.clone(),
+                        "linkpath" => entry.link = v.clone(),
+                        "uname" => entry.uname = v.clone(),
+                        "gname" => entry.gname = v.clone(),
+                        "size" => entry.size = v.parse().map_err(|_e| invalid("invalid pax size"))?,
+                        "uid" => entry.uid = v.parse().map_err(|_e| invalid("invalid pax uid"))?,
+                        "gid" => entry.gid = v.parse().map_err(|_e| invalid("invalid pax gid"))?,
+                        // Can have decimals, we only keep seconds
+                        "mtime" => {
+                            entry.mtime = v
+                                .split('.')
+                                .next()
+                                .and_then(|e| e.parse().ok())
+                                .ok_or_else(|| invalid("invalid pax mtime"))?
+                        }
+                        _ => (),
+                    }
+                }
+            }
+
+            // Only files have data, but the size is respected for any type to keep the alignment
+            self.remaining = match entry.kind {
+                EntryType::HardLink | EntryType::Symlink | EntryType::Directory => 0,
+                _ => entry.size,
+            };
+            self.pad = padding(self.remaining);
+            return Ok(Some(entry));
+        }
+    }
+
+    /// Copy the data of the current entry to "output".
+    pub fn read_data(&mut self, output: &mut dyn Write) -> io::Result<u64> {
+        let copied = io::copy(&mut (&mut self.inner).take(self.remaining), output)?;
+        if copied != self.remaining {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "archive truncated"));
+        }
+        self.remaining = 0;
+        Ok(copied)
+    }
+
+    /// Extract all entries inside "destination", returning the paths extracted.
+    /// Entries with absolute paths are extracted relative to "destination", while entries with ".."
+    /// or that would be written through a symlink are refused.
+    pub fn unpack(&mut self, destination: &Path) -> io::Result<Vec<String>> {
+        let mut extracted: Vec<String> = Vec::new();
+        // Directories' modes and times are set at the end; adding files change the times, and a read only
+        // directory ("0555") would not allow to write its content
+        let mut directories: Vec<(PathBuf, u32, i64)> = Vec::new();
+
+        fs::create_dir_all(destination)?;
+        while let Some(entry) = self.next_entry()? {
+            let target = match safe_path(destination, &entry.path)? {
+                Some(d) => d,
+                // The root itself, like "./"
+                None => continue,
+            };
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            match entry.kind {
+                EntryType::Directory => {
+                    // A symlink extracted before with the same name would take the permissions out of
+                    // "destination", it is replaced by a real directory
+                    match fs::symlink_metadata(&target) {
+                        Ok(d) if d.is_dir() => (),
+                        Ok(d) if d.file_type().is_symlink() => {
+                            fs::remove_file(&target)?;
+                            fs::create_dir(&target)?;
+                        }
+                        _ => fs::create_dir(&target)?,
+                    }
+                    directories.push((target.clone(), entry.mode, entry.mtime));
+                }
+                EntryType::File => {
+                    remove_existing(&target)?;
+                    let mut file = File::create(&target)?;
+                    self.read_data(&mut file)?;
+                    file.set_permissions(fs::Permissions::from_mode(entry.mode))?;
+                    file.set_modified(mtime_to_system(entry.mtime))?;
+                }
+                EntryType::Symlink => {
+                    remove_existing(&target)?;
+                    symlink(&entry.link, &target)?;
+                }
+                EntryType::HardLink => {
+                    let source = match safe_path(destination, &entry.link)? {
+                        Some(d) => d,
+                        None => return Err(invalid(format!("{}; invalid hard link target", entry.path))),
+                    };
+                    remove_existing(&target)?;
+                    fs::hard_link(source, &target)?;
+                }
+                EntryType::Other(d) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        format!("{}; entry type '{}' is not supported", entry.path, d as char),
+                    ));
+                }
+            }
+            extracted.push(entry.path);
+        }
+
+        // The deepest first, so a parent without write permission does not stop its subdirectories. The sort is
+        // stable, a directory repeated in the archive keeps the mode of its last entry
+        directories.sort_by_key(|e| std::cmp::Reverse(e.0.components().count()));
+        for (dir, mode, mtime) in &directories {
+            // Only real directories, never through a symlink
+            match fs::symlink_metadata(dir) {
+                Ok(d) if d.is_dir() => (),
+                _ => continue,
+            }
+            fs::set_permissions(dir, fs::Permissions::from_mode(*mode))?;
+            if let Ok(d) = File::open(dir) {
+                let _ = d.set_modified(mtime_to_system(*mtime));
+            }
+        }
+        Ok(extracted)
+    }
+}
+
+fn mtime_to_system(mtime: i64) -> std::time::SystemTime {
+    if mtime >= 0 {
+        UNIX_EPOCH + Duration::from_secs(mtime as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(mtime.unsigned_abs())
+    }
+}
+
+// Delete a file or symlink to be replaced, directories are kept
+fn remove_existing(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(d) if d.is_dir() => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{}; is a directory", path.display()),
+        )),
+        Ok(_d) => fs::remove_file(path),
+        Err(_e) => Ok(()),
+    }
+}
+
+/// Join "name" to "destination" refusing paths that go out of it.
+/// Returns None if "name" is the destination itself.
+pub fn safe_path(destination: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let mut target = destination.to_path_buf();
+    let mut depth = 0;
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(d) => {
+                // Writing through a symlink extracted before can go anywhere
+                if let Ok(d) = fs::symlink_metadata(&target) {
+                    if depth > 0 && d.file_type().is_symlink() {
+                        return Err(invalid(format!("{name}; path goes through a symlink")));
+                    }
+                }
+                target.push(d);
+                depth += 1;
+            }
+            Component::ParentDir => {
+                return Err(invalid(format!("{name}; path contains '..'")));
+            }
+            // Absolute paths are taken as relative
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => (),
+        }
+    }
+    if depth == 0 {
+        return Ok(None);
+    }
+    Ok(Some(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, kind: EntryType, mode: u32, data: &[u8], link: &str) -> Entry {
+        Entry {
+            path: path.to_string(),
+            kind,
+            mode,
+            uid: 0,
+            gid: 0,
+            size: data.len() as u64,
+            mtime: 1_700_000_000,
+            link: link.to_string(),
+            uname: String::new(),
+            gname: String::new(),
+        }
+    }
+
+    fn archive(entries: &[(Entry, &[u8])]) -> Vec<u8> {
+        let mut writer = TarWriter::new(Vec::new());
+        for (entry, data) in entries {
+            writer.append(entry, &mut &data[..]).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    // Empty directory only for this test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("libtar-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::symlink_metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let long = format!("{}/file.txt", "d".repeat(150));
+        let data = archive(&[
+            (entry("dir", EntryType::Directory, 0o755, b"", ""), b""),
+            (entry("dir/a.txt", EntryType::File, 0o640, b"hello", ""), b"hello"),
+            (entry("dir/link", EntryType::Symlink, 0o777, b"", "a.txt"), b""),
+            (entry(&long, EntryType::File, 0o600, b"x", ""), b"x"),
+        ]);
+        assert_eq!(data.len() % BLOCK, 0);
+
+        let mut reader = TarReader::new(&data[..]);
+        let first = reader.next_entry().unwrap().unwrap();
+        assert_eq!((first.path.as_str(), first.kind, first.mode), ("dir/", EntryType::Directory, 0o755));
+        let second = reader.next_entry().unwrap().unwrap();
+        assert_eq!((second.path.as_str(), second.size, second.mtime), ("dir/a.txt", 5, 1_700_000_000));
+        let mut content = Vec::new();
+        reader.read_data(&mut content).unwrap();
+        assert_eq!(content, b"hello");
+        let third = reader.next_entry().unwrap().unwrap();
+        assert_eq!((third.kind, third.link.as_str()), (EntryType::Symlink, "a.txt"));
+        // Its data is skipped without reading it
+        assert_eq!(reader.next_entry().unwrap().unwrap().path, long);
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_archive() {
+        let data = archive(&[(entry("a.txt", EntryType::File, 0o644, b"hello", ""), b"hello")]);
+        let mut reader = TarReader::new(&data[..BLOCK + 2]);
+        reader.next_entry().unwrap().unwrap();
+        let error = reader.read_data(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn unpack_files() {
+        let root = scratch("unpack");
+        let data = archive(&[
+            (entry("/abs/a.txt", EntryType::File, 0o600, b"abc", ""), b"abc"),
+            (entry("abs/link", EntryType::Symlink, 0o777, b"", "a.txt"), b""),
+            (entry("abs/hard", EntryType::HardLink, 0o600, b"", "abs/a.txt"), b""),
+        ]);
+        let extracted = TarReader::new(&data[..]).unpack(&root).unwrap();
+        assert_eq!(extracted, ["abs/a.txt", "abs/link", "abs/hard"]);
+        assert_eq!(fs::read(root.join("abs/a.txt")).unwrap(), b"abc");
+        assert_eq!(mode(&root.join("abs/a.txt")), 0o600);
+        assert_eq!(fs::read_link(root.join("abs/link")).unwrap(), Path::new("a.txt"));
+        assert_eq!(fs::read(root.join("abs/hard")).unwrap(), b"abc");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn unpack_refuses_parent_dir() {
+        let root = scratch("parent");
+        let data = archive(&[(entry("a/../../evil", EntryType::File, 0o644, b"x", ""), b"x")]);
+        let error = TarReader::new(&data[..]).unpack(&root.join("out")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!root.join("evil").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn unpack_refuses_writing_through_symlink() {
+        let root = scratch("through");
+        fs::create_dir(root.join("outside")).unwrap();
+        let outside = root.join("outside").display().to_string();
+        let data = archive(&[
+            (entry("a", EntryType::Symlink, 0o777, b"", &outside), b""),
+            (entry("a/evil", EntryType::File, 0o644, b"x", ""), b"x"),
+        ]);
+        let error = TarReader::new(&data[..]).unpack(&root.join("out")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!root.join("outside/evil").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // A symlink to a directory out of the destination, then a directory entry with its name
+    #[test]
+    fn unpack_directory_over_symlink() {
+        let root = scratch("dirlink");
+        fs::create_dir(root.join("outside")).unwrap();
+        fs::set_permissions(root.join("outside"), fs::Permissions::from_mode(0o700)).unwrap();
+        let outside = root.join("outside").display().to_string();
+        let data = archive(&[
+            (entry("a", EntryType::Symlink, 0o777, b"", &outside), b""),
+            (entry("a/", EntryType::Directory, 0o777, b"", ""), b""),
+        ]);
+        TarReader::new(&data[..]).unpack(&root.join("out")).unwrap();
+        assert_eq!(mode(&root.join("outside")), 0o700);
+        let metadata = fs::symlink_metadata(root.join("out/a")).unwrap();
+        assert!(metadata.is_dir());
+        assert_eq!(metadata.mode() & 0o7777, 0o777);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Read only directories get their mode after their content is written
+    #[test]
+    fn unpack_read_only_directories() {
+        let root = scratch("readonly");
+        let data = archive(&[
+            (entry("ro/", EntryType::Directory, 0o555, b"", ""), b""),
+            (entry("ro/sub/", EntryType::Directory, 0o500, b"", ""), b""),
+            (entry("ro/sub/f.txt", EntryType::File, 0o444, b"hi", ""), b"hi"),
+        ]);
+        let out = root.join("out");
+        TarReader::new(&data[..]).unpack(&out).unwrap();
+        assert_eq!(fs::read(out.join("ro/sub/f.txt")).unwrap(), b"hi");
+        assert_eq!(mode(&out.join("ro")), 0o555);
+        assert_eq!(mode(&out.join("ro/sub")), 0o500);
+        for dir in ["ro", "ro/sub"] {
+            fs::set_permissions(out.join(dir), fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
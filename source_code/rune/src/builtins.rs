@@ -30,28 +30,40 @@ use std::io::BufReader;
 use std::io::{self, Read, Write};
 
 // thread lib
+use std::sync::{mpsc, Mutex};
 use std::thread;
 
 // Unix lib
-use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+use std::os::unix::fs::{symlink, FileTypeExt, MetadataExt, PermissionsExt};
 
 // Process lib
 use std::process::Command;
 
-// Time lib
-use std::time::SystemTime;
-
 // HashMap lib
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // RavnOS libraries
+extern crate libcompress;
 extern crate libconfarg;
 extern crate libfile;
+extern crate libregex;
 extern crate libstream;
-
-use libconfarg::RavnArguments;
-use libfile::{decode_base64, which, RavnFile, RavnSizeFile};
-use libstream::{file_filter, getprocs, search_replace_string, Epoch, Stream};
+extern crate libtar;
+
+use libcompress::{GzDecoder, GzEncoder, DEFAULT_LEVEL};
+use libconfarg::{ArgError, ArgSpec, ValueType};
+use libfile::{
+    decode_base64, dynamic_tag_name, elf_kind_name, elf_machine_name, fs_stat, hash_file, open_decompressed,
+    section_kind_name, segment_kind_name, sniff_file, symbol_bind_name, symbol_kind_name, which, which_all, CommandCache, Elf, HashAlgorithm,
+    RavnFile, RavnSizeFile, Symbol,
+};
+use libstream::{
+    file_filter, getprocs, json_to_ravnkey, parse_json, parse_ravnkey, ravnkey_to_json,
+    parse_timestamp, search_replace_string, to_json_pretty, to_ravnkey, DateTime, Epoch, JsonValue, RavnValue,
+    Stream, TimeZone,
+};
+use libregex::Regex;
+use libtar::{EntryType, TarReader, TarWriter};
 
 // Because this file is not a binary or lib, is just another module, to import
 // under score another module we must use "crate"
@@ -60,7 +72,7 @@ use crate::io_mods::get_user_home;
 // Here we use a const and not let because is a global variable
 // As we know the size of each word we can use "&str" and then we specify the number
 // of elements. This is because a const must have know size at compiling time.
-const LBUILTINS: [&str; 39] = [
+const LBUILTINS: [&str; 47] = [
     "base64",
     "basename",
     "cd",
@@ -69,14 +81,20 @@ const LBUILTINS: [&str; 39] = [
     "cp",
     "date",
     "decodebase64",
+    "df",
     "disable_history",
     "du",
     "echoraw",
+    "elf",
     "enable_history",
     "env",
     "exit",
     "expand",
     "false",
+    "file",
+    "gunzip",
+    "gzip",
+    "hash",
     "history",
     "head",
     "help",
@@ -98,6 +116,8 @@ const LBUILTINS: [&str; 39] = [
     "show",
     "sleep",
     "tail",
+    "tar",
+    "top",
     "which",
     "$?",
 ];
@@ -111,17 +131,23 @@ _cd [PATH]: If path do not exist, goes to user home directory.
 _clear: Clean the screen.
 _count [file]: Show the file's number lines and words
 _cp [source] [destination]: copy file or directory from [source] to [destination].
-_date: display the current time and date in UTC-0 (which is the same that GTM-0).
+_date [options] [+format]: display the current time and date, or other date, in the local zone, UTC or other zone.
 _decodebase64 [input] [file]: decocde input from base64 to file.
+_df [path_1] [path_n]: show size, used and available space of filesystems containing the paths, or of all mounted filesystems.
 _disable_history: disable save commands to history without truncate the file.
-_du [path]: show disk usage ('du') in [path], read recusively.
+_du [options] [path_1] [path_n]: show disk usage ('du') in paths, read recusively. Options; '-d [number]' max depth, '-b' apparent size instead allocated, '-x' stay in one filesystem, '--exclude [glob]' skip matches, '-n [number]' only the biggest.
 _enable_history: enable save commands to history.
 _echoraw: show string into stdout without interpreting special characters.
+_elf [options] [file_1] [file_n]: show the ELF files; class, machine, interpreter, needed libraries and build id. '-l' segments, '-S' sections, '-s' symbols, '--dyn-syms' dynamic symbols, '-d' dynamic section, '-n' notes, '-a' all and '--static' checks that the files are fully static.
 _env: show environment variables.
 _exit: exit the shell properly.
 _expand: convert tabs to spaces in file (with new file; [FILE]-edited), with '-t X' you can specify the spaces number, first the options (if exists) and then the file.
 _false [option] : returns a false value, '-n' for rune native or '-u' for 1 (false in Unix and GNU).
-_head -n [number] [file]: show [number] first lines for file.
+_file [options] [file_1] [file_n]: show the type of the files by their content; text encoding, ELF, gzip, tar, PNG, device tree or kernel image. '--mime' shows only the MIME type.
+_gunzip [file_1] [file_n]: decompress gzip files, '[file].gz' is decompressed into '[file]' and the compressed one is kept.
+_gzip [option] [file_1] [file_n]: compress files with gzip into '[file].gz' keeping the original, '-1' (fastest) to '-9' (smallest) sets the level.
+_hash [options] [file_1] [file_n]: show the files' digest, '-a [sha256/sha512/blake2b/crc32]' sets the algorithm (sha256 by default) and '--check [manifest]' verifies the digests saved in the manifest.
+_head [-n number] [file]: show [number] first lines for file, 10 by default.
 _history: show the history commands with date and time.
 _home: returns the current user's home directory.
 _id [options]: show current user, '-n' for name and '-u' for UUID.
@@ -141,7 +167,9 @@ _seq [first]:[last]:[increment] : start a secuence from [first] to [last] using
 _show [options] [file_1] [file_n]: show file's content, file's content in hexadecimal, system information or difference.
 _sleep [seconds]:[nanoseconds] : waits X seconds with Y nanoseconds.
 _tail [number] [file] : show the last [number] lines of [file].
-_which [binary]: show where is located the binary based in PATH environment variable.
+_tar [option] [archive] [paths]: '-c [archive] [path_1] [path_n]' create (with '-z' compressed with gzip), '-t [archive]' list (with '-v' verbose) and '-x [archive] [destination]' extract the tar archive, compressed archives are detected when listing or extracting.
+_top [options]: live view of CPU, memory, load, uptime and processes. '-n [number]' processes shown, '-d [seconds]' refresh delay, '-s [cpu/mem/pid/name]' sort order.
+_which [options] [binary]: show where is located the binary based in PATH environment variable, '-a' shows every match. The shell saves the commands it runs; '-l' lists them and '-r' forgets them.
 _$?: print the latest command exit return, not include builtins";
 
 const RUNE_VERSION: &str = "v0.46.26";
@@ -366,19 +394,21 @@ fn info() -> String {
 
 fn base64(input: &String) -> Option<String> {
     let input: Vec<String> = input.split(' ').map(|e| e.to_string()).collect();
-    let mut strreturn: String = String::new();
+    let mut document = RavnValue::Block(Vec::new());
     for names in &input {
         let file = match fs::File::open(&names) {
             Ok(d) => d,
             Err(_e) => return None,
         };
         if input.len() > 1 {
-            strreturn = strreturn + &format!("filename {names} base64 {{ {} }}\n\n", file.encode_base64());
+            let mut entry = RavnValue::Block(Vec::new());
+            entry.push("base64", file.encode_base64().into());
+            document.push(names.as_str(), entry);
         } else {
-            strreturn = format!("base64 {{ {} }}\n", file.encode_base64());
+            document.push("base64", file.encode_base64().into());
         }
     }
-    return Some(strreturn);
+    Some(to_ravnkey(&document))
 }
 
 // Takes the path and returns only the file_name
@@ -401,14 +431,65 @@ fn basename(input: &String) -> Option<String> {
 
 // As this function do not return Err(e) does not matter if (e) is static str becuase
 // never will exist
-fn date() -> Result<String, &'static str> {
-    let convertion = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH){
-        Ok(d) => (d.as_secs() as i64).epoch_to_human(),
-        Err(_e) => {
-            return Err(&"Error getting SystemTime since UNIX_EPOCH");
-        },
+// _date [-u] [-z zone] [-d timestamp] [--rfc3339] [+format]
+// Everything after '+' is the format, so it can have spaces.
+fn date(input: &str) -> Result<String, String> {
+    // The format is the first word starting with '+' which is not the value of an option, it takes the rest of
+    // the line; a date as "2024-01-02T03:04:05+01:00" has '+' too
+    let mut format_at = None;
+    let mut previous = "";
+    let mut at = 0;
+    for word in input.split(' ') {
+        if word.starts_with('+') && !matches!(previous, "-d" | "--date" | "-z" | "--zone") {
+            format_at = Some(at);
+            break;
+        }
+        if !word.is_empty() {
+            previous = word;
+        }
+        at += word.len() + 1;
+    }
+    let (options, format) = match format_at {
+        Some(i) => (&input[..i], Some(&input[i + 1..])),
+        None => (input, None),
+    };
+    let spec = ArgSpec::new("_date", "Display the current time and date, or other date, in the local zone, UTC or other zone.")
+        .flag("-u, --utc", "in UTC.")
+        .option("-z, --zone", "zone", ValueType::Text, "in other zone; Europe/Madrid, UTC, CET-1CEST,M3.5.0,M10.5.0/3.")
+        .option("-d, --date", "date", ValueType::Text, "other date; 2024-01-02T03:04:05Z or @1704164645.")
+        .flag("-I, --rfc3339", "in RFC 3339 format.")
+        .optional("+format", "format of the date, the rest of the line; '+%Y-%m-%d %H:%M:%S %Z'.");
+    let matches = match spec.parse(&options.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
     };
-    Ok(format!( "{convertion}"))
+    if let Some(d) = matches.positional("+format") {
+        return Err(format!("unexpected argument '{d}', the format starts with '+'"));
+    }
+
+    let mut zone = if matches.flag("--utc") { TimeZone::utc() } else { TimeZone::local() };
+    if let Some(name) = matches.value("--zone") {
+        zone = match TimeZone::load(name) {
+            Ok(d) => d,
+            Err(e) => TimeZone::from_posix(name).ok_or(format!("zone {name}; {e}"))?,
+        };
+    }
+    let moment: Option<DateTime> = match matches.value("--date") {
+        Some(text) => Some(parse_timestamp(text).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let rfc3339 = matches.flag("--rfc3339");
+
+    let datetime = match moment {
+        Some(d) => DateTime::in_zone(d.timestamp(), d.nanosecond, &zone),
+        None => DateTime::now_in(&zone),
+    };
+    Ok(match (format, rfc3339) {
+        (Some(f), _) => datetime.format(f),
+        (None, true) => datetime.to_rfc3339(),
+        (None, false) => datetime.format("%Y-%m-%d %H:%M:%S %Z"),
+    })
 }
 
 fn decodebase64(input: &String) -> Option<String> {
@@ -443,44 +524,192 @@ fn decodebase64(input: &String) -> Option<String> {
     }
 }
 
-fn disk_usage(input: &String) -> Option<String> {
-    let input = input.clone();
+// Disk usage options
+struct DuConfig {
+    max_depth: Option<usize>,
+    apparent: bool,
+    one_fs: bool,
+    excludes: Vec<Regex>,
+    top: Option<usize>,
+}
 
-    if input.split(' ').map(|e| e.to_string()).collect::<Vec<String>>().len() > 1 {
-        return Some("Too many arguments. Pass just one path".to_string());
-    } else if input.len() == 0 {
-        return Some("Too few arguments. Pass just one path".to_string());
+fn disk_usage(input: &str) -> Result<String, String> {
+    let mut config = DuConfig {
+        max_depth: None,
+        apparent: false,
+        one_fs: false,
+        excludes: Vec::new(),
+        top: None,
+    };
+    let spec = ArgSpec::new("_du", "Show disk usage in paths, read recursively.")
+        .option("-d, --max-depth", "number", ValueType::Unsigned, "show only the entries up to [number] directories below the paths.")
+        .option("-n, --top", "number", ValueType::Unsigned, "show only the [number] biggest entries.")
+        .option("--exclude", "glob", ValueType::Text, "skip the entries whose name or path match, it can be given several times.")
+        .flag("-b, --apparent-size", "apparent size instead of allocated.")
+        .flag("-x, --one-file-system", "stay in the filesystem of each path.")
+        .many("path", "the current directory by default.");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+    config.max_depth = matches.get("--max-depth");
+    config.top = matches.get("--top");
+    config.apparent = matches.flag("--apparent-size");
+    config.one_fs = matches.flag("--one-file-system");
+    for glob in matches.values("--exclude") {
+        match Regex::from_glob(glob) {
+            Ok(d) => config.excludes.push(d),
+            Err(e) => return Err(format!("Invalid exclude pattern {glob}; {e}")),
+        }
     }
+    let mut paths: Vec<&str> = matches.positional_values("path");
 
-    let fs_struct = input.readdir_recursive();
-    let mut temp_buff = HashMap::new();
-    let mut result = String::new();
+    if paths.is_empty() {
+        paths.push(".");
+    }
 
-    for dir in fs_struct.dbuff {
-        let size = match fs::metadata(dir.clone()){
-            Ok(d) => d.size().size_to_human(),
-            Err(_e) => format!("Error getting directory's metadata"),
+    // Files with more than one hard link are counted once, by device and inode
+    let mut seen: HashSet<(u64, u64)> = HashSet::new();
+    let mut entries: Vec<(u64, String)> = Vec::new();
+    for path in paths {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("{path}; {e}")),
         };
-        temp_buff.insert(size, dir);
+        let _ = du_walk(Path::new(path), &metadata, 0, metadata.dev(), &config, &mut seen, &mut entries);
     }
 
-    for file in fs_struct.fbuff {
-        let size = match fs::metadata(file.clone()){
-            Ok(d) => d.size().size_to_human(),
-            Err(_e) => format!("Error getting file's metadata"),
-        };
-        temp_buff.insert(size, file);
+    if let Some(top) = config.top {
+        entries.sort_by_key(|e| std::cmp::Reverse(e.0));
+        entries.truncate(top);
     }
 
-    for (k, i) in temp_buff {
-        result = result + &format!("{k} {i}\n");
+    let mut result = String::new();
+    for (size, path) in entries {
+        result = result + &format!("{} {path}\n", size.size_to_human());
     }
+    Ok(result)
+}
 
-    if result.is_empty() {
-        return None;
+// Walks "path" adding to "entries" each file and directory (with the size of its content) inside the
+// max depth. Returns the size of "path".
+fn du_walk(
+    path: &Path,
+    metadata: &fs::Metadata,
+    depth: usize,
+    root_dev: u64,
+    config: &DuConfig,
+    seen: &mut HashSet<(u64, u64)>,
+    entries: &mut Vec<(u64, String)>,
+) -> u64 {
+    if !metadata.is_dir() && metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino())) {
+        return 0;
+    }
+    // Allocated size is counted in 512 bytes blocks, not in filesystem's blocks
+    let mut size = if config.apparent {
+        metadata.size()
     } else {
-        return Some(result);
+        metadata.blocks() * 512
+    };
+
+    if metadata.is_dir() {
+        match fs::read_dir(path) {
+            Ok(d) => {
+                for entry in d.flatten() {
+                    let epath = entry.path();
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let full = epath.display().to_string();
+                    if config.excludes.iter().any(|e| e.is_match(&name) || e.is_match(&full)) {
+                        continue;
+                    }
+                    let emetadata = match fs::symlink_metadata(&epath) {
+                        Ok(d) => d,
+                        Err(e) => {
+                            eprintln!("{full}; {e}");
+                            continue;
+                        }
+                    };
+                    if config.one_fs && emetadata.dev() != root_dev {
+                        continue;
+                    }
+                    size += du_walk(&epath, &emetadata, depth + 1, root_dev, config, seen, entries);
+                }
+            }
+            Err(e) => eprintln!("{}; {e}", path.display()),
+        }
+    }
+
+    if config.max_depth.is_none_or(|e| depth <= e) {
+        entries.push((size, path.display().to_string()));
     }
+    size
+}
+
+// Show the filesystems' space. Without arguments show all mounted filesystems.
+fn disk_free(input: &str) -> Result<String, String> {
+    // Mount point, device and filesystem type
+    let mut mounts: Vec<(String, String, String)> = Vec::new();
+    let mtab = match fs::read_to_string("/proc/mounts") {
+        Ok(d) => d,
+        Err(e) => return Err(format!("Error reading /proc/mounts; {e}")),
+    };
+    for line in mtab.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() >= 3 {
+            // Spaces in mount points are written as octal (\040)
+            mounts.push((fields[1].replace("\\040", " "), fields[0].to_string(), fields[2].to_string()));
+        }
+    }
+
+    let paths: Vec<&str> = input.split_whitespace().collect();
+    let mut selected: Vec<(String, String, String)> = Vec::new();
+    if paths.is_empty() {
+        selected = mounts;
+    } else {
+        for path in paths {
+            let cpath = match fs::canonicalize(path) {
+                Ok(d) => d,
+                Err(e) => return Err(format!("{path}; {e}")),
+            };
+            // The mount point is the longest one that contains the path
+            match mounts.iter().filter(|e| cpath.starts_with(&e.0)).max_by_key(|e| e.0.len()) {
+                Some(d) => selected.push(d.clone()),
+                None => return Err(format!("{path}; mount point not found")),
+            }
+        }
+    }
+
+    let mut document = RavnValue::Block(Vec::new());
+    for (mount, device, fstype) in selected {
+        let stat = match fs_stat(Path::new(&mount)) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{mount}; {e}");
+                continue;
+            }
+        };
+        // Pseudo filesystems (proc, sysfs, etc) have not size
+        if stat.total == 0 && input.trim().is_empty() {
+            continue;
+        }
+        let used = stat.total - stat.free;
+        // Same as "df", the percentage is from the space available to users
+        let percent = if used + stat.available > 0 {
+            (used * 100).div_ceil(used + stat.available)
+        } else {
+            0
+        };
+        let mut entry = RavnValue::Block(Vec::new());
+        entry.push("device", device.into());
+        entry.push("type", fstype.into());
+        entry.push("size", stat.total.size_to_human().into());
+        entry.push("used", used.size_to_human().into());
+        entry.push("available", stat.available.size_to_human().into());
+        entry.push("use", format!("{percent}%").into());
+        document.push(mount, entry);
+    }
+    Ok(to_ravnkey(&document))
 }
 
 fn environmentvar() -> String {
@@ -573,16 +802,14 @@ fn expand(input: String) -> String {
 }
 
 fn count(input: &String) -> String {
-    let mut output;
-    output = format!("Lines {{ {} }} \n", match fs::read_to_string(input){
-        Ok(d) => d.lines().count(),
-        Err(e) => return format!("Error reading file; {e}"),
-    });
-    output = output + &format!("Words - Letters {{ {:?} }} \n", match fs::read_to_string(input){
-        Ok(d) => d.word_count(),
+    let content = match fs::read_to_string(input) {
+        Ok(d) => d,
         Err(e) => return format!("Error reading file; {e}"),
-    });
-    output
+    };
+    let mut document = RavnValue::Block(Vec::new());
+    document.push("Lines", content.lines().count().to_string().into());
+    document.push("Words - Letters", format!("{:?}", content.word_count()).into());
+    to_ravnkey(&document)
 }
 
 fn cd(path: String) -> () {
@@ -832,6 +1059,229 @@ fn copy<'a>(source: &Path, dest: &Path) -> Result<(), &'a str> {
     }
 }
 
+// ELF files as ravnkey; "[file] { class { 64 } ... segment { ... } section { ... } }"
+fn elf(input: &str) -> Result<String, String> {
+    let spec = ArgSpec::new("_elf", "Show the headers, segments, sections, symbols, dynamic section and notes of ELF files.")
+        .flag("--header", "show the whole file header.")
+        .flag("-l, --segments", "show the program headers.")
+        .flag("-S, --sections", "show the section headers.")
+        .flag("-s, --symbols", "show the symbols of '.symtab'.")
+        .flag("--dyn-syms", "show the symbols of '.dynsym'.")
+        .flag("-d, --dynamic", "show the dynamic section.")
+        .flag("-n, --notes", "show the notes.")
+        .flag("-a, --all", "show everything.")
+        .flag("--static", "only check that the files are fully static, without interpreter and needed libraries.")
+        .many("file", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+    let files = matches.positional_values("file");
+    if files.is_empty() {
+        return Err("Few arguments; [options] [file_1] [file_n]".to_string());
+    }
+    let all = matches.flag("-a");
+    let hex = |e: u64| format!("{e:#x}");
+
+    let mut document = RavnValue::Block(Vec::new());
+    let mut dynamic_files = Vec::new();
+    for file in files {
+        let elf = match Elf::open(Path::new(file)) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("{file}; {e}")),
+        };
+        let mut entry = RavnValue::Block(Vec::new());
+        if matches.flag("--static") {
+            entry.push("static", elf.is_static().to_string().into());
+            if !elf.is_static() {
+                dynamic_files.push(file);
+            }
+            document.push(file, entry);
+            continue;
+        }
+
+        let header = &elf.header;
+        entry.push("class", header.class.to_string().into());
+        entry.push("endian", if header.little_endian { "little" } else { "big" }.into());
+        entry.push("type", if elf.is_pie() { "pie executable" } else { elf_kind_name(header.kind) }.into());
+        entry.push("machine", elf_machine_name(header.machine).into());
+        entry.push("entry", hex(header.entry).into());
+        if matches.flag("--header") || all {
+            entry.push("os_abi", header.os_abi.to_string().into());
+            entry.push("version", header.version.to_string().into());
+            entry.push("flags", hex(header.flags as u64).into());
+            entry.push("phoff", header.phoff.to_string().into());
+            entry.push("shoff", header.shoff.to_string().into());
+            entry.push("segments", header.phnum.to_string().into());
+            entry.push("sections", header.shnum.to_string().into());
+            entry.push("shstrndx", header.shstrndx.to_string().into());
+        }
+        if let Some(d) = &elf.interpreter {
+            entry.push("interpreter", d.as_str().into());
+        }
+        // Objects and cores are not linked, they are not static or dynamic
+        if header.kind == 2 || header.kind == 3 {
+            entry.push("static", elf.is_static().to_string().into());
+        }
+        if let Some(d) = elf.build_id() {
+            entry.push("build_id", d.into());
+        }
+        for library in elf.needed() {
+            entry.push("needed", library.into());
+        }
+
+        if matches.flag("-l") || all {
+            for segment in &elf.segments {
+                let flags: String = [(4, 'r'), (2, 'w'), (1, 'x')]
+                    .iter()
+                    .map(|(bit, letter)| if segment.flags & bit != 0 { *letter } else { '-' })
+                    .collect();
+                let mut block = RavnValue::Block(Vec::new());
+                block.push("type", segment_kind_name(segment.kind).into());
+                block.push("flags", flags.into());
+                block.push("offset", hex(segment.offset).into());
+                block.push("vaddr", hex(segment.vaddr).into());
+                block.push("paddr", hex(segment.paddr).into());
+                block.push("filesz", segment.filesz.to_string().into());
+                block.push("memsz", segment.memsz.to_string().into());
+                block.push("align", segment.align.to_string().into());
+                entry.push("segment", block);
+            }
+        }
+        if matches.flag("-S") || all {
+            // The first section header is always empty
+            for section in elf.sections.iter().skip(1) {
+                let mut block = RavnValue::Block(Vec::new());
+                block.push("name", section.name.as_str().into());
+                block.push("type", section_kind_name(section.kind).into());
+                block.push("flags", hex(section.flags).into());
+                block.push("addr", hex(section.addr).into());
+                block.push("offset", hex(section.offset).into());
+                block.push("size", section.size.to_string().into());
+                block.push("link", section.link.to_string().into());
+                block.push("info", section.info.to_string().into());
+                block.push("align", section.align.to_string().into());
+                block.push("entsize", section.entsize.to_string().into());
+                entry.push("section", block);
+            }
+        }
+        if matches.flag("-s") || all {
+            for symbol in elf.symbols.iter().skip(1) {
+                entry.push("symbol", elf_symbol(&elf, symbol));
+            }
+        }
+        if matches.flag("--dyn-syms") || all {
+            for symbol in elf.dynamic_symbols.iter().skip(1) {
+                entry.push("dynamic_symbol", elf_symbol(&elf, symbol));
+            }
+        }
+        if matches.flag("-d") || all {
+            for dynamic in &elf.dynamic {
+                let mut block = RavnValue::Block(Vec::new());
+                block.push("tag", dynamic_tag_name(dynamic.tag).into());
+                match &dynamic.text {
+                    Some(d) => block.push("value", d.as_str().into()),
+                    None => block.push("value", hex(dynamic.value).into()),
+                }
+                entry.push("dynamic", block);
+            }
+        }
+        if matches.flag("-n") || all {
+            for note in &elf.notes {
+                let mut block = RavnValue::Block(Vec::new());
+                block.push("owner", note.owner.as_str().into());
+                block.push("type", note.kind_name().into());
+                block.push("size", note.desc.len().to_string().into());
+                entry.push("note", block);
+            }
+        }
+        document.push(file, entry);
+    }
+
+    let output = to_ravnkey(&document).trim_end().to_string();
+    // With '--static' the result is false when a file is dynamic, so it can be used in conditions
+    if !dynamic_files.is_empty() {
+        println!("{output}");
+        return Err(format!("Not static; {}", dynamic_files.join(" ")));
+    }
+    Ok(output)
+}
+
+fn elf_symbol(elf: &Elf, symbol: &Symbol) -> RavnValue {
+    let mut block = RavnValue::Block(Vec::new());
+    block.push("name", symbol.name.as_str().into());
+    block.push("value", format!("{:#x}", symbol.value).into());
+    block.push("size", symbol.size.to_string().into());
+    block.push("bind", symbol_bind_name(symbol.bind).into());
+    block.push("type", symbol_kind_name(symbol.kind).into());
+    block.push("section", elf.section_name(symbol.section).into());
+    block
+}
+
+// Type of the files by their content, as ravnkey; "[file] { type { [type] } mime { [mime] } ... }"
+fn file_type(input: &str) -> Result<String, String> {
+    let spec = ArgSpec::new("_file", "Show the type of the files by their content.")
+        .flag("--mime", "show only the MIME type.")
+        .many("file", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+    let files = matches.positional_values("file");
+    if files.is_empty() {
+        return Err("Few arguments; [options] [file_1] [file_n]".to_string());
+    }
+
+    let mut document = RavnValue::Block(Vec::new());
+    for file in files {
+        let metadata = match fs::symlink_metadata(file) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("{file}; {e}")),
+        };
+        // Only regular files are read, the rest are known by their metadata
+        let kind = metadata.file_type();
+        let special = if kind.is_symlink() {
+            Some("inode/symlink")
+        } else if kind.is_dir() {
+            Some("inode/directory")
+        } else if kind.is_block_device() {
+            Some("inode/blockdevice")
+        } else if kind.is_char_device() {
+            Some("inode/chardevice")
+        } else if kind.is_fifo() {
+            Some("inode/fifo")
+        } else if kind.is_socket() {
+            Some("inode/socket")
+        } else {
+            None
+        };
+        let details: Vec<(&str, String)> = match special {
+            Some(mime) => {
+                let mut details = vec![("type", mime["inode/".len()..].to_string()), ("mime", mime.to_string())];
+                if let Ok(d) = fs::read_link(file) {
+                    details.push(("target", d.display().to_string()));
+                }
+                details
+            }
+            None => match sniff_file(Path::new(file)) {
+                Ok(d) => d.details(),
+                Err(e) => return Err(format!("{file}; {e}")),
+            },
+        };
+
+        let mut entry = RavnValue::Block(Vec::new());
+        for (key, value) in details {
+            if !matches.flag("--mime") || key == "mime" {
+                entry.push(key, value.into());
+            }
+        }
+        document.push(file, entry);
+    }
+    Ok(to_ravnkey(&document).trim_end().to_string())
+}
+
 fn ffalse(input: &String) -> Result<(), bool> {
     if !input.contains("-n") && !input.contains("-u") {
         eprintln!("Bad arguments; -n or -u");
@@ -846,28 +1296,197 @@ fn ffalse(input: &String) -> Result<(), bool> {
     return Err(false);
 }
 
-fn head(input: &String) {
-    let file;
-    let mut fdata = Default::default();
-    let mut lnumber = 0;
-    let mut cnumber = 1;
-    let s_lnumber: Vec<&str> = input.split(' ').collect();
-    if input.contains("-n") && s_lnumber.len() <= 2 {
-        eprintln!("Not enough arguments. Remember; head -n [number] [file]");
-        return;
+// Compress files with gzip, the original files are kept
+fn gzip(input: &str) -> Result<String, String> {
+    let mut level = DEFAULT_LEVEL;
+    let mut files: Vec<&str> = Vec::new();
+    for arg in input.split_whitespace() {
+        match arg.strip_prefix('-').map(|l| l.parse::<u32>()) {
+            Some(Ok(l)) if (1..=9).contains(&l) => level = l,
+            Some(_) => return Err(format!("Unknown option; {arg}")),
+            None => files.push(arg),
+        }
     }
-    if input.contains("-n") {
-        lnumber = match s_lnumber[1].trim().parse() {
+    if files.is_empty() {
+        return Err("Few arguments; [option] [file_1] [file_n]".to_string());
+    }
+
+    let mut result = String::new();
+    for file in files {
+        let source = Path::new(file);
+        if source.is_dir() {
+            return Err(format!("{file}; is a directory"));
+        }
+        let target = format!("{file}.gz");
+        if Path::new(&target).exists() {
+            return Err(format!("{target}; already exists"));
+        }
+        let mut input = match File::open(source) {
+            Ok(d) => BufReader::new(d),
+            Err(e) => return Err(format!("{file}; {e}")),
+        };
+        // The header keeps the original name and modification time, like gzip does
+        let mtime = fs::metadata(source).map(|m| m.mtime().max(0) as u32).unwrap_or(0);
+        let name = source.file_name().map(|n| n.to_string_lossy().to_string());
+        let output = match File::create(&target) {
             Ok(d) => d,
-            Err(e) => {
-                eprintln!(
-                    "Error parsing str to int; {e}\nVerify kernel compatiblity with Rust std"
-                );
-                return;
-            }
+            Err(e) => return Err(format!("{target}; {e}")),
+        };
+        let mut encoder = GzEncoder::with_header(io::BufWriter::new(output), level, name.as_deref(), mtime);
+        let compressed = io::copy(&mut input, &mut encoder).and_then(|_| encoder.finish());
+        if let Err(e) = compressed {
+            let _ = fs::remove_file(&target);
+            return Err(format!("{file}; {e}"));
+        }
+        result = result + &target + "\n";
+    }
+    Ok(result.trim_end().to_string())
+}
+
+// Decompress gzip files, the compressed files are kept
+fn gunzip(input: &str) -> Result<String, String> {
+    let files: Vec<&str> = input.split_whitespace().collect();
+    if files.is_empty() {
+        return Err("Few arguments; [file_1] [file_n]".to_string());
+    }
+
+    let mut result = String::new();
+    for file in files {
+        let target = if let Some(name) = file.strip_suffix(".tgz") {
+            format!("{name}.tar")
+        } else if let Some(name) = file.strip_suffix(".gz") {
+            name.to_string()
+        } else {
+            return Err(format!("{file}; unknown suffix, '.gz' or '.tgz' expected"));
+        };
+        if Path::new(&target).exists() {
+            return Err(format!("{target}; already exists"));
+        }
+        let mut decoder = match File::open(file) {
+            Ok(d) => GzDecoder::new(BufReader::new(d)),
+            Err(e) => return Err(format!("{file}; {e}")),
+        };
+        let output = match File::create(&target) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("{target}; {e}")),
+        };
+        let mut output = io::BufWriter::new(output);
+        let decompressed = io::copy(&mut decoder, &mut output).and_then(|_| output.flush());
+        if let Err(e) = decompressed {
+            let _ = fs::remove_file(&target);
+            return Err(format!("{file}; {e}"));
+        }
+        result = result + &target + "\n";
+    }
+    Ok(result.trim_end().to_string())
+}
+
+// Digests of files, or verification of a manifest with them
+fn hash(input: &str) -> Result<String, String> {
+    let spec = ArgSpec::new("_hash", "Show the files' digest, or verify the digests saved in a manifest.")
+        .option("-a, --algorithm", "name", ValueType::Text, "sha256, sha512, blake2b or crc32; sha256 by default.")
+        .option("-c, --check", "manifest", ValueType::Text, "verify the digests saved in the manifest.")
+        .many("file", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+    let algorithm = match matches.value("--algorithm") {
+        Some(name) => match HashAlgorithm::from_name(name) {
+            Some(d) => d,
+            None => return Err(format!("Unknown algorithm; {name}")),
+        },
+        None => HashAlgorithm::Sha256,
+    };
+    let manifest: Option<&str> = matches.value("--check");
+    let files: Vec<&str> = matches.positional_values("file");
+
+    let mut document = RavnValue::Block(Vec::new());
+    if let Some(manifest) = manifest {
+        let content = match fs::read_to_string(manifest) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("{manifest}; {e}")),
         };
+        // Entries are "[file] { [algorithm] { [digest] } }" as "_hash" prints them,
+        // or lines "[digest]  [file]" as the "*sum" tools do
+        let mut expected: Vec<(String, HashAlgorithm, String)> = Vec::new();
+        if let Ok(entries) = parse_ravnkey(&content) {
+            for (file, value) in entries.entries().unwrap_or_default() {
+                for (name, digest) in value.entries().unwrap_or_default() {
+                    let name = match HashAlgorithm::from_name(name) {
+                        Some(d) => d,
+                        None => return Err(format!("{manifest}; unknown algorithm; {name}")),
+                    };
+                    expected.push((file.clone(), name, digest.as_text().unwrap_or("").to_string()));
+                }
+            }
+        } else {
+            for line in content.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+                match line.split_once(char::is_whitespace) {
+                    Some((digest, file)) => expected.push((
+                        file.trim_start().trim_start_matches('*').to_string(),
+                        algorithm,
+                        digest.to_string(),
+                    )),
+                    None => return Err(format!("{manifest}; malformed line; {line}")),
+                }
+            }
+        }
+
+        let mut failed = 0;
+        for (file, name, digest) in expected {
+            let status = match hash_file(name, Path::new(&file)) {
+                Ok(d) if d.eq_ignore_ascii_case(&digest) => "OK",
+                Ok(_) => "FAILED",
+                Err(_e) => "MISSING",
+            };
+            if status != "OK" {
+                failed += 1;
+            }
+            document.push(file, status.into());
+        }
+        if failed > 0 {
+            return Err(format!("{}{failed} files do not match", to_ravnkey(&document)));
+        }
+        return Ok(to_ravnkey(&document).trim_end().to_string());
+    }
+
+    if files.is_empty() {
+        return Err("Few arguments; [options] [file_1] [file_n]".to_string());
+    }
+    for file in files {
+        match hash_file(algorithm, Path::new(file)) {
+            Ok(d) => {
+                let mut entry = RavnValue::Block(Vec::new());
+                entry.push(algorithm.name(), d.into());
+                document.push(file, entry);
+            }
+            Err(e) => return Err(format!("{file}; {e}")),
+        }
     }
-    file = match File::open(s_lnumber[2].trim()) {
+    Ok(to_ravnkey(&document).trim_end().to_string())
+}
+
+fn head(input: &String) {
+    let mut fdata = Default::default();
+    let mut cnumber = 1;
+    let spec = ArgSpec::new("_head", "Show the first lines of the file.")
+        .option("-n", "number", ValueType::Unsigned, "lines shown, 10 by default.")
+        .positional("file", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => {
+            print!("{d}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let lnumber: u64 = matches.get("-n").unwrap_or(10);
+    let file = match File::open(matches.positional("file").unwrap_or_default()) {
         Ok(d) => d,
         Err(e) => {
             eprintln!("Error opening file; {e}");
@@ -875,8 +1494,6 @@ fn head(input: &String) {
         }
     };
 
-    drop(s_lnumber);
-
     // Is more eficient that a raw read
     let mut buff = BufReader::new(&file);
     let _ = buff.read_to_string(&mut fdata);
@@ -909,20 +1526,24 @@ fn id(input: &String) -> Result<String, String> {
         // The return is a Vec<&str> we take the [1] position
         let uuid = (uuid_line[0].split(':').collect::<Vec<&str>>())[2];
         let guid = (uuid_line[0].split(':').collect::<Vec<&str>>())[3];
-        println!("userid {{ {uuid} }} ");
-        println!("groupid {{ {guid} }} ");
+        let mut document = RavnValue::Block(Vec::new());
+        document.push("userid", uuid.into());
+        document.push("groupid", guid.into());
+        print!("{}", to_ravnkey(&document));
     }
 
     if input.contains("-g") {
         let guid_line = file_filter(&"/etc/group".to_string(), username);
-        let mut groups = String::new();
+        let mut groups = RavnValue::List(Vec::new());
         for i in guid_line {
             let name = (i.split(':').collect::<Vec<&str>>())[0];
             let id = (i.split(':').collect::<Vec<&str>>())[2];
-            groups = groups + &name + ":" + &id + "\n";
+            groups.push_item(format!("{name}:{id}").into());
         }
 
-        println!("groups; {{ {groups} }}");
+        let mut document = RavnValue::Block(Vec::new());
+        document.push("groups", groups);
+        print!("{}", to_ravnkey(&document));
     }
 
     if !input.contains("-u") && !input.contains("-n") && !input.contains("-g") {
@@ -979,7 +1600,7 @@ fn ln(source: &Path, dest: &Path) -> Result<String, ()> {
 fn ls(input: &String) -> String {
     // env::args() takes program's arguments
     // collect() takes arguments and returns in tuple
-    let arguments: Vec<String> = input.split(' ').map(|e| e.to_string()).collect();
+    let arguments: Vec<&str> = input.split_whitespace().collect();
 
     // Result buffer
     let mut buffer: Vec<String> = Vec::new();
@@ -995,15 +1616,19 @@ fn ls(input: &String) -> String {
         clean: false,
     };
 
-    if arguments.checkarguments_help("ls") {
-        //returnbuff = format!(" ");
-        return " ".to_string();
-    }
+    let spec = ArgSpec::new("_ls", "List files and directories in the paths, the current directory by default.")
+        .flag("-l", "show directory's files and directories number.")
+        .flag("-v", "show owner, permissions, datetime format and size (in bytes).")
+        .flag("-c", "clean verbose to show only directory's content.")
+        .flag("--proc", "show the system's processes trough /proc filesystem. Only in Unix systems.")
+        .many("path", "");
+    let matches = match spec.parse(&arguments) {
+        Ok(d) => d,
+        Err(e) => return e.to_string(),
+    };
 
-    // The vec<String> return with files index is stored in "lists" variable.
-    // The method is from RavnArguments trait.
-    let mut options: Vec<&str> = Vec::new();
-    let mut lists: Vec<String> = arguments.check_arguments("ls", &mut options);
+    // The paths are stored in "lists" variable.
+    let mut lists: Vec<String> = matches.positional_values("path").into_iter().map(|e| e.to_string()).collect();
 
     if lists.is_empty() || lists.contains(&"".to_string()) || lists.contains(&".".to_string()) {
          lists.push(match env::current_dir(){
@@ -1030,17 +1655,10 @@ fn ls(input: &String) -> String {
         lists.remove(index);
     }
 
-    for confs in options {
-        if confs == "verbose" {
-            config.verbose = true;
-        } else if confs == "proc" {
-            config.proc = true;
-        } else if confs == "lines" {
-            config.lines = true;
-        } else if confs == "clean" {
-            config.clean = true;
-        }
-    }
+    config.verbose = matches.flag("-v");
+    config.proc = matches.flag("--proc");
+    config.lines = matches.flag("-l");
+    config.clean = matches.flag("-c");
 
     if config.proc {
         let procs: Vec<String> = getprocs();
@@ -1186,10 +1804,12 @@ fn ls(input: &String) -> String {
                 returnbuff = returnbuff + &format!("{}\n", ee);
             }
         } else {
+            let mut document = RavnValue::Block(Vec::new());
+            document.push(names.as_str(), entries.iter().map(|e| e.display().to_string() + "\n").collect::<String>().into());
             if !config.clean && lists.len() > 1 {
-                returnbuff = returnbuff + &format!("{names} {{ \n{}\n }}\n", &entries.iter().map(|e| e.display().to_string() + "\n").collect::<String>().trim() );
+                returnbuff += &to_ravnkey(&document);
             } else {
-                returnbuff = returnbuff + &format!( "{names} {{ \n{}\n }}",&entries.iter().map(|e| e.display().to_string() + "\n").collect::<String>().trim() );
+                returnbuff += to_ravnkey(&document).trim_end();
             }
         }
 
@@ -1374,7 +1994,7 @@ fn remove_f_d(arguments: String) -> Result<(), String> {
 }
 
 fn show(input: &String) -> Option<String> {
-    let arguments: Vec<String> = input.trim().split(' ').map(|e| e.to_string()).collect();
+    let arguments: Vec<&str> = input.split_whitespace().collect();
     //let string_return = String::new();
 
     // Init the configuration as clean
@@ -1382,29 +2002,54 @@ fn show(input: &String) -> Option<String> {
         clean: false,
         stdin: false,
         hexa: false,
+        json: false,
+        ravnkey: false,
     };
 
-    if arguments.checkarguments_help("show") {
-        return None;
-    }
+    let spec = ArgSpec::new("_show", "Show the content of the files.")
+        .flag("-c", "clean verbose to show only file's content.")
+        .flag("--stdin", "read from standard input in addition of 'file n'.")
+        .flag("--hexa", "show file's content in hexadecimal.")
+        .flag("--json", "convert the ravnkey files to JSON.")
+        .flag("--ravnkey", "convert the JSON files to ravnkey.")
+        .option("--match", "regex", ValueType::Text, "show only the lines which match the regular expression.")
+        .many("file", "");
+    let matches = match spec.parse(&arguments) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Some(d),
+        Err(e) => {
+            eprintln!("{e}");
+            return None;
+        }
+    };
 
-    // The vec<String> return with files index is stored in "archives" variable.
-    // The method is from RavnArguments trait.
-    let mut options: Vec<&str> = Vec::new();
-    let archives: Vec<String> = arguments.check_arguments("show", &mut options);
+    // "--match [regex]" keeps only the lines which match
+    let filter: Option<Regex> = match matches.value("--match") {
+        Some(pattern) => match Regex::new(pattern) {
+            Ok(d) => Some(d),
+            Err(e) => {
+                eprintln!("Error in regular expression '{pattern}'; {e}");
+                return None;
+            }
+        },
+        None => None,
+    };
 
-    if archives.len() == 0 || archives[0] == "" {
-        return None;
+    // The files are stored in "archives" variable.
+    let archives: Vec<String> = matches.positional_values("file").into_iter().map(|e| e.to_string()).collect();
+
+    config.clean = matches.flag("-c");
+    config.stdin = matches.flag("--stdin");
+    config.hexa = matches.flag("--hexa");
+    config.json = matches.flag("--json");
+    config.ravnkey = matches.flag("--ravnkey");
+
+    if config.json || config.ravnkey {
+        return show_convert(&config, &archives);
     }
 
-    for confs in options {
-        if confs == "clean" {
-            config.clean = true;
-        } else if confs == "stdin" {
-            config.stdin = true;
-        } else if confs == "hexa" {
-            config.hexa = true;
-        }
+    if archives.is_empty() || archives[0].is_empty() {
+        return None;
     }
 
     // Stdinput
@@ -1417,8 +2062,9 @@ fn show(input: &String) -> Option<String> {
         // is Err(j) will print "j" (the error per se).
         match io::stdin().read_to_string(&mut buffer) {
             Ok(_i) => {
-                let buff = format!("stdin {{ {buffer} }}");
-                return Some(buff);
+                let mut document = RavnValue::Block(Vec::new());
+                document.push("stdin", matching_lines(buffer, &filter).into());
+                return Some(to_ravnkey(&document));
             }
             Err(_j) => return None,
         }
@@ -1431,13 +2077,15 @@ fn show(input: &String) -> Option<String> {
 
     for names in &archives {
         if archives.len() == 1 {
-            buffer = String::from_utf8_lossy( match &fs::read(names){
+            buffer = matching_lines(String::from_utf8_lossy( match &fs::read(names){
                 Ok(d) => d,
                 Err(_e) => return None,
-            }).to_string();
+            }).to_string(), &filter);
 
             if !config.clean && !config.hexa {
-                buffer = format!("{names} {{ {buffer} }}");
+                let mut document = RavnValue::Block(Vec::new());
+                document.push(names.as_str(), buffer.into());
+                buffer = to_ravnkey(&document);
             } else if config.clean && !config.hexa {
                 {}
             }
@@ -1464,14 +2112,16 @@ fn show(input: &String) -> Option<String> {
             if config.clean && config.hexa {
                 {}
             } else if !config.clean && config.hexa {
-                buffer = format!("{} {{ {} }}",names,buffer);
+                let mut document = RavnValue::Block(Vec::new());
+                document.push(names.as_str(), buffer.into());
+                buffer = to_ravnkey(&document);
             }
     } else {
         let mut buffer_hexa: String = String::new();
-        let buffer_string: String = String::from_utf8_lossy( match &fs::read(names){
+        let buffer_string: String = matching_lines(String::from_utf8_lossy( match &fs::read(names){
             Ok(d) => d,
             Err(_e) => return None,
-        }).to_string();
+        }).to_string(), &filter);
 
         if config.hexa {
             // Hexa mode
@@ -1502,17 +2152,87 @@ fn show(input: &String) -> Option<String> {
     if archives.len() == 1 {
         return Some(format!("{}", buffer));
     } else {
-        for (names,files) in &fvec {
+        // Files are shown in the same order they were given
+        let mut document = RavnValue::Block(Vec::new());
+        for names in &archives {
+            let files = match fvec.remove(names) {
+                Some(d) => d,
+                None => continue,
+            };
             if !config.clean {
-                fstrings += &format!("{names} {{ {files} }}\n\n");
+                document.push(names.as_str(), files.into());
             } else {
-                fstrings += &format!("{files}");
+                fstrings += &files;
             }
         }
+        if !config.clean {
+            fstrings = to_ravnkey(&document);
+        }
     }
     return Some(fstrings);
 }
 
+// Lines of "text" which match "filter", all of them without filter
+fn matching_lines(text: String, filter: &Option<Regex>) -> String {
+    match filter {
+        Some(d) => text.lines().filter(|e| d.is_match(e)).map(|e| format!("{e}\n")).collect(),
+        None => text,
+    }
+}
+
+// Convert ravnkey to JSON ("--json") or JSON to ravnkey ("--ravnkey")
+// With only one document it is converted as is, with more each one is under its file name.
+fn show_convert(config: &libconfarg::ShowConfiguration, archives: &[String]) -> Option<String> {
+    let mut inputs: Vec<(String, String)> = Vec::new();
+    if config.stdin {
+        let mut buffer = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut buffer) {
+            eprintln!("stdin; {e}");
+            return None;
+        }
+        inputs.push(("stdin".to_string(), buffer));
+    }
+    for names in archives.iter().filter(|e| !e.is_empty()) {
+        match fs::read_to_string(names) {
+            Ok(d) => inputs.push((names.to_string(), d)),
+            Err(e) => {
+                eprintln!("{names}; {e}");
+                return None;
+            }
+        }
+    }
+
+    let mut documents: Vec<(String, RavnValue)> = Vec::new();
+    for (name, text) in inputs {
+        let document = if config.json {
+            parse_ravnkey(&text)
+        } else {
+            parse_json(&text).map(|d| json_to_ravnkey(&d))
+        };
+        match document {
+            Ok(d) => documents.push((name, d)),
+            Err(e) => {
+                eprintln!("{name}; {e}");
+                return None;
+            }
+        }
+    }
+
+    if config.json {
+        let json = match documents.len() {
+            1 => ravnkey_to_json(&documents[0].1),
+            _ => JsonValue::Object(documents.iter().map(|(n, d)| (n.clone(), ravnkey_to_json(d))).collect()),
+        };
+        Some(to_json_pretty(&json))
+    } else {
+        // The top level of ravnkey must be keys, other JSON values go under the file name
+        match documents.as_slice() {
+            [(_, d @ RavnValue::Block(_))] => Some(to_ravnkey(d)),
+            _ => Some(to_ravnkey(&RavnValue::Block(documents))),
+        }
+    }
+}
+
 fn sleep(input: &String) -> Result<(), String> {
     let seconds = match (input.split(':').collect::<Vec<&str>>())[0].parse::<u64>(){
         Ok(d) => d,
@@ -1589,27 +2309,416 @@ fn tail(input: &String) -> Result<(), &str> {
     Ok(())
 }
 
-fn fwhich(input: &String) -> Result<String, &str> {
-    let result: Vec<String> = which(input.to_string());
-    let mut sreturn = String::new();
-    if !result.is_empty() {
-        if result.len() > 1 {
-            for i in result {
-                if !sreturn.is_empty() {
-                    sreturn = sreturn + &"," + &i;
+// Create, list and extract tar archives
+fn tar(input: &str) -> Result<String, String> {
+    // Options can be joined, like "-tv"
+    let spec = ArgSpec::new("_tar", "Create, list and extract tar archives, compressed archives are detected when listing or extracting.")
+        .flag("-c", "create [archive] with [path_1] [path_n].")
+        .flag("-t", "list [archive].")
+        .flag("-x", "extract [archive] into [destination], the current directory by default.")
+        .flag("-v", "show the paths, with '-t' show also their type, date, permissions, owner and size.")
+        .flag("-z", "with '-c', compress the archive with gzip.")
+        .positional("archive", "")
+        .many("path", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+    let modes: Vec<char> = ['c', 't', 'x'].into_iter().filter(|e| matches.flag(&e.to_string())).collect();
+    let mode = match modes[..] {
+        [d] => d,
+        _ => ' ',
+    };
+    let verbose = matches.flag("-v");
+    let compress = matches.flag("-z");
+    let args: Vec<&str> = matches.positionals();
+    let archive = args[0];
+    let mut result = String::new();
+
+    match mode {
+        'c' => {
+            if args.len() < 2 {
+                return Err("Few arguments; -c [archive] [path_1] [path_n]".to_string());
+            }
+            let file = match File::create(archive) {
+                Ok(d) => d,
+                Err(e) => return Err(format!("{archive}; {e}")),
+            };
+            let created = if compress {
+                tar_create(GzEncoder::new(io::BufWriter::new(file), DEFAULT_LEVEL), &args[1..])
+                    .and_then(|w| w.finish().map_err(|e| format!("{archive}; {e}")))
+                    .map(|_| ())
+            } else {
+                tar_create(io::BufWriter::new(file), &args[1..]).map(|_| ())
+            };
+            created?;
+            if verbose {
+                result = args[1..].join("\n");
+            }
+        }
+        't' => {
+            // Compressed archives are detected by their magic number
+            let file = match open_decompressed(Path::new(archive)) {
+                Ok(d) => d,
+                Err(e) => return Err(format!("{archive}; {e}")),
+            };
+            let mut reader = TarReader::new(io::BufReader::new(file));
+            loop {
+                let entry = match reader.next_entry() {
+                    Ok(Some(d)) => d,
+                    Ok(None) => break,
+                    Err(e) => return Err(format!("{archive}; {e}")),
+                };
+                let name = match entry.kind {
+                    EntryType::Symlink => format!("{} -> {}", entry.path, entry.link),
+                    EntryType::HardLink => format!("{} link to {}", entry.path, entry.link),
+                    _ => entry.path.clone(),
+                };
+                if verbose {
+                    let kind = match entry.kind {
+                        EntryType::File => "f",
+                        EntryType::Directory => "d",
+                        EntryType::Symlink => "s",
+                        EntryType::HardLink => "h",
+                        EntryType::Other(_) => "?",
+                    };
+                    let permissions: String = format!("{:06o}", entry.mode).permission_to_human().concat();
+                    result = result
+                        + &format!(
+                            "{kind}: {name} \t[{}]\t[{permissions}]\t[{}:{}] {}\n",
+                            entry.mtime.epoch_to_human(),
+                            entry.uid,
+                            entry.gid,
+                            entry.size.size_to_human()
+                        );
                 } else {
-                    sreturn = i;
+                    result = result + &name + "\n";
                 }
             }
-        } else {
-            for i in result {
-                sreturn = i;
+        }
+        'x' => {
+            let destination = args.get(1).copied().unwrap_or(".");
+            // Compressed archives are detected by their magic number
+            let file = match open_decompressed(Path::new(archive)) {
+                Ok(d) => d,
+                Err(e) => return Err(format!("{archive}; {e}")),
+            };
+            let mut reader = TarReader::new(io::BufReader::new(file));
+            match reader.unpack(Path::new(destination)) {
+                Ok(d) => {
+                    if verbose {
+                        result = d.join("\n");
+                    }
+                }
+                Err(e) => return Err(format!("{archive}; {e}")),
+            }
+        }
+        _ => return Err("One option is needed, and only one; -c, -t or -x".to_string()),
+    }
+    Ok(result)
+}
+
+// Write the paths into a new tar archive, returns the writer to finish the compression if any
+fn tar_create<W: Write>(output: W, paths: &[&str]) -> Result<W, String> {
+    let mut writer = TarWriter::new(output);
+    for path in paths {
+        if let Err(e) = writer.append_path(Path::new(path), path) {
+            return Err(format!("{path}; {e}"));
+        }
+    }
+    writer.finish().map_err(|e| e.to_string())
+}
+
+// Process information used by "top"
+struct TopProc {
+    pid: u64,
+    name: String,
+    uid: String,
+    // utime + stime, in clock ticks
+    ticks: u64,
+    // Resident memory in kB
+    rss: u64,
+    cpu: f64,
+}
+
+// Read the first line of /proc/stat and returns (total, idle) ticks
+fn top_cpu_ticks() -> Option<(u64, u64)> {
+    let data = fs::read_to_string("/proc/stat").ok()?;
+    let line = data.lines().next()?;
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|e| e.parse::<u64>().ok())
+        .collect();
+    if values.len() < 4 {
+        return None;
+    }
+    // Fourth is idle and fifth (if exists) iowait
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    Some((values.iter().sum(), idle))
+}
+
+// Read each /proc/[pid] directory and returns the processes' information
+fn top_procs() -> Vec<TopProc> {
+    let mut procs: Vec<TopProc> = Vec::new();
+    let entries = match fs::read_dir("/proc") {
+        Ok(d) => d,
+        Err(_e) => return procs,
+    };
+
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_str().and_then(|e| e.parse::<u64>().ok()) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        // The process can finish while we are reading it, so just skip it
+        let stat = match fs::read_to_string(format!("/proc/{pid}/stat")) {
+            Ok(d) => d,
+            Err(_e) => continue,
+        };
+        // The name is between parentheses and can contain spaces, so the
+        // fields are counted from the last ')'
+        let (name, rest) = match (stat.find('('), stat.rfind(')')) {
+            (Some(s), Some(e)) if s < e => (stat[s + 1..e].to_string(), &stat[e + 1..]),
+            _ => continue,
+        };
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        // Fields 14 (utime) and 15 (stime), starting from the state (field 3)
+        let ticks = match (fields.get(11), fields.get(12)) {
+            (Some(u), Some(s)) => u.parse::<u64>().unwrap_or(0) + s.parse::<u64>().unwrap_or(0),
+            _ => continue,
+        };
+
+        let mut uid = String::new();
+        let mut rss = 0;
+        if let Ok(status) = fs::read_to_string(format!("/proc/{pid}/status")) {
+            for line in status.lines() {
+                if let Some(d) = line.strip_prefix("Uid:") {
+                    uid = d.split_whitespace().next().unwrap_or("").to_string();
+                } else if let Some(d) = line.strip_prefix("VmRSS:") {
+                    rss = d.split_whitespace().next().unwrap_or("0").parse().unwrap_or(0);
+                }
             }
         }
-        return Ok(sreturn);
+
+        procs.push(TopProc {
+            pid,
+            name,
+            uid,
+            ticks,
+            rss,
+            cpu: 0.0,
+        });
+    }
+    procs
+}
+
+// Takes the "key:" field from /proc/meminfo in kB
+fn top_meminfo(meminfo: &str, key: &str) -> u64 {
+    for line in meminfo.lines() {
+        if let Some(d) = line.strip_prefix(key) {
+            return d.split_whitespace().next().unwrap_or("0").parse().unwrap_or(0);
+        }
+    }
+    0
+}
+
+// Live system monitor
+// Keys (followed by enter); 'c' sort by CPU, 'm' by memory, 'p' by PID,
+// 'n' by name and 'q' quit.
+fn top(input: &str) -> Result<(), String> {
+    let color = libstream::Colors::new();
+    let spec = ArgSpec::new("_top", "Live view of CPU, memory, load, uptime and processes.")
+        .option("-n", "number", ValueType::Unsigned, "processes shown, 15 by default.")
+        .option("-d", "seconds", ValueType::Unsigned, "refresh delay, 2 by default.")
+        .option("-s", "order", ValueType::Text, "sort order; cpu, mem, pid or name.");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => {
+            print!("{d}");
+            return Ok(());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let number: usize = matches.get("-n").unwrap_or(15);
+    let delay: u64 = match matches.get("-d") {
+        Some(0) => return Err("Error; '-d' needs the seconds between refresh".to_string()),
+        Some(d) => d,
+        None => 2,
+    };
+    let mut sort = match matches.value("-s") {
+        Some("cpu") | None => 'c',
+        Some("mem") => 'm',
+        Some("pid") => 'p',
+        Some("name") => 'n',
+        _ => return Err("Error; '-s' needs; cpu, mem, pid or name".to_string()),
+    };
+
+    // Read the keys in another thread, so the screen can be refreshed while
+    // waiting for them
+    let (sc, rc) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 1];
+        while let Ok(1) = stdin.read(&mut buffer) {
+            if sc.send(buffer[0]).is_err() || buffer[0] == b'q' {
+                break;
+            }
+        }
+    });
+
+    let ncpu = fs::read_to_string("/proc/stat")
+        .map(|d| d.lines().filter(|l| l.starts_with("cpu") && !l.starts_with("cpu ")).count())
+        .unwrap_or(1)
+        .max(1);
+    let mut last_cpu = top_cpu_ticks();
+    let mut last_procs: HashMap<u64, u64> = top_procs().iter().map(|e| (e.pid, e.ticks)).collect();
+    // Give some time to take the first difference
+    thread::sleep(Duration::from_millis(500));
+
+    loop {
+        let cpu = top_cpu_ticks();
+        let mut procs = top_procs();
+
+        let (dtotal, cpu_usage) = match (last_cpu, cpu) {
+            (Some((t0, i0)), Some((t1, i1))) if t1 > t0 => {
+                let dtotal = t1 - t0;
+                let didle = i1.saturating_sub(i0);
+                (dtotal, 100.0 * (dtotal.saturating_sub(didle)) as f64 / dtotal as f64)
+            }
+            _ => (0, 0.0),
+        };
+
+        for p in &mut procs {
+            let before = last_procs.get(&p.pid).copied().unwrap_or(p.ticks);
+            if dtotal > 0 {
+                // The total ticks are the sum of all CPUs, so 100% is one full core
+                p.cpu = 100.0 * ncpu as f64 * p.ticks.saturating_sub(before) as f64 / dtotal as f64;
+            }
+        }
+        last_cpu = cpu;
+        last_procs = procs.iter().map(|e| (e.pid, e.ticks)).collect();
+
+        match sort {
+            'm' => procs.sort_by_key(|e| std::cmp::Reverse(e.rss)),
+            'p' => procs.sort_by_key(|e| e.pid),
+            'n' => procs.sort_by(|a, b| a.name.cmp(&b.name)),
+            _ => procs.sort_by(|a, b| b.cpu.partial_cmp(&a.cpu).unwrap_or(std::cmp::Ordering::Equal)),
+        }
+
+        let meminfo = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        let memtotal = top_meminfo(&meminfo, "MemTotal:");
+        let memavailable = top_meminfo(&meminfo, "MemAvailable:");
+        let swaptotal = top_meminfo(&meminfo, "SwapTotal:");
+        let swapfree = top_meminfo(&meminfo, "SwapFree:");
+
+        let loadavg = fs::read_to_string("/proc/loadavg")
+            .map(|d| d.split_whitespace().take(3).collect::<Vec<&str>>().join(" "))
+            .unwrap_or_else(|_e| "-".to_string());
+        let uptime = match fs::read_to_string("/proc/uptime") {
+            Ok(d) => {
+                let secs = d.split('.').next().unwrap_or("0").parse::<u64>().unwrap_or(0);
+                format!("{}d {:02}:{:02}:{:02}", secs / 86400, (secs % 86400) / 3600, (secs % 3600) / 60, secs % 60)
+            }
+            Err(_e) => "-".to_string(),
+        };
+
+        let mut screen = String::new();
+        // Same escapes than "clear" builtin
+        screen += "\x1B[1;1H\x1B[2J";
+        screen += &format!("uptime {{ {uptime} }} load {{ {loadavg} }} tasks {{ {} }}\n", procs.len());
+        screen += &format!("cpu {{ {cpu_usage:.1}% }} threads {{ {ncpu} }}\n");
+        screen += &format!(
+            "memory {{ {} used / {} }} swap {{ {} used / {} }}\n\n",
+            ((memtotal - memavailable.min(memtotal)) * 1024).size_to_human(),
+            (memtotal * 1024).size_to_human(),
+            ((swaptotal - swapfree.min(swaptotal)) * 1024).size_to_human(),
+            (swaptotal * 1024).size_to_human()
+        );
+        screen += &format!("{}{:>7} {:>6} {:>6} {:>8}  NAME{}\n", color.cyan, "PID", "UID", "CPU%", "MEM", color.reset);
+        for p in procs.iter().take(number) {
+            screen += &format!(
+                "{:>7} {:>6} {:>6.1} {:>8}  {}\n",
+                p.pid,
+                p.uid,
+                p.cpu,
+                (p.rss * 1024).size_to_human(),
+                p.name
+            );
+        }
+        screen += "\n[c] cpu [m] memory [p] pid [n] name [q] quit; followed by enter\n";
+        print!("{screen}");
+        let _ = io::stdout().flush();
+
+        match rc.recv_timeout(Duration::from_secs(delay)) {
+            Ok(b'q') | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Ok(key) if b"cmpn".contains(&key) => sort = key as char,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+// Commands already found in PATH, used to run them
+static COMMANDS: Mutex<CommandCache> = Mutex::new(CommandCache::new());
+
+// Path of the command to run, from the saved ones or searched in PATH
+pub fn command_path(name: &str) -> io::Result<PathBuf> {
+    match COMMANDS.lock() {
+        Ok(mut d) => d.get(name),
+        Err(_e) => which(name),
+    }
+}
+
+// Removes a saved command, by the same name given to "command_path"
+pub fn forget_command(name: &str) {
+    if let Ok(mut d) = COMMANDS.lock() {
+        d.forget(name);
+    }
+}
+
+fn fwhich(input: &str) -> Result<String, String> {
+    let spec = ArgSpec::new("_which", "Show where is located the binary based in PATH environment variable.")
+        .flag("-a, --all", "show every match in PATH, not only the first one.")
+        .flag("-l, --list", "show the commands saved by the shell, as ravnkey.")
+        .flag("-r, --rehash", "forget the commands saved by the shell.")
+        .optional("binary", "");
+    let matches = match spec.parse(&input.split_whitespace().collect::<Vec<&str>>()) {
+        Ok(d) => d,
+        Err(ArgError::Help(d)) => return Ok(d),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if matches.flag("-r") || matches.flag("-l") {
+        let mut commands = match COMMANDS.lock() {
+            Ok(d) => d,
+            Err(_e) => return Err("Error reading the saved commands".to_string()),
+        };
+        if matches.flag("-r") {
+            commands.clear();
+        }
+        let mut document = RavnValue::Block(Vec::new());
+        for (name, path) in commands.entries() {
+            document.push(name, path.display().to_string().into());
+        }
+        return Ok(to_ravnkey(&document).trim_end().to_string());
+    }
+
+    let binary = match matches.positional("binary") {
+        Some(d) => d,
+        None => return Err("Few arguments; [options] [binary]".to_string()),
+    };
+    let result: Vec<PathBuf> = if matches.flag("-a") {
+        which_all(binary).map_err(|e| e.to_string())?
     } else {
-        return Err("Not found");
+        vec![which(binary).map_err(|e| e.to_string())?]
+    };
+    if result.is_empty() {
+        return Err(format!("{binary} not found in PATH"));
     }
+    Ok(result.iter().map(|e| e.display().to_string()).collect::<Vec<String>>().join("\n"))
 }
 
 ////////////////
@@ -1643,16 +2752,33 @@ pub fn rbuiltins(command: &str, b_arguments: String) -> Result<String, &str> {
             result = info();
             Ok(result)
         } else if command == "date" {
-            date()
+            match date(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("error getting the date")
+                }
+            }
         } else if command == "decodebase64" {
             match decodebase64(&b_arguments) {
                 Some(d) => Ok(d),
                 None => Err("error converting"),
             }
+        } else if command == "df" {
+            match disk_free(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error reading filesystems")
+                }
+            }
         } else if command == "du" {
             match disk_usage(&b_arguments) {
-                Some(d) => Ok(d),
-                None => Err("Error reading path"),
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error reading path")
+                }
             }
         } else if command == "echoraw" {
             result = echoraw(&b_arguments);
@@ -1660,6 +2786,46 @@ pub fn rbuiltins(command: &str, b_arguments: String) -> Result<String, &str> {
         } else if command == "expand" {
             result = expand(b_arguments);
             Ok(result)
+        } else if command == "elf" {
+            match elf(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error reading ELF file")
+                }
+            }
+        } else if command == "file" {
+            match file_type(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error reading file")
+                }
+            }
+        } else if command == "gunzip" {
+            match gunzip(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error decompressing file")
+                }
+            }
+        } else if command == "gzip" {
+            match gzip(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error compressing file")
+                }
+            }
+        } else if command == "hash" {
+            match hash(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error hashing files")
+                }
+            }
         } else if command == "head" {
             let _ = head(&b_arguments);
             Ok("".to_string())
@@ -1793,10 +2959,29 @@ pub fn rbuiltins(command: &str, b_arguments: String) -> Result<String, &str> {
         } else if command == "tail" {
             let _ = tail(&b_arguments);
             Ok("".to_string())
+        } else if command == "tar" {
+            match tar(&b_arguments) {
+                Ok(d) => Ok(d),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error working with tar archive")
+                }
+            }
+        } else if command == "top" {
+            match top(&b_arguments) {
+                Ok(()) => Ok("".to_string()),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Error running top")
+                }
+            }
         } else if command == "which" {
             match fwhich(&b_arguments) {
                 Ok(d) => Ok(d),
-                Err(_e) => Err("Not found"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Err("Not found")
+                }
             }
         } else if command == "clear" {
             let _ = clear();
@@ -234,9 +234,18 @@ pub fn aliases() -> HashMap<String,String> {
 	let mut lalias: HashMap<String,String> = HashMap::new();
 
 	ffile.read_to_string(&mut buffer).expect("Fail to open rune alias, check ~/.ravnos/rune_alias file");
-	for ddata in buffer.lines(){
-		let list = ddata.split("=").map(|e| e.trim()).collect::<Vec<&str>>();
-		lalias.insert(list[0].to_string(),list[1].to_string());
+	// "name = command" lines, split on the first '=' so the command can have more. Lines without it are
+	// skipped with a warning, one bad line does not lose the other aliases
+	for (number, ddata) in buffer.lines().enumerate(){
+		if ddata.trim().is_empty() || ddata.trim_start().starts_with('#') {
+			continue;
+		}
+		match ddata.split_once('=') {
+			Some((name, command)) if !name.trim().is_empty() => {
+				lalias.insert(name.trim().to_string(),command.trim().to_string());
+			},
+			_ => eprintln!("~/.ravnos/rune_alias line {}; expected 'name = command', skipped", number + 1),
+		}
 	}
 	lalias
 	
@@ -15,7 +15,6 @@
 
 // Process crate
 use std::process;
-use std::process::Stdio;
 
 // Colections crate
 use std::collections::HashMap;
@@ -26,6 +25,8 @@ use std::io::Write;
 
 use std::fs::{self,OpenOptions};
 
+use libcommand::{parse_argv, signal_name, Exit, Io, Spawn};
+
 // Import the files inside scope
 mod builtins;
 mod io_mods;
@@ -747,7 +748,8 @@ pub fn fcommand( input: &str, arguments: Vec<String>, stdin_data: Vec<&str>) ->
       stderr: String::new(),
     };
 
-    // Take the first, which is the binary to execute
+    // Take the first, which is the binary to execute; the saved paths are by it, not by the whole line
+    let name = input.split(' ').next().unwrap_or_default();
     let binn = {
         if input.chars().next().unwrap() == '_' {
             let builtin = input.split('_').collect::<Vec<&str>>()[1];
@@ -774,99 +776,56 @@ pub fn fcommand( input: &str, arguments: Vec<String>, stdin_data: Vec<&str>) ->
                     return init1;
                 },
             }
-        } else if !input.contains("/bin/"){
-				match builtins::rbuiltins( "which", (input.split(' ').map(|e| e.to_string()).collect::<Vec<String>>())[0].clone() ) {
-					Ok(d) => {
-						d.split(',').collect::<Vec<&str>>()[0].to_string()
-					},
-					Err(_e) => {
-                        init1.stderr = format!("Binary {:?} not found in PATH", input);
-						return init1;
-					},
-				}
-		} else {
-				input.split(' ').map(|e| e.to_string()).collect::<Vec<String>>()[0].to_string()
-		}
+        } else {
+            // Names with '/' are used as they are, the rest are searched in PATH once and saved
+            match builtins::command_path(name) {
+                Ok(d) => d,
+                Err(e) => {
+                    init1.stderr = format!("Binary {:?} not found; {e}", input);
+                    return init1;
+                },
+            }
+        }
     };
 
-    // Execute the "binn" binary with the arguments collected in "arguments"
-    let mut proc = process::Command::new( binn );
-    // Arg method inserts the arguments in the Command struct, because of that we can iterate adding it
-    for j in &arguments {
-        // Stdout configure process' stdout
-        // Stdio::piped connect parent and child processes
-        // Stderr configure process' stderr
-        // Stdio::piped connect parent and child processes
-        //proc.arg(j.trim()).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
-
-        // Stdio::inherit allows the child process to inherit the parent's file descriptors so that it can access the TTY terminal
-        proc.arg(j.trim()).stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit());
-    }
+    // The arguments were split by spaces, they are joined again to respect the quotes; "echo 'a b'"
+    let arguments = match parse_argv(&arguments.join(" ")) {
+        Ok(d) => d,
+        Err(e) => {
+            init1.stderr = format!("Bad arguments; {e}");
+            return init1;
+        },
+    };
 
-    if stdin_data.is_empty(){
-        // Spawn execute it
-        let nproc = match proc.spawn(){
-            Ok(d) => d,
-            Err(e) => {
-                init1.stderr = format!("Binary failed to execute; {e}");
-                return init1;
-            },
-        };
-        // Wait_with_output method waits to command finishes and collect output, return Output struct
-        let output = match nproc.wait_with_output(){
-            Ok(d) => d,
-            Err(e) => {
-                init1.stderr = format!("Failed to execute; {e}");
-                return init1;
-            },
-        };
-        init1.stdout = match std::str::from_utf8(&(output.stdout).clone()){
-            Ok(d) => d.to_string(),
-            Err(_e) => String::from("Error taking stdout in buffer"),
-        };
-        init1.stderr = match std::str::from_utf8(&(output.stderr).clone()){
-            Ok(d) => d.to_string(),
-            Err(_e) => String::from("Error taking stderr in buffer"),
-        };
-    } else {
-        // Spawn execute it
-        let mut nproc = match proc.spawn(){
-            Ok(d) => d,
-            Err(e) => {
-                init1.stderr = format!("Binary failed to execute; {e}");
-                return init1;
-            },
-        };
-        if let Some(ref mut nproc_stdin) = nproc.stdin {
-            for i in stdin_data {
-                // Shadowing to allow programs like grep work properly
-                let mut i = i.to_string();
-                i.push('\n');
-                match nproc_stdin.write_all(i.as_bytes()){
-                    Ok(_d) => (),
-                    Err(_e) => eprintln!("Error to write stdin"),
-                }
-            }
-        } else {
-            eprintln!("Error taking child's stdin");
+    // Without stdin data the child inherits the terminal, as interactive programs need
+    let stdin = if stdin_data.is_empty() { Io::Inherit } else { Io::Piped };
+    let mut child = match Spawn::new(name, &binn).args(&arguments).stdin(stdin).start() {
+        Ok(d) => d,
+        Err(e) => {
+            // The saved path can be old, it is searched again the next time
+            builtins::forget_command(name);
+            init1.stderr = format!("Binary failed to execute; {e}");
             return init1;
+        },
+    };
+    if let Some(child_stdin) = child.stdin() {
+        for i in stdin_data {
+            // Each line with its end, to allow programs like grep work properly
+            match child_stdin.write_all(i.as_bytes()).and_then(|_d| child_stdin.write_all(b"\n")) {
+                Ok(_d) => (),
+                Err(_e) => {
+                    eprintln!("Error to write stdin");
+                    break;
+                },
+            }
         }
-        // Wait_with_output method waits to command finishes and collect output, return Output struct
-        let output = match nproc.wait_with_output(){
-            Ok(d) => d,
-            Err(e) => {
-                init1.stderr = format!("Failed to execute; {e}");
-                return init1;
-            },
-        };
-        init1.stdout = match std::str::from_utf8(&(output.stdout).clone()){
-            Ok(d) => d.to_string(),
-            Err(_e) => String::from("Error taking stdout in buffer"),
-        };
-        init1.stderr = match std::str::from_utf8(&(output.stderr).clone()){
-            Ok(d) => d.to_string(),
-            Err(_e) => String::from("Error taking stderr in buffer"),
-        };
+    }
+    // Wait closes the stdin, so the child reads the end of file
+    match child.wait() {
+        // The exit code is the child's business, but a crash is shown as the shells do
+        Ok(Exit::Signal(d)) => init1.stderr = format!("{name} killed by signal {d} ({})", signal_name(d)),
+        Ok(_d) => (),
+        Err(e) => init1.stderr = format!("Failed to execute; {e}"),
     }
     init1
 }
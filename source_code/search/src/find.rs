@@ -0,0 +1,630 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! "search --find", files by their metadata as find.
+//!
+//! The arguments are the paths and then an expression of tests; "-name '*.rs' -size +1k", "-type d -o -type l",
+//! "! ( -user root -perm /022 )". Tests one after the other must all be true, "-o" is an OR and has less priority.
+//! The actions are part of the expression as in find; "-print" shows the path and "-exec" runs a command, only
+//! where the tests before them are true ("-name '*.o' -exec rm {} ; -o -print"). Without actions each match
+//! is printed as a ravnkey record with its metadata, or as JSON with "--json".
+
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libcommand::Exit;
+use libregex::{Regex, RegexBuilder};
+use libstream::{Epoch, JsonValue, RavnValue, RavnWriter, Stream, WalkEntry, Walker};
+
+use crate::{print_json, record};
+
+// Comparison of a number, as sizes in bytes and ages in seconds
+#[derive(Debug)]
+enum Compare {
+    Less(u64),
+    More(u64),
+    // From the first to the second, not included
+    Range(u64, u64),
+}
+
+// How "N" with a unit is compared
+#[derive(Clone, Copy, Debug)]
+enum Round {
+    // Ages; "3" days is from 3 to 4 days
+    Down,
+    // Sizes as find; rounded up to the unit, "1k" is from 1 to 1024 bytes and "-1M" only empty files
+    Up,
+}
+
+impl Compare {
+    // "+N" more, "-N" less, "N" only that and "N..M" from N to M; with "units" for the suffixes
+    fn parse(text: &str, units: &[(char, u64)], default: u64, round: Round) -> Result<Compare, String> {
+        let number = |text: &str| -> Result<(u64, u64), String> {
+            let (digits, unit) = match text.char_indices().last() {
+                Some((i, c)) if c.is_ascii_alphabetic() => match units.iter().find(|e| e.0 == c) {
+                    Some(d) => (&text[..i], d.1),
+                    None => return Err(format!("unknown unit '{c}' in '{text}'")),
+                },
+                _ => (text, default),
+            };
+            match digits.parse::<u64>() {
+                Ok(d) => Ok((d.saturating_mul(unit), unit)),
+                Err(_e) => Err(format!("'{text}' is not a number")),
+            }
+        };
+        // First value of "N"; rounded up, "2k" starts after "1k"
+        let low = |(value, unit): (u64, u64)| match round {
+            Round::Down => value,
+            Round::Up => value.saturating_sub(unit).saturating_add(1).min(value),
+        };
+        if let Some(d) = text.strip_prefix('+') {
+            let (value, unit) = number(d)?;
+            // Ages are whole units as find, "+1" day is 2 days or more
+            return Ok(Compare::More(match round {
+                Round::Down => value.saturating_add(unit) - 1,
+                Round::Up => value,
+            }));
+        }
+        if let Some(d) = text.strip_prefix('-') {
+            return Ok(Compare::Less(low(number(d)?)));
+        }
+        if let Some((from, to)) = text.split_once("..") {
+            return Ok(Compare::Range(low(number(from)?), number(to)?.0.saturating_add(1)));
+        }
+        let (value, unit) = number(text)?;
+        let end = match round {
+            Round::Down => value.saturating_add(unit),
+            Round::Up => value.saturating_add(1),
+        };
+        Ok(Compare::Range(low((value, unit)), end))
+    }
+
+    fn holds(&self, value: u64) -> bool {
+        match self {
+            Compare::Less(d) => value < *d,
+            Compare::More(d) => value > *d,
+            Compare::Range(from, to) => value >= *from && value < *to,
+        }
+    }
+}
+
+// Blocks of 512 bytes by default, as find
+const SIZE_UNITS: [(char, u64); 6] = [
+    ('c', 1),
+    ('w', 2),
+    ('b', 512),
+    ('k', 1024),
+    ('M', 1024 * 1024),
+    ('G', 1024 * 1024 * 1024),
+];
+const TIME_UNITS: [(char, u64); 5] = [('s', 1), ('m', 60), ('h', 3600), ('d', 86400), ('w', 604800)];
+
+#[derive(Debug)]
+enum Perm {
+    // "644", only those bits
+    Exact(u32),
+    // "-644", all those bits
+    All(u32),
+    // "/022", any of those bits
+    Any(u32),
+    // "rw-r--r--", as "permission_to_human" shows them
+    Human(String),
+}
+
+impl Perm {
+    fn parse(text: &str) -> Result<Perm, String> {
+        let octal = |text: &str| u32::from_str_radix(text, 8).map_err(|_e| format!("'{text}' is not an octal mode"));
+        if let Some(d) = text.strip_prefix('-') {
+            return Ok(Perm::All(octal(d)?));
+        }
+        if let Some(d) = text.strip_prefix('/') {
+            return Ok(Perm::Any(octal(d)?));
+        }
+        if text.chars().all(|e| e.is_ascii_digit()) {
+            return Ok(Perm::Exact(octal(text)?));
+        }
+        let human = compact(text);
+        if human.len() != 9 || !human.chars().all(|e| "rwx-".contains(e)) {
+            return Err(format!("'{text}' is not a mode, as '644', '-111', '/022' or 'rw-r--r--'"));
+        }
+        Ok(Perm::Human(human))
+    }
+
+    fn holds(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match self {
+            Perm::Exact(d) => mode == *d,
+            Perm::All(d) => mode & d == *d,
+            Perm::Any(d) => mode & d != 0,
+            Perm::Human(d) => compact(&human_permissions(mode)) == *d,
+        }
+    }
+}
+
+// "rw-, r--, r--" as "rw-r--r--"
+fn compact(text: &str) -> String {
+    text.chars().filter(|e| *e != ',' && *e != ' ').collect()
+}
+
+// Permissions of owner, group and others, as "rw-, r--, r--"
+fn human_permissions(mode: u32) -> String {
+    format!("{:06o}", mode & 0o777).permission_to_human().concat().trim().to_string()
+}
+
+#[derive(Debug)]
+enum Expr {
+    Name(Regex),
+    Path(Regex),
+    Type(char),
+    Size(Compare),
+    Modified(Compare),
+    Accessed(Compare),
+    User(u32),
+    Group(u32),
+    Perm(Perm),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    True,
+    // Actions, true when they work
+    Print,
+    // Command and arguments, "{}" is the path
+    Exec(Vec<String>),
+}
+
+// Data of the tests which does not change between the entries
+struct Context {
+    now: u64,
+}
+
+// What the actions did, for the exit code and to stop
+#[derive(Default)]
+struct Effects {
+    failed: bool,
+    // The output was closed, as by "head"
+    closed: bool,
+}
+
+impl Expr {
+    // "-o" and the tests together are evaluated in order and stop as soon as the result is known, so an action
+    // only runs where the tests before it are true
+    fn holds(&self, entry: &WalkEntry, context: &Context, effects: &mut Effects) -> bool {
+        let metadata = &entry.metadata;
+        let age = |time: i64| context.now.saturating_sub(time.max(0) as u64);
+        match self {
+            Expr::Name(d) => d.is_match(entry.file_name().as_encoded_bytes()),
+            Expr::Path(d) => d.is_match(entry.path.as_os_str().as_encoded_bytes()),
+            Expr::Type(d) => type_letter(metadata) == *d,
+            Expr::Size(d) => d.holds(metadata.size()),
+            Expr::Modified(d) => d.holds(age(metadata.mtime())),
+            Expr::Accessed(d) => d.holds(age(metadata.atime())),
+            Expr::User(d) => metadata.uid() == *d,
+            Expr::Group(d) => metadata.gid() == *d,
+            Expr::Perm(d) => d.holds(metadata.mode()),
+            Expr::Not(d) => !d.holds(entry, context, effects),
+            Expr::And(a, b) => a.holds(entry, context, effects) && b.holds(entry, context, effects),
+            Expr::Or(a, b) => a.holds(entry, context, effects) || b.holds(entry, context, effects),
+            Expr::True => true,
+            Expr::Print => {
+                effects.closed |= writeln!(io::stdout(), "{}", entry.path.display()).is_err();
+                true
+            }
+            Expr::Exec(d) => {
+                let success = exec(d, &entry.path.display().to_string());
+                effects.failed |= !success;
+                success
+            }
+        }
+    }
+
+    fn has_action(&self) -> bool {
+        match self {
+            Expr::Print | Expr::Exec(_) => true,
+            Expr::Not(d) => d.has_action(),
+            Expr::And(a, b) | Expr::Or(a, b) => a.has_action() || b.has_action(),
+            _ => false,
+        }
+    }
+}
+
+// Letter of the type as in "-type"
+fn type_letter(metadata: &Metadata) -> char {
+    let kind = metadata.file_type();
+    if kind.is_dir() {
+        'd'
+    } else if kind.is_symlink() {
+        'l'
+    } else if kind.is_block_device() {
+        'b'
+    } else if kind.is_char_device() {
+        'c'
+    } else if kind.is_fifo() {
+        'p'
+    } else if kind.is_socket() {
+        's'
+    } else {
+        'f'
+    }
+}
+
+fn type_name(letter: char) -> &'static str {
+    match letter {
+        'd' => "directory",
+        'l' => "symlink",
+        'b' => "block device",
+        'c' => "character device",
+        'p' => "fifo",
+        's' => "socket",
+        _ => "file",
+    }
+}
+
+// Names of users and groups, from "/etc/passwd" and "/etc/group"
+struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    fn read() -> Names {
+        // "name:password:id:..."
+        let read = |path: &str| -> HashMap<u32, String> {
+            let text = fs::read_to_string(path).unwrap_or_default();
+            text.lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split(':').collect();
+                    let id = fields.get(2)?.parse().ok()?;
+                    Some((id, fields[0].to_string()))
+                })
+                .collect()
+        };
+        Names {
+            users: read("/etc/passwd"),
+            groups: read("/etc/group"),
+        }
+    }
+
+    // The id of a name, or the number as it is
+    fn id(names: &HashMap<u32, String>, name: &str) -> Option<u32> {
+        name.parse()
+            .ok()
+            .or_else(|| names.iter().find(|e| e.1 == name).map(|e| *e.0))
+    }
+
+    fn name(names: &HashMap<u32, String>, id: u32) -> String {
+        names.get(&id).cloned().unwrap_or_else(|| id.to_string())
+    }
+}
+
+// Recursive descent over the arguments of the expression
+struct Parser<'a> {
+    tokens: &'a [String],
+    at: usize,
+    names: &'a Names,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.at).map(|e| e.as_str())
+    }
+
+    fn value(&mut self, test: &str) -> Result<&str, String> {
+        self.at += 1;
+        match self.tokens.get(self.at) {
+            Some(d) => {
+                self.at += 1;
+                Ok(d)
+            }
+            None => Err(format!("{test} needs a value")),
+        }
+    }
+
+    // or := and ("-o" and)*
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while matches!(self.peek(), Some("-o" | "-or")) {
+            self.at += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    // and := not (["-a"] not)*
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        loop {
+            match self.peek() {
+                None | Some("-o" | "-or" | ")") => return Ok(expr),
+                Some("-a" | "-and") => self.at += 1,
+                Some(_) => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+    }
+
+    // not := ("!" | "-not") not | "(" or ")" | test
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some("!" | "-not") => {
+                self.at += 1;
+                Ok(Expr::Not(Box::new(self.not()?)))
+            }
+            Some("(") => {
+                self.at += 1;
+                let expr = self.or()?;
+                if self.peek() != Some(")") {
+                    return Err("missing ')'".to_string());
+                }
+                self.at += 1;
+                Ok(expr)
+            }
+            Some(_) => self.test(),
+            None => Err("expression ends after an operator".to_string()),
+        }
+    }
+
+    fn test(&mut self) -> Result<Expr, String> {
+        let test = self.peek().unwrap_or_default().to_string();
+        let names = self.names;
+        let glob = |glob: &str, ignore_case: bool| {
+            let pattern = libregex::glob_to_regex(glob);
+            RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| format!("{test} '{glob}'; {e}"))
+        };
+        let expr = match test.as_str() {
+            "-name" => glob(self.value(&test)?, false).map(Expr::Name)?,
+            "-iname" => glob(self.value(&test)?, true).map(Expr::Name)?,
+            "-regex" => {
+                let value = self.value(&test)?;
+                Expr::Path(Regex::new(value).map_err(|e| format!("{test} '{value}'; {e}"))?)
+            }
+            "-type" => {
+                let value = self.value(&test)?;
+                match value {
+                    "f" | "d" | "l" | "b" | "c" | "p" | "s" => Expr::Type(value.chars().next().unwrap_or('f')),
+                    _ => return Err(format!("-type '{value}' is not one of f, d, l, b, c, p or s")),
+                }
+            }
+            "-size" => Expr::Size(Compare::parse(self.value(&test)?, &SIZE_UNITS, 512, Round::Up)?),
+            "-mtime" => Expr::Modified(Compare::parse(self.value(&test)?, &TIME_UNITS, 86400, Round::Down)?),
+            "-atime" => Expr::Accessed(Compare::parse(self.value(&test)?, &TIME_UNITS, 86400, Round::Down)?),
+            "-user" => {
+                let value = self.value(&test)?;
+                Expr::User(Names::id(&names.users, value).ok_or(format!("-user '{value}' is not a user"))?)
+            }
+            "-group" => {
+                let value = self.value(&test)?;
+                Expr::Group(Names::id(&names.groups, value).ok_or(format!("-group '{value}' is not a group"))?)
+            }
+            "-perm" => Expr::Perm(Perm::parse(self.value(&test)?)?),
+            "-print" => {
+                self.at += 1;
+                Expr::Print
+            }
+            "-exec" => {
+                self.at += 1;
+                let command: Vec<String> = self.tokens[self.at..].iter().take_while(|e| *e != ";").cloned().collect();
+                // The command and the ';'
+                self.at += command.len() + 1;
+                if command.is_empty() || self.at > self.tokens.len() {
+                    return Err("-exec needs a command, ended by ';'".to_string());
+                }
+                Expr::Exec(command)
+            }
+            "" => return Err("missing test".to_string()),
+            _ => return Err(format!("unknown test '{test}'")),
+        };
+        Ok(expr)
+    }
+}
+
+// Options which are not part of the expression
+const OPTIONS: [&str; 4] = ["-maxdepth", "-mindepth", "-L", "--json"];
+
+/// What "search --find" does with its arguments.
+struct Find {
+    paths: Vec<String>,
+    expr: Expr,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    json: bool,
+}
+
+impl Find {
+    // Takes an option and its value; false if "token" is not an option
+    fn option<I: Iterator<Item = String>>(&mut self, token: &str, tokens: &mut I) -> Result<bool, String> {
+        let mut depth = |option: &str| -> Result<usize, String> {
+            match tokens.next().map(|e| e.parse::<usize>()) {
+                Some(Ok(d)) => Ok(d),
+                _ => Err(format!("{option} needs a number")),
+            }
+        };
+        match token {
+            "-maxdepth" => self.max_depth = Some(depth("-maxdepth")?),
+            "-mindepth" => self.min_depth = depth("-mindepth")?,
+            "-L" => self.follow_links = true,
+            "--json" => self.json = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn parse(tokens: Vec<String>, names: &Names) -> Result<Find, String> {
+        let mut find = Find {
+            paths: Vec::new(),
+            expr: Expr::True,
+            min_depth: 0,
+            max_depth: None,
+            follow_links: false,
+            json: false,
+        };
+        // The paths are first, until the expression; options can be before them, "-L [path]"
+        let mut tokens = tokens.into_iter().peekable();
+        let leading = |e: &String| OPTIONS.contains(&e.as_str()) || !(e.starts_with('-') || e == "!" || e == "(");
+        while let Some(token) = tokens.next_if(leading) {
+            if !find.option(&token, &mut tokens)? {
+                find.paths.push(token);
+            }
+        }
+        if find.paths.is_empty() {
+            find.paths.push(".".to_string());
+        }
+
+        // Options can be anywhere, the rest is the expression
+        let mut rest: Vec<String> = Vec::new();
+        while let Some(token) = tokens.next() {
+            if find.option(&token, &mut tokens)? {
+                continue;
+            }
+            match token.as_str() {
+                // Its command can have options of find, as "-L"
+                "-exec" => {
+                    rest.push(token);
+                    for d in tokens.by_ref() {
+                        let end = d == ";";
+                        rest.push(d);
+                        if end {
+                            break;
+                        }
+                    }
+                }
+                _ => rest.push(token),
+            }
+        }
+
+        if !rest.is_empty() {
+            let mut parser = Parser {
+                tokens: &rest,
+                at: 0,
+                names,
+            };
+            find.expr = parser.or()?;
+            if let Some(d) = parser.peek() {
+                return Err(format!("unexpected '{d}'"));
+            }
+        }
+        Ok(find)
+    }
+}
+
+// Metadata of a match as ravnkey
+fn ravnkey_record(entry: &WalkEntry, names: &Names) -> RavnValue {
+    let metadata = &entry.metadata;
+    let mut record = RavnValue::Block(Vec::new());
+    record.push("type", type_name(type_letter(metadata)).into());
+    record.push("size", metadata.size().to_string().into());
+    record.push("permissions", human_permissions(metadata.mode()).into());
+    record.push("mode", format!("{:04o}", metadata.mode() & 0o7777).into());
+    record.push("owner", Names::name(&names.users, metadata.uid()).into());
+    record.push("group", Names::name(&names.groups, metadata.gid()).into());
+    record.push("modified", metadata.mtime().epoch_to_human().into());
+    record.push("accessed", metadata.atime().epoch_to_human().into());
+    if entry.symlink {
+        if let Ok(d) = fs::read_link(&entry.path) {
+            record.push("target", d.display().to_string().into());
+        }
+    }
+    record
+}
+
+fn json_record(path: &str, metadata: &RavnValue) -> JsonValue {
+    let mut fields = vec![("path", path.into())];
+    for (key, value) in metadata.entries().unwrap_or_default() {
+        let value: JsonValue = match (key.as_str(), value.as_text()) {
+            ("size", Some(d)) => d.parse::<i64>().map(JsonValue::from).unwrap_or_else(|_e| d.into()),
+            (_, Some(d)) => d.into(),
+            _ => continue,
+        };
+        fields.push((key.as_str(), value));
+    }
+    record(fields)
+}
+
+// Runs the command of "-exec" with the path, returns if it ended well
+fn exec(command: &[String], path: &str) -> bool {
+    let arguments: Vec<String> = command[1..].iter().map(|e| e.replace("{}", path)).collect();
+    match Command::new(&command[0]).args(&arguments).status() {
+        Ok(d) if d.success() => true,
+        Ok(d) => {
+            eprintln!("{} with {path}; {}", command[0], Exit::from(d));
+            false
+        }
+        Err(e) => {
+            eprintln!("{}; {e}", command[0]);
+            false
+        }
+    }
+}
+
+/// Runs "search --find" with the arguments after it, returns the exit code.
+pub fn find(tokens: Vec<String>, json_output: bool) -> i32 {
+    let names = Names::read();
+    let mut find = match Find::parse(tokens, &names) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error in find expression; {e}");
+            return 2;
+        }
+    };
+    find.json |= json_output;
+    let context = Context {
+        now: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |e| e.as_secs()),
+    };
+
+    let actions = find.expr.has_action();
+    let mut effects = Effects::default();
+    let mut writer = RavnWriter::new(io::stdout().lock());
+    let mut results: Vec<JsonValue> = Vec::new();
+    for path in &find.paths {
+        let walker = Walker::new(path)
+            .sorted(true)
+            .follow_links(find.follow_links)
+            .max_depth(find.max_depth);
+        for entry in walker {
+            let entry = match entry {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("{e}");
+                    effects.failed = true;
+                    continue;
+                }
+            };
+            if entry.depth < find.min_depth || !find.expr.holds(&entry, &context, &mut effects) || actions {
+                if effects.closed {
+                    return i32::from(effects.failed);
+                }
+                continue;
+            }
+            let name = entry.path.display().to_string();
+            if find.json {
+                results.push(json_record(&name, &ravnkey_record(&entry, &names)));
+            } else if writer.value(&name, &ravnkey_record(&entry, &names)).is_err() {
+                return i32::from(effects.failed);
+            }
+        }
+    }
+    if find.json && !actions {
+        print_json(results);
+    }
+    i32::from(effects.failed)
+}
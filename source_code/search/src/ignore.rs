@@ -0,0 +1,175 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! ".ravnignore" files, the paths that "search -r" does not read.
+//!
+//! One glob per line, as ".gitignore"; empty lines and lines starting with '#' are skipped.
+//! A glob without '/' is compared with the name of the entries in that directory and below it,
+//! with '/' it is compared with the path from the directory of the file, and "**" is any number of directories.
+//! A glob ending in '/' only ignores directories, one starting with '!' includes again what an earlier glob ignored.
+//! The files of the directories below are read after, so their globs are checked later and win.
+
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use libregex::Regex;
+use libstream::WalkEntry;
+
+/// Name of the ignore files.
+pub const IGNORE_FILE: &str = ".ravnignore";
+
+enum Segment {
+    // "**"
+    Any,
+    Glob(Regex),
+}
+
+struct Rule {
+    segments: Vec<Segment>,
+    anchored: bool,
+    negate: bool,
+    dir_only: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Result<Option<Rule>, String> {
+        let mut glob = line.trim_end();
+        if glob.is_empty() || glob.starts_with('#') {
+            return Ok(None);
+        }
+        let negate = glob.starts_with('!');
+        if negate {
+            glob = &glob[1..];
+        }
+        let dir_only = glob.ends_with('/');
+        glob = glob.trim_end_matches('/');
+        let anchored = glob.contains('/');
+        glob = glob.trim_start_matches('/');
+        if glob.is_empty() {
+            return Ok(None);
+        }
+
+        let mut segments = Vec::new();
+        for segment in glob.split('/').filter(|e| !e.is_empty()) {
+            if segment == "**" {
+                segments.push(Segment::Any);
+            } else {
+                segments.push(Segment::Glob(Regex::from_glob(segment).map_err(|e| format!("{segment}; {e}"))?));
+            }
+        }
+        Ok(Some(Rule {
+            segments,
+            anchored,
+            negate,
+            dir_only,
+        }))
+    }
+
+    // "names" is the path from the directory of the ignore file
+    fn matches(&self, names: &[&[u8]], dir: bool) -> bool {
+        if self.dir_only && !dir {
+            return false;
+        }
+        if self.anchored {
+            return matches_segments(&self.segments, names);
+        }
+        match names.last() {
+            Some(d) => matches_segments(&self.segments, &[d]),
+            None => false,
+        }
+    }
+}
+
+fn matches_segments(segments: &[Segment], names: &[&[u8]]) -> bool {
+    match segments.split_first() {
+        None => names.is_empty(),
+        Some((Segment::Any, rest)) => (0..=names.len()).any(|i| matches_segments(rest, &names[i..])),
+        Some((Segment::Glob(regex), rest)) => match names.split_first() {
+            Some((name, tail)) => regex.is_match(*name) && matches_segments(rest, tail),
+            None => false,
+        },
+    }
+}
+
+// Rules of the ignore file of a directory
+struct Frame {
+    depth: usize,
+    rules: Vec<Rule>,
+}
+
+/// Rules of the directories from the root of the walk to the current one.
+pub struct Ignores {
+    enabled: bool,
+    frames: Vec<Frame>,
+}
+
+impl Ignores {
+    pub fn new(enabled: bool) -> Ignores {
+        Ignores {
+            enabled,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Reads the ignore file of a directory given by the walker, its rules are used for the entries below it.
+    pub fn enter(&mut self, dir: &Path, depth: usize) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let path = dir.join(IGNORE_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("{}; {e}", path.display())),
+        };
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            match Rule::parse(line) {
+                Ok(Some(d)) => rules.push(d),
+                Ok(None) => {}
+                Err(e) => return Err(format!("{}; line {}; {e}", path.display(), number + 1)),
+            }
+        }
+        if !rules.is_empty() {
+            self.frames.push(Frame { depth, rules });
+        }
+        Ok(())
+    }
+
+    /// If the entry is ignored. The entries must come in the order of the walker.
+    pub fn is_ignored(&mut self, entry: &WalkEntry) -> bool {
+        // Rules of the directories the walk already left
+        while self.frames.last().is_some_and(|e| e.depth >= entry.depth) {
+            self.frames.pop();
+        }
+        if self.frames.is_empty() {
+            return false;
+        }
+        let names: Vec<&[u8]> = entry.path.iter().map(|e| e.as_bytes()).collect();
+        let dir = entry.is_dir();
+        let mut ignored = false;
+        for frame in &self.frames {
+            // The path from the directory of the frame, the last names
+            let from = names.len().saturating_sub(entry.depth - frame.depth);
+            for rule in &frame.rules {
+                if ignored == rule.negate && rule.matches(&names[from..], dir) {
+                    ignored = !rule.negate;
+                }
+            }
+        }
+        ignored
+    }
+}
@@ -20,17 +20,287 @@ use std::env;
 use std::process;
 
 // Stdinput and stdoutput read
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, Read, Write};
 
 // File lib
 use std::fs::File;
 
 // RavnOS libraries
 extern crate libconfarg;
+extern crate libregex;
 extern crate libstream;
 
-use libconfarg::RavnArguments;
-use libstream::{file_filter, getprocs, binary_search, Stream};
+mod find;
+mod ignore;
+mod recursive;
+
+use libconfarg::{ArgSpec, SearchConfiguration, ValueType};
+use libregex::{escape, Regex, RegexBuilder, RegexError};
+use libstream::{getprocs, find_bytes, ByteSearch, BytePattern, Colors, parse_ravnkey, ravnkey_to_json, to_json_pretty, JsonValue, LineMatcher, LineSearch, RavnQuery, RavnSchema, RavnValue, RavnWriter, Severity, Walker};
+
+// String to search; as is, or a regular expression with "-E", "-i" or "-w"
+#[derive(Clone)]
+enum Pattern {
+    Text(String),
+    // With "-w" only the matches which are whole words count
+    Regex(Regex, bool),
+}
+
+impl Pattern {
+    fn new(text: &str, config: &SearchConfiguration) -> Result<Pattern, RegexError> {
+        if !config.regex && !config.ignore_case && !config.word {
+            return Ok(Pattern::Text(text.to_string()));
+        }
+        let pattern = if config.regex { text.to_string() } else { escape(text) };
+        let regex = RegexBuilder::new(&pattern).case_insensitive(config.ignore_case).build()?;
+        Ok(Pattern::Regex(regex, config.word))
+    }
+
+    // Byte offset of the first match in the line
+    fn find(&self, line: &[u8]) -> Option<usize> {
+        match self {
+            Pattern::Text(d) => find_bytes(line, d.as_bytes()),
+            Pattern::Regex(d, false) => d.find(line).map(|e| e.start),
+            Pattern::Regex(d, true) => d.find_iter(line).find(|e| whole_word(line, e.start, e.end)).map(|e| e.start),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        self.find(text.as_bytes()).is_some()
+    }
+
+    // Ranges of all the matches in the line, to highlight them
+    fn matches(&self, line: &[u8]) -> Vec<(usize, usize)> {
+        match self {
+            Pattern::Text(d) if d.is_empty() => Vec::new(),
+            Pattern::Text(d) => {
+                let mut found = Vec::new();
+                let mut at = 0;
+                while let Some(i) = find_bytes(&line[at..], d.as_bytes()) {
+                    found.push((at + i, at + i + d.len()));
+                    at += i + d.len();
+                }
+                found
+            }
+            Pattern::Regex(d, word) => d
+                .find_iter(line)
+                .filter(|e| !e.is_empty() && (!word || whole_word(line, e.start, e.end)))
+                .map(|e| (e.start, e.end))
+                .collect(),
+        }
+    }
+
+    // Lines of the reader with the options of the line search
+    fn lines<R: BufRead>(&self, reader: R, config: &SearchConfiguration) -> LineSearch<R, &Pattern> {
+        LineSearch::new(reader, self)
+            .invert(config.invert)
+            .context(config.before_context, config.after_context)
+            .max_count(config.max_count)
+    }
+
+    fn file_lines(&self, path: &str, config: &SearchConfiguration) -> io::Result<LineSearch<BufReader<File>, &Pattern>> {
+        Ok(self.lines(BufReader::new(File::open(path)?), config))
+    }
+}
+
+impl LineMatcher for &Pattern {
+    fn find(&mut self, line: &[u8]) -> Option<usize> {
+        Pattern::find(self, line)
+    }
+}
+
+// As "-w" in grep; the match is not preceded or followed by letters, digits or '_'
+fn whole_word(line: &[u8], start: usize, end: usize) -> bool {
+    let word = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+    let before = start > 0 && word(&line[start - 1]);
+    let after = line.get(end).is_some_and(word);
+    !before && !after
+}
+
+// The line with the matches in red
+fn highlight(line: &[u8], pattern: &Pattern, colors: &Colors) -> String {
+    let mut text = String::new();
+    let mut at = 0;
+    for (start, end) in pattern.matches(line) {
+        text += &String::from_utf8_lossy(&line[at..start]);
+        text += colors.red;
+        text += &String::from_utf8_lossy(&line[start..end]);
+        text += colors.reset;
+        at = end;
+    }
+    text += &String::from_utf8_lossy(&line[at..]);
+    text
+}
+
+// Prints the lines found in one input as grep does, "name" is shown before each one when there are several inputs.
+// Returns the number of matching lines.
+// "shown" tells if lines of other file were printed before, to separate them with context
+fn print_lines<R: BufRead>(name: &str, show_name: bool, lines: LineSearch<R, &Pattern>, pattern: &Pattern, config: &SearchConfiguration, shown: &mut bool) -> u64 {
+    let colors = Colors::new();
+    let paint = |color: &str, text: &str| -> String {
+        if config.color {
+            format!("{color}{text}{}", colors.reset)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = io::stdout().lock();
+    let mut count = 0;
+    let mut last: Option<u64> = None;
+    for line in lines {
+        let line = match line {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{name}; {e}");
+                break;
+            }
+        };
+        if !line.context {
+            count += 1;
+        }
+        if config.files_with_matches {
+            if count > 0 {
+                let _ = writeln!(out, "{}", paint(colors.green, name));
+                return count;
+            }
+            continue;
+        }
+        if config.count {
+            continue;
+        }
+
+        // With context, the groups of lines which are not together are separated, as the ones of each file
+        let context = config.before_context > 0 || config.after_context > 0;
+        let apart = match last {
+            Some(d) => line.number > d + 1,
+            None => *shown,
+        };
+        if context && apart {
+            let _ = writeln!(out, "{}", paint(colors.cyan, "--"));
+        }
+        last = Some(line.number);
+        *shown = true;
+
+        let separator = paint(colors.cyan, if line.context { "-" } else { ":" });
+        let mut prefix = String::new();
+        if show_name {
+            prefix = prefix + &paint(colors.green, name) + &separator;
+        }
+        if config.line_number {
+            prefix = prefix + &paint(colors.yellow, &line.number.to_string()) + &separator;
+        }
+        let text = if config.color && !line.context && !config.invert {
+            highlight(&line.bytes, pattern, &colors)
+        } else {
+            line.text().into_owned()
+        };
+        let _ = writeln!(out, "{prefix}{text}");
+    }
+    if config.count {
+        if show_name {
+            let _ = writeln!(out, "{}{}{count}", paint(colors.green, name), paint(colors.cyan, ":"));
+        } else {
+            let _ = writeln!(out, "{count}");
+        }
+    }
+    count
+}
+
+// Results of "--json", printed as one array
+fn print_json(results: Vec<JsonValue>) {
+    print!("{}", to_json_pretty(&JsonValue::Array(results)));
+}
+
+fn record(fields: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+// Options of search, the text of "--help" is written from them
+fn spec() -> ArgSpec {
+    ArgSpec::new("search", "Search a string in files, directories, environment variables, processes, stdin or ravnkey data.")
+        .flag("-b", "search inside binary.")
+        .flag("--offsets", "with '-b', show the offset in bytes of each match, overlapping ones too.")
+        .flag("--hex", "with '-b', the string is bytes in hexadecimal and '??' is any byte; 'de ad ?? ef'.")
+        .flag("-d", "search the string in directories' name.")
+        .flag("-e", "search the string in environment variables data.")
+        .flag("-f", "search the string inside file.")
+        .flag("-p", "search the string in system processes.")
+        .flag("-r", "search recursively the string in directories' name and file's data.")
+        .flag("-s", "search the string in stdin.")
+        .flag(
+            "-E",
+            "the string is a regular expression, with '-f', '-s', '-r', '-d', '-e' and '-p'.
+            Syntax; '.', '[a-z]', '[^0-9]', '\\d', '\\w', '\\s', '^', '$', '\\b', '(...)', 'a|b',
+            '*', '+', '?', '{n,m}' and '(?i)' to ignore case.",
+        )
+        .flag("-i", "ignore case.")
+        .flag("-w", "match only whole words.")
+        .flag("-v", "show the lines which do not match.")
+        .flag("-n", "show the line number before each line.")
+        .flag("-c", "show only the number of matching lines.")
+        .flag("-l", "show only the names of the files with matches.")
+        .option("-m", "number", ValueType::Unsigned, "stop after [number] matching lines in each file.")
+        .option("-A", "number", ValueType::Unsigned, "show [number] lines after each match.")
+        .option("-B", "number", ValueType::Unsigned, "show [number] lines before each match.")
+        .option(
+            "-C",
+            "number",
+            ValueType::Unsigned,
+            "show [number] lines around each match.
+            Context lines use '-' after the file and number instead of ':', groups are separated by '--'.",
+        )
+        .flag(
+            "--color",
+            "highlight the matches, file names and line numbers.
+            With '-f', '-s' and '-r'; '-i', '-w' and '-v' also with '-d', '-e' and '-p'.",
+        )
+        .option("-j", "number", ValueType::Unsigned, "with '-r', read files in [number] threads, by default one per core.")
+        .flag(
+            "--sort",
+            "with '-r', show the results in the order of the directories, sorted by name,
+            instead of as the threads finish them.",
+        )
+        .flag("-a", "with '-r', search also binary files, the ones with a NUL byte in the first 8 KiB.")
+        .flag(
+            "--no-ignore",
+            "with '-r', do not skip the paths in '.ravnignore' files. Each line of those
+            is a glob as in '.gitignore'; 'target/', '*.o', '/build', 'docs/**/*.pdf', '!keep.o'.",
+        )
+        .flag(
+            "-k, -rk",
+            "search the data from key paths in stdin. Take the data and key with sintax; [key] { [data] }
+            each [String] is a path; 'services.sshd.binary', 'services.*[user=root].binary',
+            'ports[0]', 'service#1', '**.binary'. Several paths can be given.",
+        )
+        .flag(
+            "--ravnkey",
+            "with '-k' show each result as ravnkey, '[path] { [data] }', instead of only the data.
+            with '--check' and '--schema' show each problem as ravnkey.",
+        )
+        .flag("--json", "with '-k', '-r', '-e', '-b --offsets', '--check' and '--schema' show the results as a JSON array.")
+        .flag("--check", "check the syntax of the ravnkey files, [String] is the first file.")
+        .flag(
+            "--schema",
+            "validate the ravnkey files with the schema in [String]; types, required keys, unknown keys.
+            The schema is ravnkey too; '* { binary { path required } arguments { string } }'.",
+        )
+        .flag(
+            "--find",
+            "[path]... [expression]; find files by their metadata, all the arguments after it are for it.
+            Each match is shown as ravnkey with its type, size, permissions, owner and times.
+            Tests; -name [glob], -iname [glob], -regex [regex] of the path,
+            -type [f|d|l|b|c|p|s], -size [+|-][number][c|w|b|k|M|G] or [from]..[to],
+            in blocks of 512 bytes by default and rounded up to the unit as find, '-size -1M' is only empty files,
+            -mtime and -atime [+|-][number][s|m|h|d|w] of age, days by default and in whole units as find, '+1' is 2 days or more,
+            -user [name|id], -group [name|id], -perm [644|-644|/022|rw-r--r--].
+            Tests together must all be true; '-o' is OR, '!' is NOT and '(' ')' group them.
+            Actions; -print to show the path, -exec [command] {} ; to run it with the path, true if it ends well.
+            They are part of the expression, '-name *.o -exec rm {} ; -o -print', without them each match is shown.
+            Options, also before the paths; -maxdepth [number], -mindepth [number], -L to follow symbolic links, --json.",
+        )
+        .positional("String", "string, regular expression, key path or first file, by the options.")
+        .many("path", "where to search.")
+}
 
 fn main() {
     // env::args() takes program's arguments (the first is always the self binary).
@@ -40,65 +310,131 @@ fn main() {
     // Remove the binary name from vector (remove the data from index zero).
     arguments.remove(0);
 
-    // Check if some arguments are help
-    if arguments.checkarguments_help("search") {
-        process::exit(0);
+    // Everything after "--find" is its expression, which has its own options
+    if let Some(i) = arguments.iter().position(|e| e == "--find") {
+        let tokens = arguments.split_off(i + 1);
+        arguments.pop();
+        if arguments.iter().chain(tokens.iter()).any(|e| e == "-h" || e == "--help") {
+            print!("{}", spec().help());
+            process::exit(0);
+        }
+        let json_output = arguments.iter().any(|e| e == "--json");
+        process::exit(find::find(tokens, json_output));
     }
 
+    // Prints the help or the usage error and ends if it is needed
+    let matches = spec().parse_or_exit(&arguments);
+
     // Configuration struct
     let mut inst1 = libconfarg::SearchConfiguration {
-        file: false,
-        binary: false,
-        directory: false,
-        environment: false,
-        processes: false,
-        recursive: false,
-        input: false,
-        ravnkey: false,
+        file: matches.flag("-f"),
+        binary: matches.flag("-b"),
+        directory: matches.flag("-d"),
+        environment: matches.flag("-e"),
+        processes: matches.flag("-p"),
+        recursive: matches.flag("-r"),
+        input: matches.flag("-s"),
+        ravnkey: matches.flag("-k"),
+        ravnkey_output: matches.flag("--ravnkey"),
+        check: matches.flag("--check"),
+        schema: matches.flag("--schema"),
+        json_output: matches.flag("--json"),
+        regex: matches.flag("-E"),
+        line_number: matches.flag("-n"),
+        count: matches.flag("-c"),
+        invert: matches.flag("-v"),
+        files_with_matches: matches.flag("-l"),
+        word: matches.flag("-w"),
+        ignore_case: matches.flag("-i"),
+        color: matches.flag("--color"),
+        before_context: 0,
+        after_context: 0,
+        max_count: matches.get("-m"),
+        sort: matches.flag("--sort"),
+        text: matches.flag("-a"),
+        no_ignore: matches.flag("--no-ignore"),
+        threads: matches.get("-j"),
+        offsets: matches.flag("--offsets"),
+        hex: matches.flag("--hex"),
     };
+    if let Some(d) = matches.get("-C") {
+        (inst1.before_context, inst1.after_context) = (d, d);
+    }
+    if let Some(d) = matches.get("-A") {
+        inst1.after_context = d;
+    }
+    if let Some(d) = matches.get("-B") {
+        inst1.before_context = d;
+    }
+    if inst1.threads == Some(0) {
+        eprintln!("Option -j needs at least one thread");
+        process::exit(1);
+    }
 
-    // Vector to store options
-    let mut options: Vec<&str> = Vec::new();
-    let mut inputs: Vec<String> = arguments.check_arguments("search", &mut options);
-    if inputs.is_empty() {
-        process::exit(0);
-    }
-    // String to search must be the first position (0)
-    let ssearch: String = inputs[0].clone();
-    // Delete the string to search from the vector because can be more than just 1 file in which search.
-    inputs.remove(0);
-
-    // Check arguments
-    for confs in options {
-        if confs == "file" {
-            inst1.file = true;
-        } else if confs == "directory" {
-            inst1.directory = true;
-        } else if confs == "environment" {
-            inst1.environment = true;
-        } else if confs == "proc" {
-            inst1.processes = true;
-        } else if confs == "recursive" {
-            inst1.recursive = true;
-        } else if confs == "input" {
-            inst1.input = true;
-        } else if confs == "ravnkey" {
-            inst1.ravnkey = true;
-        } else if confs == "binary" {
-            inst1.binary = true;
+    // String to search, the rest are where to search it
+    let ssearch: String = matches.positional("String").unwrap_or_default().to_string();
+    let inputs: Vec<String> = matches
+        .positional_values("path")
+        .into_iter()
+        .map(|e| e.to_string())
+        .collect();
+
+    let pattern = match Pattern::new(&ssearch, &inst1) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error in regular expression '{ssearch}'; {e}");
+            process::exit(1);
         }
-    }
+    };
 
     // Search inside bynary
     if inst1.binary {
+        let bytes = if inst1.hex {
+            match BytePattern::parse_hex(&ssearch) {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("Error in hexadecimal pattern '{ssearch}'; {e}");
+                    process::exit(1);
+                }
+            }
+        } else {
+            BytePattern::new(ssearch.as_bytes())
+        };
+        // With "--offsets" the output is ravnkey; "[file] { [offset] }", or JSON; {"file": [file], "offset": [offset]}
+        let mut writer = RavnWriter::new(std::io::stdout().lock());
+        let mut found: Vec<JsonValue> = Vec::new();
         for ffiles in &inputs {
-            let file = File::open(ffiles.clone()).expect("Fail opening file; {filename}");
-            match binary_search( &ffiles, file, ssearch.clone()) {
-                Ok(_d) => println!("{}; matches", ffiles),
-                Err(e) => println!("{}; {e}",ffiles),
+            let file = match File::open(ffiles) {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("{ffiles}; {e}");
+                    continue;
+                }
+            };
+            let mut matches = ByteSearch::new(file, bytes.clone());
+            if !inst1.offsets {
+                match matches.next() {
+                    Some(Ok(_d)) => println!("{}; matches", ffiles),
+                    Some(Err(e)) => eprintln!("{ffiles}; {e}"),
+                    None => println!("{}; Not found in binary", ffiles),
+                }
+                continue;
+            }
+            for offset in matches {
+                match offset {
+                    Ok(d) if inst1.json_output => {
+                        found.push(record(vec![("file", ffiles.as_str().into()), ("offset", (d as i64).into())]));
+                    }
+                    Ok(d) => {
+                        let _ = writer.text(ffiles, &d.to_string());
+                    }
+                    Err(e) => eprintln!("{ffiles}; {e}"),
+                }
             }
         }
-
+        if inst1.json_output && inst1.offsets {
+            print_json(found);
+        }
     }
 
     // Search for [key] and extract [data]
@@ -107,65 +443,127 @@ fn main() {
         let stdinvar = std::io::stdin();
         let mut locking = stdinvar.lock();
 
-        // Shadowing
-        let ssearch = ssearch.as_str();
-
         locking
             .read_to_end(&mut stdin_buffer)
             .expect("Error reading stdin.");
         let string: &str =
             std::str::from_utf8(&stdin_buffer).expect("Error converting input to UTF-8 strings.");
-        let hmap = string.to_string().readkey();
+        let document = match parse_ravnkey(string) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error parsing ravnkey; {e}");
+                process::exit(1);
+            }
+        };
 
-        match hmap.get(ssearch) {
-            Some(e) => println!("{e}"),
-            None => eprintln!("Not found"),
+        // Each argument is a query, all are applied to the same document
+        let mut found = false;
+        let mut results: Vec<JsonValue> = Vec::new();
+        let mut writer = RavnWriter::new(std::io::stdout().lock());
+        for squery in std::iter::once(&ssearch).chain(inputs.iter()) {
+            let query = match RavnQuery::parse(squery) {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("Error in query '{squery}'; {e}");
+                    process::exit(1);
+                }
+            };
+            for (path, value) in query.select(&document) {
+                found = true;
+                if inst1.json_output {
+                    results.push(record(vec![("path", path.into()), ("value", ravnkey_to_json(value))]));
+                    continue;
+                }
+                // Text is printed as is, blocks and lists as ravnkey
+                match (inst1.ravnkey_output, value) {
+                    (true, _) => {
+                        let _ = writer.value(&path, value);
+                    }
+                    (false, RavnValue::Text(e)) => println!("{e}"),
+                    (false, _) => print!("{value}"),
+                }
+            }
+        }
+        if inst1.json_output {
+            print_json(results);
+        }
+        if !found {
+            eprintln!("Not found");
+            process::exit(1);
         }
     }
 
-    // Search in stdin
-    if inst1.input {
-        // Where to save data
-        let mut stdin_buffer: Vec<u8> = Vec::new();
-        // Stdinput control
-        let stdinvar = std::io::stdin();
-        // Block stdinput to control it
-        let mut locking = stdinvar.lock();
-
-        // Take in consideration this; read_to_end saves data in u8
-        locking
-            .read_to_end(&mut stdin_buffer)
-            .expect("Error reading stdin.");
-
-        // Transform u8 (integers 8 bytes) to strings
-        let strings =
-            std::str::from_utf8(&stdin_buffer).expect("Error converting input to UTF-8 strings.");
+    // Lint ravnkey files, only the syntax or also with a schema
+    if inst1.check || inst1.schema {
+        let (schema, files) = if inst1.schema {
+            let mut text = String::new();
+            if let Err(e) = File::open(&ssearch).and_then(|mut f| f.read_to_string(&mut text)) {
+                eprintln!("Error reading schema {ssearch}; {e}");
+                process::exit(1);
+            }
+            match RavnSchema::parse(&text) {
+                Ok(d) => (d, inputs.clone()),
+                Err(e) => {
+                    eprintln!("Error in schema {ssearch}; {e}");
+                    process::exit(1);
+                }
+            }
+        } else {
+            (RavnSchema::new().open(), std::iter::once(ssearch.clone()).chain(inputs.iter().cloned()).collect())
+        };
 
-        // Read stdin_buffer and search in each line
-        for inl in strings.lines() {
-            if inl.contains(&ssearch) {
-                println!("{inl}");
+        let mut failed = false;
+        let mut results: Vec<JsonValue> = Vec::new();
+        let mut writer = RavnWriter::new(std::io::stdout().lock());
+        for file in &files {
+            let mut text = String::new();
+            if let Err(e) = File::open(file).and_then(|mut f| f.read_to_string(&mut text)) {
+                eprintln!("Error reading {file}; {e}");
+                failed = true;
+                continue;
+            }
+            for diagnostic in schema.check(&text).diagnostics {
+                failed |= diagnostic.severity == Severity::Error;
+                let severity = if diagnostic.severity == Severity::Error { "error" } else { "warning" };
+                if inst1.json_output {
+                    results.push(record(vec![
+                        ("file", file.as_str().into()),
+                        ("line", (diagnostic.line as i64).into()),
+                        ("column", (diagnostic.column as i64).into()),
+                        ("severity", severity.into()),
+                        ("path", diagnostic.path.into()),
+                        ("message", diagnostic.message.into()),
+                    ]));
+                } else if inst1.ravnkey_output {
+                    let mut record = RavnValue::Block(Vec::new());
+                    record.push("line", diagnostic.line.to_string().into());
+                    record.push("column", diagnostic.column.to_string().into());
+                    record.push("severity", severity.into());
+                    record.push("path", diagnostic.path.into());
+                    record.push("message", diagnostic.message.into());
+                    let _ = writer.value(file, &record);
+                } else {
+                    println!("{file}; {diagnostic}");
+                }
             }
         }
+        if inst1.json_output {
+            print_json(results);
+        }
+        if failed {
+            process::exit(1);
+        }
+    }
+
+    // Search in stdin, line by line
+    if inst1.input {
+        let stdinvar = std::io::stdin();
+        print_lines("stdin", false, pattern.lines(stdinvar.lock(), &inst1), &pattern, &inst1, &mut false);
     }
 
     // Search recursively
     if inst1.recursive {
-        let results = inputs[0].readdir_recursive();
-        // Each struct field is Vec<String> so, we must use an iterator over them
-
-        // Dir
-        for dir in &results.dbuff {
-            if dir.contains(&ssearch) {
-                println!("d; {dir}");
-            }
-        }
-        // Files
-        for files in &results.fbuff {
-            for outputs in file_filter(&files, ssearch.clone()) {
-                println!("f; {files}\n\n {outputs}\n\n");
-            }
-        }
+        recursive::search_recursive(&pattern, &inputs, &inst1);
     }
 
     // Start to check and work.
@@ -176,43 +574,60 @@ fn main() {
         // Iterate over each string
         for procs in buffer1 {
             // Check if that string contains the search string
-            if procs.contains(&ssearch) {
+            if pattern.is_match(&procs) != inst1.invert {
                 println!("{procs}");
             }
         }
     }
 
     if inst1.file {
+        let mut shown = false;
         for files in &inputs {
-            for outputs in file_filter(&files, ssearch.clone()) {
-                if inputs.len() > 1 {
-                    println!("{files}: {outputs}\n");
-                } else {
-                    println!("{outputs}");
+            match pattern.file_lines(files, &inst1) {
+                Ok(d) => {
+                    print_lines(files, inputs.len() > 1, d, &pattern, &inst1, &mut shown);
                 }
+                Err(e) => eprintln!("{files}; {e}"),
             }
         }
     }
 
     if inst1.environment {
+        let mut found: Vec<JsonValue> = Vec::new();
         for strings in &inputs {
             let data = match env::var(strings) {
                 Ok(value) => value,
                 Err(_e) => "".to_string(),
             };
-            if data.contains(&ssearch) {
-                println!("{strings}: {data}");
+            if pattern.is_match(&data) != inst1.invert {
+                if inst1.json_output {
+                    found.push(record(vec![("name", strings.as_str().into()), ("value", data.into())]));
+                } else {
+                    let _ = RavnWriter::new(std::io::stdout().lock()).text(strings, &data);
+                }
             }
         }
+        if inst1.json_output {
+            print_json(found);
+        }
     }
 
 
     if inst1.directory {
         for files in &inputs {
-            let output = files.readdir_recursive();
-            //output.sort();
-            for archives in output.dbuff {
-                if archives.as_str().contains(&ssearch) {
+            for entry in Walker::new(files).sorted(true) {
+                let entry = match entry {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        continue;
+                    }
+                };
+                if entry.depth == 0 || !entry.is_dir() {
+                    continue;
+                }
+                let archives = entry.path.display().to_string();
+                if pattern.is_match(&archives) != inst1.invert {
                     println!("{}", archives.as_str());
                 }
             }
@@ -0,0 +1,276 @@
+//! This file is part of RavnOS.
+//!
+//! RavnOS is free software:
+//! you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation,
+//! either version 3 of the License, or (at your option) any later version.
+//!
+//! RavnOS is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+//! without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//!
+//!  You should have received a copy of the GNU General Public License along with RavnOS. If not, see <https://www.gnu.org/licenses/>
+
+//!
+//! Copyright; Joaquin "ShyanJMC" Crespo - 2022-2023-2024
+
+//! "search -r", the files are read by a pool of threads.
+//!
+//! One thread walks the directories and sends the files to the pool, each worker searches a whole file and sends
+//! back what it found. The main thread prints the results, as they come or, with "--sort", in the order of the walk;
+//! each entry has a number and the ones which come early wait for the ones before them.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use libconfarg::SearchConfiguration;
+use libstream::{is_binary_file, JsonValue, LineMatch, RavnWriter, Walker};
+
+use crate::ignore::Ignores;
+use crate::{print_json, record, Pattern};
+
+// Files waiting for a worker, by thread
+const QUEUE_BY_THREAD: usize = 64;
+
+// What was found in one entry of the walk
+enum Found {
+    // Directory whose name matches
+    Directory(String),
+    File {
+        path: String,
+        lines: Vec<LineMatch>,
+        count: u64,
+        // Error reading after some lines
+        error: Option<io::Error>,
+    },
+    // Nothing to show, as a binary file, it only keeps its place in the order
+    Nothing,
+    Error(String),
+}
+
+/// Searches the pattern in the names of the directories and the content of the files under each input.
+pub fn search_recursive(pattern: &Pattern, inputs: &[String], config: &SearchConfiguration) {
+    let threads = config
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |e| e.get()));
+    let (jobs_tx, jobs_rx) = mpsc::sync_channel::<(usize, String)>(threads * QUEUE_BY_THREAD);
+    let jobs_rx = Mutex::new(jobs_rx);
+    let (results_tx, results_rx) = mpsc::channel::<(usize, Found)>();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let results_tx = results_tx.clone();
+            // Each worker has its own copy, the regular expressions are faster without sharing
+            let pattern = pattern.clone();
+            let jobs_rx = &jobs_rx;
+            scope.spawn(move || loop {
+                let job = match jobs_rx.lock() {
+                    Ok(d) => d.recv(),
+                    Err(_e) => break,
+                };
+                let (index, path) = match job {
+                    Ok(d) => d,
+                    // No more files
+                    Err(_e) => break,
+                };
+                if results_tx.send((index, search_file(path, &pattern, config))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        let walk_results = results_tx.clone();
+        scope.spawn(move || walk(inputs, pattern, config, jobs_tx, walk_results));
+        // The results end when the walker and all the workers are done
+        drop(results_tx);
+        print_results(results_rx, config);
+    });
+}
+
+// Walks the inputs, the files go to the workers and the rest straight to the results
+fn walk(
+    inputs: &[String],
+    pattern: &Pattern,
+    config: &SearchConfiguration,
+    jobs: mpsc::SyncSender<(usize, String)>,
+    results: Sender<(usize, Found)>,
+) {
+    let mut index = 0;
+    let send = |found: Found, index: &mut usize| {
+        let _ = results.send((*index, found));
+        *index += 1;
+    };
+    for input in inputs {
+        let mut ignores = Ignores::new(!config.no_ignore);
+        let mut walker = Walker::new(input).sorted(true);
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(d) => d,
+                Err(e) => {
+                    send(Found::Error(e.to_string()), &mut index);
+                    continue;
+                }
+            };
+            if ignores.is_ignored(&entry) {
+                walker.skip_dir();
+                continue;
+            }
+            let path = entry.path.display().to_string();
+            if entry.is_dir() {
+                if let Err(e) = ignores.enter(&entry.path, entry.depth) {
+                    send(Found::Error(e), &mut index);
+                }
+                // The name of the input is not a result
+                if entry.depth > 0 && pattern.is_match(&path) != config.invert {
+                    send(Found::Directory(path), &mut index);
+                }
+            } else if entry.symlink && entry.path.is_dir() {
+                // Links to directories are not entered, only their name is checked
+                if pattern.is_match(&path) != config.invert {
+                    send(Found::Directory(path), &mut index);
+                }
+            } else if entry.file_type().is_file() || (entry.symlink && entry.path.is_file()) {
+                // Devices, pipes and sockets are not read, they can block
+                if jobs.send((index, path)).is_err() {
+                    return;
+                }
+                index += 1;
+            }
+        }
+    }
+}
+
+// Lines of one file, only what will be shown is kept
+fn search_file(path: String, pattern: &Pattern, config: &SearchConfiguration) -> Found {
+    let mut file = match File::open(&path) {
+        Ok(d) => d,
+        Err(e) => return Found::Error(format!("{path}; {e}")),
+    };
+    let (binary, block) = match is_binary_file(&mut file) {
+        Ok(d) => d,
+        Err(e) => return Found::Error(format!("{path}; {e}")),
+    };
+    if binary && !config.text {
+        return Found::Nothing;
+    }
+
+    let reader = BufReader::new(io::Cursor::new(block).chain(file));
+    let mut lines = Vec::new();
+    let mut count: u64 = 0;
+    let mut error = None;
+    for line in pattern.lines(reader, config) {
+        let line = match line {
+            Ok(d) => d,
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        };
+        if !line.context {
+            count += 1;
+        }
+        if config.files_with_matches && count > 0 {
+            break;
+        }
+        if !config.files_with_matches && !config.count {
+            lines.push(line);
+        }
+    }
+    Found::File {
+        path,
+        lines,
+        count,
+        error,
+    }
+}
+
+// Output is ravnkey; "directory { [path] }" and "[file] { [line] }"
+// With "--json"; {"directory": [path]} and {"file": [file], "line": [line]}
+fn print_results(results: Receiver<(usize, Found)>, config: &SearchConfiguration) {
+    let mut writer = RavnWriter::new(io::stdout().lock());
+    let mut json: Vec<JsonValue> = Vec::new();
+    let mut print = |found: Found| print_found(found, &mut writer, &mut json, config);
+
+    if config.sort {
+        let mut waiting: BTreeMap<usize, Found> = BTreeMap::new();
+        let mut next = 0;
+        for (index, found) in results {
+            waiting.insert(index, found);
+            while let Some(d) = waiting.remove(&next) {
+                print(d);
+                next += 1;
+            }
+        }
+    } else {
+        for (_index, found) in results {
+            print(found);
+        }
+    }
+
+    if config.json_output {
+        print_json(json);
+    }
+}
+
+fn print_found<W: io::Write>(found: Found, writer: &mut RavnWriter<W>, json: &mut Vec<JsonValue>, config: &SearchConfiguration) {
+    let (path, lines, count, error) = match found {
+        Found::Directory(d) => {
+            if config.json_output {
+                json.push(record(vec![("directory", d.as_str().into())]));
+            } else {
+                let _ = writer.text("directory", &d);
+            }
+            return;
+        }
+        Found::Nothing => return,
+        Found::Error(e) => {
+            eprintln!("{e}");
+            return;
+        }
+        Found::File {
+            path,
+            lines,
+            count,
+            error,
+        } => (path, lines, count, error),
+    };
+
+    for line in lines {
+        if config.json_output {
+            let mut fields = vec![
+                ("file", path.as_str().into()),
+                ("number", (line.number as i64).into()),
+                ("offset", (line.offset as i64).into()),
+                ("line", line.text().into_owned().into()),
+            ];
+            if line.context {
+                fields.push(("context", true.into()));
+            }
+            json.push(record(fields));
+        } else if config.line_number {
+            let separator = if line.context { "-" } else { ":" };
+            let _ = writer.text(&path, &format!("{}{separator}{}", line.number, line.text()));
+        } else {
+            let _ = writer.text(&path, &line.text());
+        }
+    }
+    if let Some(e) = error {
+        eprintln!("{path}; {e}");
+    }
+    // "file { [path] }" with "-l" and "[file] { [count] }" with "-c"
+    if config.files_with_matches && count > 0 {
+        if config.json_output {
+            json.push(record(vec![("file", path.as_str().into())]));
+        } else {
+            let _ = writer.text("file", &path);
+        }
+    } else if config.count {
+        if config.json_output {
+            json.push(record(vec![("file", path.as_str().into()), ("count", (count as i64).into())]));
+        } else {
+            let _ = writer.text(&path, &count.to_string());
+        }
+    }
+}